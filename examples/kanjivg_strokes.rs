@@ -0,0 +1,76 @@
+//! Writes `assets/kana_strokes.txt` from KanjiVG.
+//!
+//! KanjiVG (https://kanjivg.tagaini.net) by Ulrich Apel is licensed under
+//! CC BY-SA 3.0, and so is the file this makes. Unpack the `kanji` folder of
+//! a release (kanjivg-YYYYMMDD-main.zip) and run
+//!
+//!     cargo run --example kanjivg_strokes -- path/to/kanji > assets/kana_strokes.txt
+//!
+//! The paths of a file are in stroke order, so they are copied as they are.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Hiragana ぁ..ゖ and katakana ァ..ヺ.
+const RANGES: [(u32, u32); 2] = [(0x3041, 0x3096), (0x30A1, 0x30FA)];
+
+fn main() -> ExitCode {
+    let Some(dir) = env::args().nth(1) else {
+        eprintln!("usage: kanjivg_strokes <kanjivg kanji folder>");
+        return ExitCode::FAILURE;
+    };
+    let dir = Path::new(&dir);
+
+    println!("# Stroke order of the kana from KanjiVG (https://kanjivg.tagaini.net),");
+    println!("# Copyright (C) Ulrich Apel, licensed under CC BY-SA 3.0");
+    println!("# (https://creativecommons.org/licenses/by-sa/3.0/). This file is");
+    println!("# shared under the same license.");
+    println!("# Made by examples/kanjivg_strokes.rs, do not edit by hand. One kana per");
+    println!("# line: the character, then its strokes in writing order separated by");
+    println!("# \"|\", each an SVG path in KanjiVG's 109x109 box.");
+
+    let mut missing = vec![];
+    for kana in RANGES
+        .iter()
+        .flat_map(|(from, to)| *from..=*to)
+        .filter_map(char::from_u32)
+    {
+        let file = dir.join(format!("{:05x}.svg", kana as u32));
+        match fs::read_to_string(&file) {
+            Ok(svg) => {
+                let paths = stroke_paths(&svg);
+                if paths.is_empty() {
+                    missing.push(kana);
+                } else {
+                    println!("{} {}", kana, paths.join(" | "));
+                }
+            }
+            Err(_) => missing.push(kana),
+        }
+    }
+    if !missing.is_empty() {
+        eprintln!("no strokes for: {}", missing.iter().collect::<String>());
+    }
+    ExitCode::SUCCESS
+}
+
+/// `d` of every path, in the order of the file. The `kvg:` namespace is
+/// only declared in the DTD of the file, so it is scanned rather than parsed
+/// as XML.
+fn stroke_paths(svg: &str) -> Vec<String> {
+    let mut paths = vec![];
+    for element in svg.split("<path").skip(1) {
+        let Some(start) = element.find(" d=\"") else {
+            continue;
+        };
+        let data = &element[start + 4..];
+        let Some(end) = data.find('"') else {
+            continue;
+        };
+        // a path is one line of the output
+        paths.push(data[..end].split_whitespace().collect::<Vec<&str>>().join(" "));
+    }
+    paths
+}
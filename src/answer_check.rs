@@ -0,0 +1,289 @@
+use crate::dictionary::split_with_coma;
+use crate::kana::{to_hiragana, to_katakana};
+use crate::kanji::is_kanji;
+use serde::{Deserialize, Serialize};
+
+/// Half-width katakana U+FF61..=U+FF9F in code point order.
+const HALF_WIDTH: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+const VOICED: &str = "カキクケコサシスセソタチツテトハヒフヘホ";
+const SEMI_VOICED: &str = "ハヒフヘホ";
+
+/// Key of the rules in the settings table.
+pub const ANSWER_RULES: &str = "answer_rules";
+
+/// Which differences between the typed and the expected answer are forgiven.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnswerRules {
+    /// Ｆｕｌｌ-width Latin and ﾊﾝｶｸ katakana count as the usual forms.
+    pub fold_width: bool,
+    /// Hiragana and katakana are interchangeable.
+    pub kana_equivalence: bool,
+    /// ー equals the vowel it lengthens: こーひー is こうひい.
+    pub long_vowels: bool,
+    /// Notes in brackets are optional: "(to) eat" accepts "eat".
+    pub strip_notes: bool,
+    /// A typo or two in a long non-Japanese answer.
+    pub typo_tolerance: bool,
+}
+
+impl Default for AnswerRules {
+    fn default() -> Self {
+        Self {
+            fold_width: true,
+            kana_equivalence: false,
+            long_vowels: true,
+            strip_notes: true,
+            typo_tolerance: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnswerMatch {
+    Exact,
+    /// Accepted thanks to one of the rules; the exact spelling is worth showing.
+    Close,
+    Wrong,
+}
+
+/// Checks the answer against the whole expected text and each of its comma
+/// separated alternatives.
+pub fn check_answer(answer: &str, correct: &str, rules: &AnswerRules) -> AnswerMatch {
+    let alternatives = split_with_coma(correct);
+    if answer == correct || alternatives.iter().any(|a| a == answer) {
+        return AnswerMatch::Exact;
+    }
+
+    let answer = normalize(answer, rules);
+    if answer.is_empty() {
+        return AnswerMatch::Wrong;
+    }
+    let close = std::iter::once(correct.to_string())
+        .chain(alternatives)
+        .map(|expected| normalize(&expected, rules))
+        .any(|expected| {
+            expected == answer || (rules.typo_tolerance && is_typo(&answer, &expected))
+        });
+    if close {
+        AnswerMatch::Close
+    } else {
+        AnswerMatch::Wrong
+    }
+}
+
+pub fn normalize(text: &str, rules: &AnswerRules) -> String {
+    let mut text = text.to_string();
+    if rules.fold_width {
+        text = fold_width(&text);
+    }
+    if rules.strip_notes {
+        text = strip_notes(&text);
+    }
+    if rules.kana_equivalence {
+        text = to_hiragana(&text);
+    }
+    if rules.long_vowels {
+        text = expand_long_vowels(&text);
+    }
+    text.to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn fold_width(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.chars() {
+        match c as u32 {
+            0xFF01..=0xFF5E => result.extend(char::from_u32(c as u32 - 0xFEE0)),
+            0x3000 => result.push(' '),
+            0xFF61..=0xFF9F => {
+                let full = HALF_WIDTH.chars().nth((c as u32 - 0xFF61) as usize).unwrap();
+                // the voicing marks are separate characters in half-width
+                let last = result.chars().last();
+                match (full, last) {
+                    ('゛', Some('ウ')) => replace_last(&mut result, 'ヴ'),
+                    ('゛', Some(base)) if VOICED.contains(base) => {
+                        replace_last(&mut result, shifted(base, 1))
+                    }
+                    ('゜', Some(base)) if SEMI_VOICED.contains(base) => {
+                        replace_last(&mut result, shifted(base, 2))
+                    }
+                    _ => result.push(full),
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn shifted(c: char, by: u32) -> char {
+    char::from_u32(c as u32 + by).unwrap_or(c)
+}
+
+fn replace_last(text: &mut String, c: char) {
+    text.pop();
+    text.push(c);
+}
+
+fn strip_notes(text: &str) -> String {
+    let mut result = String::new();
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '(' | '[' | '（' | '【' => depth += 1,
+            ')' | ']' | '）' | '】' => depth = (depth - 1).max(0),
+            _ if depth == 0 => result.push(c),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Replaces ー with the kana it stands for, as long vowels are usually
+/// spelled: あ, い, う, い after e and う after o.
+fn expand_long_vowels(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.chars() {
+        if c != 'ー' {
+            result.push(c);
+            continue;
+        }
+        let vowel = result.chars().last().and_then(vowel_of);
+        match vowel {
+            Some(vowel) => result.push(vowel),
+            None => result.push(c),
+        }
+    }
+    result
+}
+
+fn vowel_of(kana: char) -> Option<char> {
+    const ROWS: [(&str, char); 5] = [
+        ("あかさたなはまやらわがざだばぱぁゃゎ", 'あ'),
+        ("いきしちにひみりぎじぢびぴぃ", 'い'),
+        ("うくすつぬふむゆるぐずづぶぷぅゅゔ", 'う'),
+        ("えけせてねへめれげぜでべぺぇ", 'い'),
+        ("おこそとのほもよろをごぞどぼぽぉょ", 'う'),
+    ];
+    let hiragana = to_hiragana(&kana.to_string()).chars().next()?;
+    let vowel = ROWS
+        .iter()
+        .find(|(row, _)| row.contains(hiragana))
+        .map(|(_, vowel)| *vowel)?;
+    if hiragana == kana {
+        Some(vowel)
+    } else {
+        to_katakana(&vowel.to_string()).chars().next()
+    }
+}
+
+/// Small Levenshtein distance on long answers in an alphabet: 1 from five
+/// letters, 2 from ten. Japanese answers have to be spelled exactly.
+fn is_typo(answer: &str, expected: &str) -> bool {
+    let japanese = |text: &str| {
+        text.chars()
+            .any(|c| matches!(c, 'ぁ'..='ゖ' | 'ァ'..='ヺ' | 'ー') || is_kanji(c))
+    };
+    if japanese(answer) || japanese(expected) {
+        return false;
+    }
+    let length = expected.chars().count();
+    let allowed = match length {
+        0..5 => return false,
+        5..10 => 1,
+        _ => 2,
+    };
+    levenshtein(answer, expected) <= allowed
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(answer: &str, correct: &str) -> AnswerMatch {
+        check_answer(answer, correct, &AnswerRules::default())
+    }
+
+    #[test]
+    fn whole_text_and_alternatives_are_exact() {
+        assert_eq!(check("cat, kitty", "cat, kitty"), AnswerMatch::Exact);
+        assert_eq!(check("cat", "cat, kitty"), AnswerMatch::Exact);
+        assert_eq!(check("kitty", "cat, kitty"), AnswerMatch::Exact);
+        assert_eq!(check("", "cat"), AnswerMatch::Wrong);
+    }
+
+    #[test]
+    fn rules_make_an_answer_close() {
+        assert_eq!(check("Cat ", "cat"), AnswerMatch::Close);
+        assert_eq!(check("eat", "(to) eat"), AnswerMatch::Close);
+        assert_eq!(check("ｃａｔ", "cat"), AnswerMatch::Close);
+        assert_eq!(check("ｶﾞｯｺｳ", "ガッコウ"), AnswerMatch::Close);
+        assert_eq!(check("コウヒイ", "コーヒー"), AnswerMatch::Close);
+    }
+
+    #[test]
+    fn kana_equivalence_is_optional() {
+        assert_eq!(check("ねこ", "ネコ"), AnswerMatch::Wrong);
+        let rules = AnswerRules {
+            kana_equivalence: true,
+            ..AnswerRules::default()
+        };
+        assert_eq!(check_answer("ねこ", "ネコ", &rules), AnswerMatch::Close);
+        assert_eq!(check_answer("こうひい", "コーヒー", &rules), AnswerMatch::Close);
+    }
+
+    #[test]
+    fn typos_are_forgiven_by_length() {
+        // under five letters nothing is forgiven
+        assert_eq!(check("bord", "bird"), AnswerMatch::Wrong);
+        // five to nine letters: one
+        assert_eq!(check("hovse", "house"), AnswerMatch::Close);
+        assert_eq!(check("hovze", "house"), AnswerMatch::Wrong);
+        assert_eq!(check("chocolote", "chocolate"), AnswerMatch::Close);
+        assert_eq!(check("chokolote", "chocolate"), AnswerMatch::Wrong);
+        // ten and more: two
+        assert_eq!(check("baskatbull", "basketball"), AnswerMatch::Close);
+        assert_eq!(check("boskatbull", "basketball"), AnswerMatch::Wrong);
+    }
+
+    #[test]
+    fn japanese_and_disabled_typos_need_the_exact_spelling() {
+        assert_eq!(check("たべもん", "たべもの"), AnswerMatch::Wrong);
+        let rules = AnswerRules {
+            typo_tolerance: false,
+            ..AnswerRules::default()
+        };
+        assert_eq!(check_answer("hovse", "house", &rules), AnswerMatch::Wrong);
+    }
+
+    #[test]
+    fn normalize_applies_only_the_chosen_rules() {
+        let none = AnswerRules {
+            fold_width: false,
+            kana_equivalence: false,
+            long_vowels: false,
+            strip_notes: false,
+            typo_tolerance: false,
+        };
+        assert_eq!(normalize("  To  (be) ＯＫ ", &none), "to (be) ｏｋ");
+        assert_eq!(normalize("  To  (be) ＯＫ ", &AnswerRules::default()), "to ok");
+        assert_eq!(normalize("ラーメン", &AnswerRules::default()), "ラアメン");
+    }
+}
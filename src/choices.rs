@@ -0,0 +1,198 @@
+use crate::data_provider::word_confusions::save_word_confusion;
+use crate::dictionary::split_with_coma;
+use crate::kana::is_kana;
+use crate::kanji::is_kanji;
+use crate::lang::WordData;
+use crate::AppState;
+use iced::widget::{button, Column};
+use iced::{Element, Fill};
+use rand::random_range;
+use std::collections::{HashMap, HashSet};
+
+/// How many times each word (first id) was answered with another one (second id).
+pub type WordConfusions = HashMap<(u32, u32), u32>;
+
+/// A card answered by picking one of several options.
+#[derive(Clone, Debug)]
+pub struct Choice {
+    /// Word id and the text shown on the button.
+    pub options: Vec<(u32, String)>,
+    pub correct: usize,
+    pub picked: Option<usize>,
+}
+
+impl Choice {
+    /// Options for `target` with distractors from `pool`. `answer` is the
+    /// side of the card the options show. `None` when nothing can be offered
+    /// besides the answer itself.
+    pub fn new(
+        target: &WordData,
+        pool: &[WordData],
+        answer: impl Fn(&WordData) -> String,
+        confusions: &WordConfusions,
+    ) -> Option<Choice> {
+        let correct_text = answer(target);
+        let candidates = pool
+            .iter()
+            .filter(|w| w.id != target.id)
+            .map(|w| (w, answer(w)))
+            .filter(|(_, text)| !text.is_empty() && *text != correct_text)
+            .collect::<Vec<(&WordData, String)>>();
+        let count = option_count(pool.len()).min(candidates.len() + 1);
+        if count < 2 {
+            return None;
+        }
+
+        let mut scored = candidates
+            .into_iter()
+            .map(|(word, text)| {
+                let score = similarity(target, &correct_text, word, &text, confusions);
+                // a little noise so the same distractors don't come up every time
+                (score + random_range(0.0..1.5), word.id, text)
+            })
+            .collect::<Vec<(f32, u32, String)>>();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut seen = HashSet::from([correct_text.clone()]);
+        let mut options = scored
+            .into_iter()
+            .filter(|(_, _, text)| seen.insert(text.clone()))
+            .take(count - 1)
+            .map(|(_, id, text)| (id, text))
+            .collect::<Vec<(u32, String)>>();
+        let correct = random_range(0..=options.len());
+        options.insert(correct, (target.id, correct_text));
+
+        Some(Choice {
+            options,
+            correct,
+            picked: None,
+        })
+    }
+
+    /// Marks the option and tells whether it was the right one.
+    pub fn pick(&mut self, index: usize) -> bool {
+        self.picked = Some(index);
+        index == self.correct
+    }
+
+    /// Id of the word picked instead of the right one.
+    pub fn confused_with(&self) -> Option<u32> {
+        let picked = self.picked?;
+        if picked == self.correct {
+            return None;
+        }
+        self.options.get(picked).map(|(id, _)| *id)
+    }
+
+    /// The options as buttons; once one is picked the right one is green and
+    /// a wrong pick red.
+    pub fn view<'a, M: Clone + 'a>(&self, on_pick: fn(usize) -> M) -> Element<'a, M> {
+        let mut column = Column::new().spacing(5).width(300);
+        for (i, (_, text)) in self.options.iter().enumerate() {
+            let style = match self.picked {
+                Some(_) if i == self.correct => button::success,
+                Some(picked) if picked == i => button::danger,
+                Some(_) => button::secondary,
+                None => button::primary,
+            };
+            column = column.push(
+                button(iced::widget::text!("{}. {}", i + 1, text))
+                    .width(Fill)
+                    .style(style)
+                    .on_press(on_pick(i)),
+            );
+        }
+        column.into()
+    }
+
+    /// The options with only the pick marked, for answers checked later.
+    pub fn view_unmarked<'a, M: Clone + 'a>(&self, on_pick: fn(usize) -> M) -> Element<'a, M> {
+        let mut column = Column::new().spacing(5).width(300);
+        for (i, (_, text)) in self.options.iter().enumerate() {
+            let style = if self.picked == Some(i) {
+                button::primary
+            } else {
+                button::secondary
+            };
+            column = column.push(
+                button(iced::widget::text!("{}. {}", i + 1, text))
+                    .width(Fill)
+                    .style(style)
+                    .on_press(on_pick(i)),
+            );
+        }
+        column.into()
+    }
+
+    pub fn picked_text(&self) -> String {
+        self.picked
+            .and_then(|i| self.options.get(i))
+            .map(|(_, text)| text.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// 4 options for a small pool, up to 6 for a large one.
+fn option_count(pool: usize) -> usize {
+    match pool {
+        0..20 => 4,
+        20..100 => 5,
+        _ => 6,
+    }
+}
+
+/// How good a distractor `word` is for `target`: same group and tags,
+/// an answer of similar length, shared kanji or kana, and past mix-ups.
+fn similarity(
+    target: &WordData,
+    target_text: &str,
+    word: &WordData,
+    text: &str,
+    confusions: &WordConfusions,
+) -> f32 {
+    let mut score = 0.0;
+    if word.group_id == target.group_id {
+        score += 2.0;
+    }
+
+    let tags = split_with_coma(&target.tags);
+    let shared_tags = split_with_coma(&word.tags)
+        .iter()
+        .filter(|t| tags.contains(t))
+        .count();
+    score += 1.5 * shared_tags.min(2) as f32;
+
+    let difference = target_text.chars().count().abs_diff(text.chars().count());
+    score += match difference {
+        0 => 2.0,
+        1..=2 => 1.0,
+        _ => 0.0,
+    };
+
+    let japanese = |c: &char| is_kanji(*c) || is_kana(&c.to_string());
+    let own = target.key.chars().filter(japanese).collect::<HashSet<char>>();
+    let shared = word
+        .key
+        .chars()
+        .filter(japanese)
+        .collect::<HashSet<char>>()
+        .intersection(&own)
+        .count();
+    score += shared.min(3) as f32;
+
+    let mixed_up = confusions.get(&(target.id, word.id)).unwrap_or(&0)
+        + confusions.get(&(word.id, target.id)).unwrap_or(&0);
+    score += 3.0 * mixed_up.min(3) as f32;
+    score
+}
+
+/// Remembers that word `id` was answered with word `confused_with`.
+pub(crate) fn record_word_confusion(state: &mut AppState, id: u32, confused_with: u32) {
+    if id == confused_with || id == 0 || confused_with == 0 {
+        return;
+    }
+    let count = state.word_confusions.entry((id, confused_with)).or_insert(0);
+    *count += 1;
+    save_word_confusion(id, confused_with, *count, &state.connection);
+}
@@ -0,0 +1,93 @@
+use crate::furigana::kana_reading;
+use crate::kana::is_kana;
+use crate::kanji::is_kanji;
+use crate::lang::WordData;
+use rand::random_range;
+
+/// Field of `additional` with example sentences, one per line. A translation
+/// may follow the sentence after `|`.
+pub const EXAMPLES: &str = "examples";
+const BLANK: &str = "＿＿";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Example {
+    pub sentence: String,
+    pub translation: String,
+    /// The sentence with the word blanked out.
+    pub cloze: String,
+}
+
+pub fn example_lines(word: &WordData) -> Vec<String> {
+    word.additional
+        .get(EXAMPLES)
+        .map(|text| text.split('\n').map(|l| l.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Sentences the word can be blanked out of.
+pub fn examples(word: &WordData) -> Vec<Example> {
+    example_lines(word)
+        .iter()
+        .filter_map(|line| {
+            let (sentence, translation) = line.split_once('|').unwrap_or((line, ""));
+            let sentence = sentence.trim();
+            Some(Example {
+                cloze: cloze(sentence, word)?,
+                sentence: sentence.to_string(),
+                translation: translation.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn random_example(word: &WordData) -> Option<Example> {
+    let mut list = examples(word);
+    if list.is_empty() {
+        return None;
+    }
+    Some(list.swap_remove(random_range(0..list.len())))
+}
+
+/// The sentence with the first form of the word found in it replaced by a blank.
+pub fn cloze(sentence: &str, word: &WordData) -> Option<String> {
+    forms(word).iter().find_map(|form| {
+        let start = sentence.find(form.as_str())?;
+        Some(format!(
+            "{}{}{}",
+            &sentence[..start],
+            BLANK,
+            &sentence[start + form.len()..]
+        ))
+    })
+}
+
+/// The word as written, its reading, and both without the last kana so a
+/// conjugated verb or adjective is found too: 食べ of 食べる in 食べた.
+/// Longest first.
+fn forms(word: &WordData) -> Vec<String> {
+    let mut written = vec![word.key.trim().to_string()];
+    if let Some(reading) = word.additional.get("reading") {
+        written.push(kana_reading(reading.trim()));
+    }
+
+    let mut forms = vec![];
+    for form in written.into_iter().filter(|f| !f.is_empty()) {
+        let mut chars = form.chars();
+        let last = chars.next_back();
+        let stem = chars.as_str();
+        let inflected = last.is_some_and(|c| is_kana(&c.to_string()))
+            && (stem.chars().any(is_kanji) || stem.chars().count() > 1);
+        if inflected {
+            forms.push(stem.to_string());
+        }
+        forms.push(form);
+    }
+    forms.sort_by_key(|f| std::cmp::Reverse(f.chars().count()));
+    let mut unique: Vec<String> = vec![];
+    for form in forms {
+        if !unique.contains(&form) {
+            unique.push(form);
+        }
+    }
+    unique
+}
@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+
+/// Kana that look alike. Groups mix only one script; only the members that
+/// are enabled in the current `KanaSet` are drilled.
+pub const CONFUSABLE_GROUPS: &[&[&str]] = &[
+    &["シ", "ツ"],
+    &["ソ", "ン"],
+    &["シ", "ン"],
+    &["ツ", "ソ"],
+    &["ク", "ケ", "タ"],
+    &["コ", "ユ"],
+    &["ノ", "メ"],
+    &["チ", "テ"],
+    &["ス", "ヌ"],
+    &["ウ", "ワ", "フ"],
+    &["マ", "ム"],
+    &["ぬ", "め"],
+    &["わ", "れ", "ね"],
+    &["る", "ろ"],
+    &["は", "ほ", "ま"],
+    &["さ", "ち"],
+    &["き", "さ"],
+    &["い", "り"],
+    &["こ", "に"],
+    &["あ", "お"],
+    &["け", "は"],
+];
+
+/// How many times each kana (first) was answered as another one (second).
+pub type Confusions = HashMap<(String, String), u32>;
+
+/// Kana from `enabled` that are likely to be mistaken for `kana`: the
+/// curated look-alikes first, then the user's own mix-ups, most frequent first.
+pub fn look_alikes(kana: &str, enabled: &[String], confusions: &Confusions) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    for group in CONFUSABLE_GROUPS.iter().filter(|g| g.contains(&kana)) {
+        for other in group.iter().filter(|o| **o != kana) {
+            if enabled.iter().any(|e| e == other) && !result.iter().any(|r| r == other) {
+                result.push(other.to_string());
+            }
+        }
+    }
+
+    let mut recorded = confusions
+        .iter()
+        .filter_map(|((a, b), count)| {
+            if a == kana {
+                Some((b.clone(), *count))
+            } else if b == kana {
+                Some((a.clone(), *count))
+            } else {
+                None
+            }
+        })
+        .filter(|(other, _)| enabled.contains(other))
+        .collect::<Vec<(String, u32)>>();
+    recorded.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    for (other, _) in recorded {
+        if !result.contains(&other) {
+            result.push(other);
+        }
+    }
+
+    result
+}
+
+/// Groups of at least two enabled kana to drill, with a weight that grows
+/// with the number of recorded mix-ups inside the group.
+pub fn drill_groups(enabled: &[String], confusions: &Confusions) -> Vec<(Vec<String>, u32)> {
+    let mut groups: Vec<(Vec<String>, u32)> = vec![];
+    let mut seen: HashSet<Vec<String>> = HashSet::new();
+
+    let mut push = |members: Vec<String>| {
+        let mut key = members.clone();
+        key.sort();
+        if members.len() < 2 || !seen.insert(key) {
+            return;
+        }
+        let weight = 1 + confusions
+            .iter()
+            .filter(|((a, b), _)| members.contains(a) && members.contains(b))
+            .map(|(_, count)| *count)
+            .sum::<u32>();
+        groups.push((members, weight));
+    };
+
+    for group in CONFUSABLE_GROUPS {
+        push(
+            group
+                .iter()
+                .filter(|k| enabled.iter().any(|e| e == *k))
+                .map(|k| k.to_string())
+                .collect(),
+        );
+    }
+    for (a, b) in confusions.keys() {
+        if a != b && enabled.contains(a) && enabled.contains(b) {
+            push(vec![a.clone(), b.clone()]);
+        }
+    }
+
+    groups
+}
@@ -1,8 +1,9 @@
-use crate::repetitions::CardSetSettings;
+use crate::data_provider::card_stats::delete_stats_of_other_kind;
+use crate::repetitions::{CardKind, CardSetSettings, FuriganaMode};
 use rusqlite::Connection;
 
 pub fn load_sets(connection: &Connection) -> Vec<CardSetSettings> {
-    let mut stmt = connection.prepare("SELECT id, name, forward, backward, filter FROM card_set").unwrap();
+    let mut stmt = connection.prepare("SELECT id, name, forward, backward, filter, kind, furigana FROM card_set").unwrap();
     let iter = stmt.query_map([], |row| {
         Ok(CardSetSettings {
             id: row.get(0)?,
@@ -10,6 +11,8 @@ pub fn load_sets(connection: &Connection) -> Vec<CardSetSettings> {
             forward: row.get(2)?,
             backward: row.get(3)?,
             filter: row.get(4)?,
+            kind: CardKind::from_key(&row.get::<_, String>(5)?),
+            furigana: FuriganaMode::from_key(&row.get::<_, String>(6)?),
             count: None
         })
     }).unwrap();
@@ -25,12 +28,14 @@ pub fn load_sets(connection: &Connection) -> Vec<CardSetSettings> {
 pub fn add_set(set: &mut CardSetSettings, connection: &Connection) {
     let index = connection
         .query_row(
-            "INSERT INTO card_set (name, forward, backward, filter) VALUES (?1, ?2, ?3, ?4) RETURNING id",
+            "INSERT INTO card_set (name, forward, backward, filter, kind, furigana) VALUES (?1, ?2, ?3, ?4, ?5, ?6) RETURNING id",
             (
                 &set.name,
                 &set.forward,
                 &set.backward,
                 &set.filter,
+                set.kind.key(),
+                set.furigana.key(),
             ),
             |row| row.get(0)
         )
@@ -47,16 +52,19 @@ pub fn update_card_set(set: &mut CardSetSettings, connection: &Connection){
     else {
         connection
             .execute(
-                "UPDATE card_set SET name = ?1, forward = ?2, backward = ?3, filter = ?4 WHERE id = ?5",
+                "UPDATE card_set SET name = ?1, forward = ?2, backward = ?3, filter = ?4, kind = ?5, furigana = ?6 WHERE id = ?7",
                 (
                     &set.name,
                     &set.forward,
                     &set.backward,
                     &set.filter,
+                    set.kind.key(),
+                    set.furigana.key(),
                     &set.id
                 ),
             )
             .unwrap_or_else(|e| {println!("{}", e); 0});
+        delete_stats_of_other_kind(set.id, set.kind, connection);
     }
 }
 
@@ -1,9 +1,10 @@
 use crate::lang::CardStatistics;
-use crate::repetitions::CardSetSettings;
+use crate::repetitions::{CardKind, CardSetSettings};
 use rusqlite::Connection;
 
 pub fn load_stats_of_set(set: &CardSetSettings, connection: &Connection) -> Vec<CardStatistics> {
-    let mut stmt = connection.prepare("SELECT id, word_id, score, last_opened FROM card_stats WHERE set_id = ?1").unwrap();
+    let (table, column) = stats_table(set.kind);
+    let mut stmt = connection.prepare(&format!("SELECT id, {}, score, last_opened FROM {} WHERE set_id = ?1", column, table)).unwrap();
     let iter = stmt.query_map((set.id,), |row| {
         Ok(CardStatistics {
             id: row.get(0)?,
@@ -22,10 +23,11 @@ pub fn load_stats_of_set(set: &CardSetSettings, connection: &Connection) -> Vec<
     buffer
 }
 
-pub fn add_stat(stat: &mut CardStatistics, connection: &Connection) {
+pub fn add_stat(stat: &mut CardStatistics, kind: CardKind, connection: &Connection) {
+    let (table, column) = stats_table(kind);
     let index = connection
         .query_row(
-            "INSERT INTO card_stats (word_id, set_id, score, last_opened) VALUES (?1, ?2, ?3, ?4) RETURNING id",
+            &format!("INSERT INTO {} ({}, set_id, score, last_opened) VALUES (?1, ?2, ?3, ?4) RETURNING id", table, column),
             (
                 &stat.word_id,
                 &stat.set_id,
@@ -39,10 +41,10 @@ pub fn add_stat(stat: &mut CardStatistics, connection: &Connection) {
     stat.id = index;
 }
 
-pub fn update_stat_score(stat: &CardStatistics, connection: &Connection){
+pub fn update_stat_score(stat: &CardStatistics, kind: CardKind, connection: &Connection){
         connection
             .execute(
-                "UPDATE card_stats SET score = ?1, last_opened = ?2 WHERE id = ?3",
+                &format!("UPDATE {} SET score = ?1, last_opened = ?2 WHERE id = ?3", stats_table(kind).0),
                 (
                     &stat.score,
                     &stat.last_open,
@@ -52,14 +54,38 @@ pub fn update_stat_score(stat: &CardStatistics, connection: &Connection){
             .unwrap_or_else(|e| {println!("{}", e); 0});
 }
 
-pub fn delete_stat(stat: &CardStatistics, connection: &Connection) {
+pub fn delete_stat(stat: &CardStatistics, kind: CardKind, connection: &Connection) {
     if stat.id == 0 {
         return;
     }
     connection
-        .execute("DELETE FROM card_stats WHERE id = ?1", (&stat.id,))
+        .execute(&format!("DELETE FROM {} WHERE id = ?1", stats_table(kind).0), (&stat.id,))
         .unwrap_or_else(|e| {
             println!("{}", e);
             0
         });
 }
+
+/// Statistics the set has in the table of the other card kind, left from
+/// before its kind was changed: the ids there mean nothing now.
+pub fn delete_stats_of_other_kind(set_id: u32, kind: CardKind, connection: &Connection) {
+    let other = match kind {
+        CardKind::Words => CardKind::Kanji,
+        CardKind::Kanji => CardKind::Words,
+    };
+    connection
+        .execute(&format!("DELETE FROM {} WHERE set_id = ?1", stats_table(other).0), (set_id,))
+        .unwrap_or_else(|e| {
+            println!("{}", e);
+            0
+        });
+}
+
+/// Kanji cards point to the kanji table, so they keep their statistics apart
+/// from the word cards.
+fn stats_table(kind: CardKind) -> (&'static str, &'static str) {
+    match kind {
+        CardKind::Words => ("card_stats", "word_id"),
+        CardKind::Kanji => ("kanji_card_stats", "kanji_id"),
+    }
+}
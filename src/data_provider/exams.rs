@@ -0,0 +1,74 @@
+use crate::exam::{ExamAnswer, ExamRecord, QuestionKind};
+use rusqlite::Connection;
+
+/// Stores the exam with its answers and returns the new id.
+pub fn save_exam(exam: &ExamRecord, connection: &Connection) -> rusqlite::Result<u32> {
+    // dropped without commit on the first error, so no exam is left half saved
+    let transaction = connection.unchecked_transaction()?;
+    let id = transaction.query_row(
+        "INSERT INTO exams (source, taken_at, total, correct) VALUES (?1, ?2, ?3, ?4) RETURNING id",
+        (&exam.source, &exam.taken_at, exam.total, exam.correct),
+        |row| row.get(0),
+    )?;
+
+    for (position, answer) in exam.answers.iter().enumerate() {
+        transaction.execute(
+            "INSERT INTO exam_answers (exam_id, position, kind, prompt, expected, given, is_correct)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (
+                id,
+                position as u32,
+                answer.kind.key(),
+                &answer.prompt,
+                &answer.expected,
+                &answer.given,
+                answer.correct,
+            ),
+        )?;
+    }
+    transaction.commit()?;
+    Ok(id)
+}
+
+/// Past exams, newest first, without their answers.
+pub fn load_exams(connection: &Connection) -> Vec<ExamRecord> {
+    let mut stmt = connection
+        .prepare("SELECT id, source, taken_at, total, correct FROM exams ORDER BY taken_at DESC")
+        .unwrap();
+    let iter = stmt
+        .query_map([], |row| {
+            Ok(ExamRecord {
+                id: row.get(0)?,
+                source: row.get(1)?,
+                taken_at: row.get(2)?,
+                total: row.get(3)?,
+                correct: row.get(4)?,
+                answers: vec![],
+            })
+        })
+        .unwrap();
+
+    iter.map(|exam| exam.unwrap()).collect()
+}
+
+pub fn load_exam_answers(exam_id: u32, connection: &Connection) -> Vec<ExamAnswer> {
+    let mut stmt = connection
+        .prepare(
+            "SELECT kind, prompt, expected, given, is_correct FROM exam_answers
+            WHERE exam_id = ?1 ORDER BY position",
+        )
+        .unwrap();
+    let iter = stmt
+        .query_map([exam_id], |row| {
+            Ok(ExamAnswer {
+                kind: QuestionKind::from_key(&row.get::<_, String>(0)?),
+                prompt: row.get(1)?,
+                expected: row.get(2)?,
+                given: row.get(3)?,
+                correct: row.get(4)?,
+            })
+        })
+        .unwrap();
+
+    iter.map(|answer| answer.unwrap()).collect()
+}
@@ -0,0 +1,151 @@
+use crate::jmdict::JmdictEntry;
+use rusqlite::Connection;
+use std::thread;
+use std::time::Duration;
+
+/// Entries written per transaction. The pages write to the same database,
+/// and a single transaction for the whole import would keep them waiting
+/// until it ends.
+const BATCH: usize = 2000;
+/// Time between batches for the pages to get the write lock.
+const PAUSE: Duration = Duration::from_millis(20);
+
+/// Replaces the imported JMdict. Every written form and reading goes to
+/// `jmdict_forms`, so the lookup is a range scan of its primary key.
+///
+/// The entries are written to import tables first and take the place of the
+/// old ones in a single transaction at the end, so a failed import keeps the
+/// previous dictionary whole.
+pub fn save_jmdict(list: &[JmdictEntry], connection: &Connection) -> Result<(), String> {
+    let result = create_import_tables(connection).and_then(|_| {
+        for batch in list.chunks(BATCH) {
+            save_batch(batch, connection)?;
+            thread::sleep(PAUSE);
+        }
+        swap_import_tables(connection)
+    });
+    result.map_err(|e| {
+        let _ = connection.execute_batch(
+            "DROP TABLE IF EXISTS jmdict_forms_import; DROP TABLE IF EXISTS jmdict_entries_import;",
+        );
+        format!("JMdict не загружен, прежний словарь не изменился: {}", e)
+    })
+}
+
+/// Empty tables of the same shape as `jmdict_entries` and `jmdict_forms`.
+fn create_import_tables(connection: &Connection) -> rusqlite::Result<()> {
+    connection.execute_batch(
+        "DROP TABLE IF EXISTS jmdict_forms_import;
+DROP TABLE IF EXISTS jmdict_entries_import;
+create table jmdict_entries_import
+(
+    id     INTEGER           not null
+        primary key,
+    common INTEGER default 0 not null,
+    entry  TEXT              not null
+);
+create table jmdict_forms_import
+(
+    form     TEXT    not null,
+    entry_id INTEGER not null
+        references jmdict_entries_import
+            on delete cascade,
+    primary key (form, entry_id)
+) without rowid;",
+    )
+}
+
+/// Renaming the entries table also renames the reference of the forms to it.
+fn swap_import_tables(connection: &Connection) -> rusqlite::Result<()> {
+    let transaction = connection.unchecked_transaction()?;
+    transaction.execute_batch(
+        "DROP TABLE jmdict_forms;
+DROP TABLE jmdict_entries;
+ALTER TABLE jmdict_entries_import RENAME TO jmdict_entries;
+ALTER TABLE jmdict_forms_import RENAME TO jmdict_forms;",
+    )?;
+    transaction.commit()
+}
+
+fn save_batch(batch: &[JmdictEntry], connection: &Connection) -> rusqlite::Result<()> {
+    let transaction = connection.unchecked_transaction()?;
+    {
+        let mut entry_stmt = transaction.prepare(
+            "INSERT OR REPLACE INTO jmdict_entries_import (id, common, entry) VALUES (?1, ?2, ?3)",
+        )?;
+        let mut form_stmt = transaction.prepare(
+            "INSERT OR IGNORE INTO jmdict_forms_import (form, entry_id) VALUES (?1, ?2)",
+        )?;
+        for entry in batch {
+            entry_stmt.execute((
+                &entry.id,
+                &entry.common,
+                serde_json::to_string(entry).unwrap(),
+            ))?;
+            for form in entry.forms() {
+                form_stmt.execute((form, &entry.id))?;
+            }
+        }
+    }
+    transaction.commit()
+}
+
+/// Entries with a form starting with the text: exact matches first, then
+/// common words, then the shorter ones.
+pub fn lookup_jmdict(prefix: &str, limit: usize, connection: &Connection) -> Vec<JmdictEntry> {
+    if prefix.is_empty() {
+        return vec![];
+    }
+    // U+10FFFF sorts after any text starting with the prefix
+    let end = format!("{}\u{10FFFF}", prefix);
+    let mut stmt = connection
+        .prepare(
+            "SELECT e.entry FROM jmdict_forms f JOIN jmdict_entries e ON e.id = f.entry_id
+            WHERE f.form >= ?1 AND f.form < ?2
+            GROUP BY e.id
+            ORDER BY MAX(f.form = ?1) DESC, e.common DESC, MIN(length(f.form)), e.id
+            LIMIT ?3",
+        )
+        .unwrap();
+    let iter = stmt
+        .query_map((prefix, &end, limit as i64), |row| row.get::<_, String>(0))
+        .unwrap();
+
+    entries(iter.flatten())
+}
+
+pub fn jmdict_size(connection: &Connection) -> u32 {
+    connection
+        .query_row("SELECT count(*) FROM jmdict_entries", [], |row| row.get(0))
+        .unwrap_or_else(|e| {
+            println!("{}", e);
+            0
+        })
+}
+
+/// Entries with exactly this written form or reading, common ones first.
+pub fn find_jmdict(form: &str, connection: &Connection) -> Vec<JmdictEntry> {
+    let mut stmt = connection
+        .prepare(
+            "SELECT e.entry FROM jmdict_forms f JOIN jmdict_entries e ON e.id = f.entry_id
+            WHERE f.form = ?1
+            ORDER BY e.common DESC, e.id",
+        )
+        .unwrap();
+    let iter = stmt
+        .query_map((form,), |row| row.get::<_, String>(0))
+        .unwrap();
+
+    entries(iter.flatten())
+}
+
+fn entries(rows: impl Iterator<Item = String>) -> Vec<JmdictEntry> {
+    let mut buffer = vec![];
+    for entry in rows {
+        match serde_json::from_str::<JmdictEntry>(&entry) {
+            Ok(entry) => buffer.push(entry),
+            Err(e) => println!("{}", e),
+        }
+    }
+    buffer
+}
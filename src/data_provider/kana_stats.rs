@@ -0,0 +1,78 @@
+use crate::confusables::Confusions;
+use crate::lang::KanaStatistics;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+pub fn load_kana_stats(connection: &Connection) -> HashMap<String, KanaStatistics> {
+    let mut stmt = connection
+        .prepare("SELECT kana, attempts, errors, helps, total_time_ms FROM kana_stats")
+        .unwrap();
+    let iter = stmt
+        .query_map([], |row| {
+            Ok(KanaStatistics {
+                kana: row.get(0)?,
+                attempts: row.get(1)?,
+                errors: row.get(2)?,
+                helps: row.get(3)?,
+                total_time_ms: row.get(4)?,
+            })
+        })
+        .unwrap();
+
+    let mut buffer = HashMap::new();
+    for stat in iter {
+        let stat = stat.unwrap();
+        buffer.insert(stat.kana.clone(), stat);
+    }
+
+    buffer
+}
+
+pub fn save_kana_stat(stat: &KanaStatistics, connection: &Connection) {
+    connection
+        .execute(
+            "INSERT INTO kana_stats (kana, attempts, errors, helps, total_time_ms) VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(kana) DO UPDATE SET attempts = ?2, errors = ?3, helps = ?4, total_time_ms = ?5",
+            (
+                &stat.kana,
+                &stat.attempts,
+                &stat.errors,
+                &stat.helps,
+                &stat.total_time_ms,
+            ),
+        )
+        .unwrap_or_else(|e| {
+            println!("{}", e);
+            0
+        });
+}
+
+pub fn load_kana_confusions(connection: &Connection) -> Confusions {
+    let mut stmt = connection
+        .prepare("SELECT kana, confused_with, count FROM kana_confusions")
+        .unwrap();
+    let iter = stmt
+        .query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))
+        .unwrap();
+
+    let mut buffer = HashMap::new();
+    for confusion in iter {
+        let (pair, count) = confusion.unwrap();
+        buffer.insert(pair, count);
+    }
+
+    buffer
+}
+
+pub fn save_kana_confusion(kana: &str, confused_with: &str, count: u32, connection: &Connection) {
+    connection
+        .execute(
+            "INSERT INTO kana_confusions (kana, confused_with, count) VALUES (?1, ?2, ?3)
+            ON CONFLICT(kana, confused_with) DO UPDATE SET count = ?3",
+            (kana, confused_with, count),
+        )
+        .unwrap_or_else(|e| {
+            println!("{}", e);
+            0
+        });
+}
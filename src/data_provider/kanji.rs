@@ -0,0 +1,102 @@
+use crate::kanji::KanjiData;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+pub fn load_kanji(connection: &Connection) -> HashMap<char, KanjiData> {
+    let mut stmt = connection
+        .prepare(
+            "SELECT id, literal, on_readings, kun_readings, meanings, strokes, grade, jlpt, radical, components FROM kanji",
+        )
+        .unwrap();
+    let iter = stmt
+        .query_map([], |row| {
+            Ok(KanjiData {
+                id: row.get(0)?,
+                literal: row.get(1)?,
+                on_readings: row.get(2)?,
+                kun_readings: row.get(3)?,
+                meanings: row.get(4)?,
+                strokes: row.get(5)?,
+                grade: row.get(6)?,
+                jlpt: row.get(7)?,
+                radical: row.get(8)?,
+                components: row.get(9)?,
+            })
+        })
+        .unwrap();
+
+    let mut buffer = HashMap::new();
+    for kanji in iter {
+        let kanji = kanji.unwrap();
+        if let Some(literal) = kanji.literal.chars().next() {
+            buffer.insert(literal, kanji);
+        }
+    }
+
+    buffer
+}
+
+/// Inserts or refreshes the kanji by literal, so ids and card statistics
+/// survive importing a newer KANJIDIC. One transaction, the file has ~13k entries.
+pub fn save_kanji(list: &[KanjiData], connection: &Connection) {
+    let transaction = match connection.unchecked_transaction() {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    {
+        let mut stmt = transaction
+            .prepare(
+                "INSERT INTO kanji (literal, on_readings, kun_readings, meanings, strokes, grade, jlpt, radical, components)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                ON CONFLICT(literal) DO UPDATE SET on_readings = ?2, kun_readings = ?3, meanings = ?4,
+                strokes = ?5, grade = ?6, jlpt = ?7, radical = ?8, components = ?9",
+            )
+            .unwrap();
+        for kanji in list {
+            stmt.execute((
+                &kanji.literal,
+                &kanji.on_readings,
+                &kanji.kun_readings,
+                &kanji.meanings,
+                &kanji.strokes,
+                &kanji.grade,
+                &kanji.jlpt,
+                &kanji.radical,
+                &kanji.components,
+            ))
+            .unwrap_or_else(|e| {
+                println!("{}", e);
+                0
+            });
+        }
+    }
+    transaction.commit().unwrap_or_else(|e| println!("{}", e));
+}
+
+/// Rows for kanji KANJIDIC hasn't brought yet: only the literal. An import
+/// fills them in place, keeping their ids and card statistics.
+pub fn add_bare_kanji(literals: &[char], connection: &Connection) {
+    let transaction = match connection.unchecked_transaction() {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    for literal in literals {
+        transaction
+            .execute(
+                "INSERT OR IGNORE INTO kanji (literal, on_readings, kun_readings, meanings, components)
+                VALUES (?1, '', '', '', '')",
+                (literal.to_string(),),
+            )
+            .unwrap_or_else(|e| {
+                println!("{}", e);
+                0
+            });
+    }
+    transaction.commit().unwrap_or_else(|e| println!("{}", e));
+}
@@ -0,0 +1,200 @@
+//! In-process stand-in for the VOICEVOX engine.
+//!
+//! Implements just enough of `/audio_query` and `/synthesis` for the voice
+//! cache to be exercised without a real engine on port 50021. The returned
+//! WAV is derived from the query, so the same text always yields the same bytes.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub body: String,
+}
+
+#[derive(Clone, Default)]
+pub struct MockBehaviour {
+    /// Status code returned by `/audio_query` instead of a query.
+    pub audio_query_error: Option<u16>,
+    /// Status code returned by `/synthesis` instead of audio.
+    pub synthesis_error: Option<u16>,
+    /// Delay before answering `/synthesis`, to make requests overlap.
+    pub synthesis_delay: Option<Duration>,
+}
+
+pub struct MockVoicevox {
+    pub url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockVoicevox {
+    pub async fn start(behaviour: MockBehaviour) -> MockVoicevox {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let recorded = recorded.clone();
+                let behaviour = behaviour.clone();
+                tokio::spawn(async move { serve(stream, recorded, behaviour).await });
+            }
+        });
+
+        MockVoicevox { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn count(&self, path: &str) -> usize {
+        self.requests().iter().filter(|r| r.path == path).count()
+    }
+}
+
+/// The audio the mock answers with for a given `/audio_query` text.
+pub fn expected_wav(text: &str) -> Vec<u8> {
+    wav_for(&audio_query_json(text))
+}
+
+fn audio_query_json(text: &str) -> String {
+    serde_json::json!({ "kana": text, "speedScale": 1.0, "outputSamplingRate": 8000 }).to_string()
+}
+
+fn wav_for(query: &str) -> Vec<u8> {
+    let samples = Sha256::digest(query.as_bytes()).repeat(4);
+    let mut wav = vec![];
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&8000u32.to_le_bytes());
+    wav.extend_from_slice(&8000u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&8u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&samples);
+    wav
+}
+
+async fn serve(
+    mut stream: TcpStream,
+    recorded: Arc<Mutex<Vec<RecordedRequest>>>,
+    behaviour: MockBehaviour,
+) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    recorded.lock().unwrap().push(request.clone());
+
+    let (status, content_type, body) = match request.path.as_str() {
+        "/audio_query" => match behaviour.audio_query_error {
+            Some(status) => (status, "text/plain", b"audio_query failed".to_vec()),
+            None => {
+                let text = request.query.get("text").cloned().unwrap_or_default();
+                (200, "application/json", audio_query_json(&text).into_bytes())
+            }
+        },
+        "/synthesis" => {
+            if let Some(delay) = behaviour.synthesis_delay {
+                tokio::time::sleep(delay).await;
+            }
+            match behaviour.synthesis_error {
+                Some(status) => (status, "text/plain", b"synthesis failed".to_vec()),
+                None => (200, "audio/wav", wav_for(&request.body)),
+            }
+        }
+        _ => (404, "text/plain", b"not found".to_vec()),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&body).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<RecordedRequest> {
+    let mut buffer = vec![];
+    let mut chunk = [0u8; 1024];
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let target = lines.next()?.split(' ').nth(1)?.to_string();
+    let length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    while buffer.len() < header_end + length {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+    Some(RecordedRequest {
+        path: path.to_string(),
+        query: query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (decode(k), decode(v)))
+            .collect(),
+        body: String::from_utf8_lossy(&buffer[header_end..]).to_string(),
+    })
+}
+
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
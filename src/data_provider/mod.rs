@@ -1,5 +1,15 @@
 pub(crate) mod words;
 pub(crate) mod card_sets;
 pub(crate) mod card_stats;
+pub(crate) mod exams;
+pub(crate) mod jmdict;
+pub(crate) mod kana_stats;
+pub(crate) mod kanji;
+pub(crate) mod settings;
+pub(crate) mod speed_runs;
 pub(crate) mod voice;
-
+pub(crate) mod word_confusions;
+#[cfg(test)]
+mod mock_voicevox;
+#[cfg(test)]
+mod voice_tests;
@@ -0,0 +1,35 @@
+use rusqlite::Connection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A choice kept between runs, stored as JSON. The default when it was
+/// never saved or can't be read.
+pub fn load_setting<T: DeserializeOwned + Default>(key: &str, connection: &Connection) -> T {
+    let value = connection.query_row("SELECT value FROM settings WHERE key = ?1", (key,), |row| {
+        row.get::<_, String>(0)
+    });
+    match value {
+        Ok(value) => serde_json::from_str(&value).unwrap_or_else(|e| {
+            println!("{}: {}", key, e);
+            T::default()
+        }),
+        Err(rusqlite::Error::QueryReturnedNoRows) => T::default(),
+        Err(e) => {
+            println!("{}", e);
+            T::default()
+        }
+    }
+}
+
+pub fn save_setting<T: Serialize>(key: &str, value: &T, connection: &Connection) {
+    connection
+        .execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+            ON CONFLICT(key) DO UPDATE SET value = ?2",
+            (key, serde_json::to_string(value).unwrap()),
+        )
+        .unwrap_or_else(|e| {
+            println!("{}", e);
+            0
+        });
+}
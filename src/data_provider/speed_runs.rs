@@ -0,0 +1,52 @@
+use crate::speed_run::RunRecord;
+use rusqlite::Connection;
+
+pub fn save_run(run: &RunRecord, connection: &Connection) -> u32 {
+    connection
+        .execute(
+            "INSERT INTO speed_runs (mode, run_limit, taken_at, answers, correct, per_minute, accuracy, median_ms)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (
+                &run.mode,
+                &run.limit,
+                &run.taken_at,
+                &run.answers,
+                &run.correct,
+                &run.per_minute,
+                &run.accuracy,
+                &run.median_ms,
+            ),
+        )
+        .unwrap_or_else(|e| {
+            println!("{}", e);
+            0
+        });
+    connection.last_insert_rowid() as u32
+}
+
+/// Runs of one mode and limit, oldest first.
+pub fn load_runs(mode: &str, limit: &str, connection: &Connection) -> Vec<RunRecord> {
+    let mut stmt = connection
+        .prepare(
+            "SELECT id, mode, run_limit, taken_at, answers, correct, per_minute, accuracy, median_ms
+            FROM speed_runs WHERE mode = ?1 AND run_limit = ?2 ORDER BY taken_at",
+        )
+        .unwrap();
+    let iter = stmt
+        .query_map((mode, limit), |row| {
+            Ok(RunRecord {
+                id: row.get(0)?,
+                mode: row.get(1)?,
+                limit: row.get(2)?,
+                taken_at: row.get(3)?,
+                answers: row.get(4)?,
+                correct: row.get(5)?,
+                per_minute: row.get(6)?,
+                accuracy: row.get(7)?,
+                median_ms: row.get(8)?,
+            })
+        })
+        .unwrap();
+
+    iter.map(|run| run.unwrap()).collect()
+}
@@ -1,38 +1,177 @@
 use crate::dictionary::app_data_dir;
 use reqwest::Client;
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
 
-pub async fn get_voice(text: &str) -> BufReader<File> {
-    let mut path = app_data_dir();
-    path.push("voice");
-    if !path.exists() {
-        std::fs::create_dir(path.clone()).unwrap();
+const ENGINE_URL: &str = "http://127.0.0.1:50021";
+const SPEAKER: u32 = 11;
+
+/// Synthesis requests that are currently running, keyed by cache file.
+/// Concurrent requests for the same text wait for the first one instead of
+/// asking the engine twice and racing on the same file.
+static IN_FLIGHT: LazyLock<Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug)]
+pub enum VoiceError {
+    Request(reqwest::Error),
+    Engine { status: u16, body: String },
+    Io(std::io::Error),
+}
+
+impl Display for VoiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VoiceError::Request(e) => write!(f, "voice engine request failed: {}", e),
+            VoiceError::Engine { status, body } => {
+                write!(f, "voice engine returned {}: {}", status, body)
+            }
+            VoiceError::Io(e) => write!(f, "voice cache error: {}", e),
+        }
     }
-    let hash = format!("{}.wav", hex::encode(Sha256::digest(text.as_bytes())));
-    path.push(hash);
-    if !path.exists() {
-        let engine_url = "http://127.0.0.1:50021";
+}
+
+impl From<reqwest::Error> for VoiceError {
+    fn from(value: reqwest::Error) -> Self {
+        VoiceError::Request(value)
+    }
+}
+
+impl From<std::io::Error> for VoiceError {
+    fn from(value: std::io::Error) -> Self {
+        VoiceError::Io(value)
+    }
+}
+
+/// VOICEVOX engine together with the directory where synthesized phrases are kept.
+#[derive(Clone, Debug)]
+pub struct VoiceEngine {
+    url: String,
+    speaker: u32,
+    cache_dir: PathBuf,
+}
+
+impl Default for VoiceEngine {
+    fn default() -> Self {
+        let mut path = app_data_dir();
+        path.push("voice");
+        VoiceEngine::new(ENGINE_URL, SPEAKER, path)
+    }
+}
+
+impl VoiceEngine {
+    pub fn new(url: &str, speaker: u32, cache_dir: PathBuf) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            speaker,
+            cache_dir,
+        }
+    }
+
+    pub fn cache_path(&self, text: &str) -> PathBuf {
+        let hash = format!("{}.wav", hex::encode(Sha256::digest(text.as_bytes())));
+        self.cache_dir.join(hash)
+    }
+
+    pub async fn voice(&self, text: &str) -> Result<BufReader<File>, VoiceError> {
+        let path = self.cache_path(text);
+        if !path.exists() {
+            let in_flight = InFlight::new(&path);
+            let _guard = in_flight.lock.lock().await;
+            if !path.exists() {
+                let audio = self.synthesize(text).await?;
+                self.store(&path, &audio).await?;
+            }
+        }
+
+        Ok(BufReader::new(File::open(path)?))
+    }
+
+    async fn synthesize(&self, text: &str) -> Result<Vec<u8>, VoiceError> {
         let client = Client::new();
+        let speaker = self.speaker.to_string();
         let query = client
-            .post(format!("{engine_url}/audio_query?text={text}&speaker=11"))
+            .post(format!("{}/audio_query", self.url))
+            .query(&[("text", text), ("speaker", speaker.as_str())])
             .send()
-            .await;
+            .await?;
+        let query = check_status(query).await?.text().await?;
 
-        let query = query.unwrap().text().await.unwrap();
-        // Synthesis
         let audio = client
-            .post(format!("{engine_url}/synthesis?speaker=11"))
+            .post(format!("{}/synthesis", self.url))
+            .query(&[("speaker", speaker.as_str())])
             .header("Content-Type", "application/json")
             .body(query)
             .send()
-            .await.unwrap()
-            .bytes()
-            .await.unwrap();
+            .await?;
+
+        Ok(check_status(audio).await?.bytes().await?.to_vec())
+    }
+
+    /// Writes through a temporary file so a half-written phrase never looks cached.
+    async fn store(&self, path: &Path, audio: &[u8]) -> Result<(), VoiceError> {
+        tokio::fs::create_dir_all(&self.cache_dir).await?;
+        let temp = path.with_extension("part");
+        tokio::fs::write(&temp, audio).await?;
+        tokio::fs::rename(&temp, path).await?;
+        Ok(())
+    }
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, VoiceError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
 
-        tokio::fs::write(&path, &audio).await.unwrap();
+    Err(VoiceError::Engine {
+        status: status.as_u16(),
+        body: response.text().await.unwrap_or_default(),
+    })
+}
+
+/// A place in `IN_FLIGHT`, given back when dropped, so a failed or cancelled
+/// request doesn't leave its entry behind.
+struct InFlight {
+    path: PathBuf,
+    lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl InFlight {
+    fn new(path: &Path) -> Self {
+        let lock = IN_FLIGHT
+            .lock()
+            .unwrap()
+            .entry(path.to_path_buf())
+            .or_default()
+            .clone();
+        Self {
+            path: path.to_path_buf(),
+            lock,
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        // One reference is held by the map and one by us: nobody else is waiting.
+        if Arc::strong_count(&self.lock) == 2 {
+            in_flight.remove(&self.path);
+        }
     }
+}
+
+#[cfg(test)]
+pub(crate) fn is_in_flight(path: &Path) -> bool {
+    IN_FLIGHT.lock().unwrap().contains_key(path)
+}
 
-    BufReader::new(File::open(path).unwrap())
+pub async fn get_voice(text: &str) -> Result<BufReader<File>, VoiceError> {
+    VoiceEngine::default().voice(text).await
 }
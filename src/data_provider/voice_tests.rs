@@ -0,0 +1,163 @@
+use crate::data_provider::mock_voicevox::{expected_wav, MockBehaviour, MockVoicevox};
+use crate::data_provider::voice::{is_in_flight, VoiceEngine, VoiceError};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+static CACHE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn temp_cache_dir() -> PathBuf {
+    let id = CACHE_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("jap_learn_voice_{}_{}", std::process::id(), id));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+async fn engine(behaviour: MockBehaviour) -> (MockVoicevox, VoiceEngine) {
+    let mock = MockVoicevox::start(behaviour).await;
+    let engine = VoiceEngine::new(&mock.url, 11, temp_cache_dir());
+    (mock, engine)
+}
+
+async fn read_voice(engine: &VoiceEngine, text: &str) -> Result<Vec<u8>, VoiceError> {
+    let mut bytes = vec![];
+    engine.voice(text).await?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[tokio::test]
+async fn cache_miss_asks_engine_and_stores_audio() {
+    let (mock, engine) = engine(MockBehaviour::default()).await;
+
+    let audio = read_voice(&engine, "こんにちは").await.unwrap();
+
+    assert_eq!(audio, expected_wav("こんにちは"));
+    assert_eq!(mock.count("/audio_query"), 1);
+    assert_eq!(mock.count("/synthesis"), 1);
+    assert_eq!(std::fs::read(engine.cache_path("こんにちは")).unwrap(), audio);
+}
+
+#[tokio::test]
+async fn cache_hit_does_not_touch_engine() {
+    let (mock, engine) = engine(MockBehaviour::default()).await;
+
+    let first = read_voice(&engine, "ねこ").await.unwrap();
+    let second = read_voice(&engine, "ねこ").await.unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(mock.count("/audio_query"), 1);
+    assert_eq!(mock.count("/synthesis"), 1);
+}
+
+#[tokio::test]
+async fn different_texts_are_cached_separately() {
+    let (mock, engine) = engine(MockBehaviour::default()).await;
+
+    let cat = read_voice(&engine, "ねこ").await.unwrap();
+    let dog = read_voice(&engine, "いぬ").await.unwrap();
+
+    assert_ne!(cat, dog);
+    assert_ne!(engine.cache_path("ねこ"), engine.cache_path("いぬ"));
+    assert_eq!(mock.count("/synthesis"), 2);
+}
+
+#[tokio::test]
+async fn parameters_are_url_encoded() {
+    let (mock, engine) = engine(MockBehaviour::default()).await;
+    let text = "食べる & 飲む? a=b #1 100%";
+
+    read_voice(&engine, text).await.unwrap();
+
+    let requests = mock.requests();
+    let query = requests.iter().find(|r| r.path == "/audio_query").unwrap();
+    assert_eq!(query.query.get("text").map(String::as_str), Some(text));
+    assert_eq!(query.query.get("speaker").map(String::as_str), Some("11"));
+
+    let synthesis = requests.iter().find(|r| r.path == "/synthesis").unwrap();
+    assert_eq!(synthesis.query.get("speaker").map(String::as_str), Some("11"));
+    let body: serde_json::Value = serde_json::from_str(&synthesis.body).unwrap();
+    assert_eq!(body["kana"], text);
+}
+
+#[tokio::test]
+async fn audio_query_error_is_reported_and_not_cached() {
+    let (mock, engine) = engine(MockBehaviour {
+        audio_query_error: Some(422),
+        ..Default::default()
+    })
+    .await;
+
+    let result = read_voice(&engine, "エラー").await;
+
+    assert!(matches!(result, Err(VoiceError::Engine { status: 422, .. })));
+    assert_eq!(mock.count("/synthesis"), 0);
+    assert!(!engine.cache_path("エラー").exists());
+}
+
+#[tokio::test]
+async fn synthesis_error_is_reported_and_not_cached() {
+    let (mock, engine) = engine(MockBehaviour {
+        synthesis_error: Some(500),
+        ..Default::default()
+    })
+    .await;
+
+    let result = read_voice(&engine, "エラー").await;
+
+    assert!(matches!(result, Err(VoiceError::Engine { status: 500, .. })));
+    assert_eq!(mock.count("/synthesis"), 1);
+    assert!(!engine.cache_path("エラー").exists());
+}
+
+#[tokio::test]
+async fn failed_request_gives_back_its_in_flight_entry() {
+    let (mock, engine) = engine(MockBehaviour {
+        synthesis_error: Some(503),
+        ..Default::default()
+    })
+    .await;
+
+    let first = read_voice(&engine, "しっぱい").await;
+    let second = read_voice(&engine, "しっぱい").await;
+
+    assert!(matches!(first, Err(VoiceError::Engine { status: 503, .. })));
+    assert!(matches!(second, Err(VoiceError::Engine { status: 503, .. })));
+    assert!(!is_in_flight(&engine.cache_path("しっぱい")));
+    // the second request was not held up by the first one
+    assert_eq!(mock.count("/synthesis"), 2);
+}
+
+#[tokio::test]
+async fn unreachable_engine_is_a_request_error() {
+    let engine = VoiceEngine::new("http://127.0.0.1:1", 11, temp_cache_dir());
+
+    let result = read_voice(&engine, "だれ").await;
+
+    assert!(matches!(result, Err(VoiceError::Request(_))));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_requests_for_same_text_synthesize_once() {
+    let (mock, engine) = engine(MockBehaviour {
+        synthesis_delay: Some(Duration::from_millis(200)),
+        ..Default::default()
+    })
+    .await;
+
+    let tasks = (0..8)
+        .map(|_| {
+            let engine = engine.clone();
+            tokio::spawn(async move { read_voice(&engine, "おなじ").await.unwrap() })
+        })
+        .collect::<Vec<_>>();
+
+    let mut results = vec![];
+    for task in tasks {
+        results.push(task.await.unwrap());
+    }
+
+    assert!(results.iter().all(|audio| *audio == expected_wav("おなじ")));
+    assert_eq!(mock.count("/audio_query"), 1);
+    assert_eq!(mock.count("/synthesis"), 1);
+}
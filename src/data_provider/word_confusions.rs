@@ -0,0 +1,33 @@
+use crate::choices::WordConfusions;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+pub fn load_word_confusions(connection: &Connection) -> WordConfusions {
+    let mut stmt = connection
+        .prepare("SELECT word_id, confused_with, count FROM word_confusions")
+        .unwrap();
+    let iter = stmt
+        .query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))
+        .unwrap();
+
+    let mut buffer = HashMap::new();
+    for confusion in iter {
+        let (pair, count) = confusion.unwrap();
+        buffer.insert(pair, count);
+    }
+
+    buffer
+}
+
+pub fn save_word_confusion(word_id: u32, confused_with: u32, count: u32, connection: &Connection) {
+    connection
+        .execute(
+            "INSERT INTO word_confusions (word_id, confused_with, count) VALUES (?1, ?2, ?3)
+            ON CONFLICT(word_id, confused_with) DO UPDATE SET count = ?3",
+            (word_id, confused_with, count),
+        )
+        .unwrap_or_else(|e| {
+            println!("{}", e);
+            0
+        });
+}
@@ -1,4 +1,5 @@
 use crate::dictionary::app_data_dir;
+use crate::fields::FieldSpec;
 use crate::lang::{WordData, WordGroup};
 use rusqlite::Connection;
 use std::collections::HashMap;
@@ -6,13 +7,216 @@ use std::collections::HashMap;
 pub fn create_db() {
     let path = app_data_dir();
     let db_file = path.join("data.db");
-    if !db_file.exists() {
+    let is_new = !db_file.exists();
+    if is_new {
         std::fs::File::create(&db_file).unwrap();
-        let connection = Connection::open(&db_file).unwrap();
-        connection.execute("PRAGMA foreign_keys = ON;", []).unwrap();
+    }
+    let connection = Connection::open(&db_file).unwrap();
+    connection.execute("PRAGMA foreign_keys = ON;", []).unwrap();
+    // readers don't wait for writers, so a JMdict import doesn't lock the pages
+    connection
+        .query_row("PRAGMA journal_mode = WAL;", [], |row| row.get::<_, String>(0))
+        .unwrap_or_else(|e| {
+            println!("{}", e);
+            String::new()
+        });
 
+    if is_new {
         create_tables(&connection);
     }
+    migrate(&connection);
+}
+
+/// Tables added after the first release. Runs on every start, so existing
+/// databases pick them up too.
+fn migrate(conn: &Connection) {
+    conn.execute(
+        "create table if not exists kana_stats
+(
+    kana          TEXT              not null
+        primary key,
+    attempts      INTEGER default 0 not null,
+    errors        INTEGER default 0 not null,
+    helps         INTEGER default 0 not null,
+    total_time_ms INTEGER default 0 not null
+);",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists kana_confusions
+(
+    kana          TEXT              not null,
+    confused_with TEXT              not null,
+    count         INTEGER default 0 not null,
+    primary key (kana, confused_with)
+);",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists speed_runs
+(
+    id         INTEGER
+        primary key autoincrement,
+    mode       TEXT    not null,
+    run_limit  TEXT    not null,
+    taken_at   TEXT    not null,
+    answers    INTEGER not null,
+    correct    INTEGER not null,
+    per_minute REAL    not null,
+    accuracy   REAL    not null,
+    median_ms  INTEGER not null
+);",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists kanji
+(
+    id           INTEGER
+        primary key autoincrement,
+    literal      TEXT              not null
+        unique,
+    on_readings  TEXT              not null,
+    kun_readings TEXT              not null,
+    meanings     TEXT              not null,
+    strokes      INTEGER default 0 not null,
+    grade        INTEGER,
+    jlpt         INTEGER,
+    radical      INTEGER default 0 not null,
+    components   TEXT              not null
+);",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists kanji_card_stats
+(
+    id          INTEGER
+        primary key autoincrement,
+    kanji_id    INTEGER           not null
+        references kanji
+            on delete cascade,
+    set_id      TEXT              not null
+        references card_set
+            on delete cascade,
+    score       INTEGER default 1 not null,
+    last_opened integer           not null
+);",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists word_confusions
+(
+    word_id       INTEGER           not null
+        references words
+            on delete cascade,
+    confused_with INTEGER           not null
+        references words
+            on delete cascade,
+    count         INTEGER default 0 not null,
+    primary key (word_id, confused_with)
+);",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists exams
+(
+    id       INTEGER
+        primary key autoincrement,
+    source   TEXT    not null,
+    taken_at TEXT    not null,
+    total    INTEGER not null,
+    correct  INTEGER not null
+);",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists exam_answers
+(
+    id         INTEGER
+        primary key autoincrement,
+    exam_id    INTEGER not null
+        references exams
+            on delete cascade,
+    position   INTEGER not null,
+    kind       TEXT    not null,
+    prompt     TEXT    not null,
+    expected   TEXT    not null,
+    given      TEXT    not null,
+    is_correct INTEGER not null
+);",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists jmdict_entries
+(
+    id     INTEGER           not null
+        primary key,
+    common INTEGER default 0 not null,
+    entry  TEXT              not null
+);",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists jmdict_forms
+(
+    form     TEXT    not null,
+    entry_id INTEGER not null
+        references jmdict_entries
+            on delete cascade,
+    primary key (form, entry_id)
+) without rowid;",
+        (),
+    )
+    .unwrap();
+    conn.execute(
+        "create table if not exists settings
+(
+    key   TEXT not null
+        primary key,
+    value TEXT not null
+);",
+        (),
+    )
+    .unwrap();
+    if !has_column(conn, "card_set", "kind") {
+        conn.execute(
+            "alter table card_set add column kind TEXT default 'words' not null",
+            (),
+        )
+        .unwrap();
+    }
+    if !has_column(conn, "card_set", "furigana") {
+        conn.execute(
+            "alter table card_set add column furigana TEXT default 'never' not null",
+            (),
+        )
+        .unwrap();
+    }
+    if !has_column(conn, "word_group", "fields") {
+        conn.execute(
+            "alter table word_group add column fields TEXT default '[]' not null",
+            (),
+        )
+        .unwrap();
+    }
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+    let mut stmt = conn
+        .prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))
+        .unwrap();
+    stmt.query_map([], |row| row.get::<_, String>(0))
+        .unwrap()
+        .flatten()
+        .any(|name| name == column)
 }
 
 fn create_tables(conn: &Connection) {
@@ -82,47 +286,71 @@ values (\"Слова\");",
 }
 
 pub fn add_word(word: &mut WordData, connection: &Connection) {
-    let index = connection
-        .query_row(
-            "INSERT INTO words (key, value, tags, more, group_id) VALUES (?1, ?2, ?3, ?4, ?5\
-            ) RETURNING id",
-            (
-                &word.key,
-                &word.value,
-                &word.tags,
-                serde_json::to_string(&word.additional).unwrap(),
-                &word.group_id,
-            ),
-            |row| row.get(0),
-        )
-        .unwrap_or_else(|e| {
-            println!("{}", e);
-            0
-        });
-
-    word.id = index;
+    word.id = insert_word(word, connection).unwrap_or_else(|e| {
+        println!("{}", e);
+        0
+    });
 }
 
 pub fn update_word(word: &mut WordData, connection: &Connection) {
     if word.id == 0 {
         add_word(word, &connection);
     } else {
-        connection
-            .execute(
-                "UPDATE words SET key = ?1, value = ?2, tags = ?3, more = ?4 WHERE id = ?5",
-                (
-                    &word.key,
-                    &word.value,
-                    &word.tags,
-                    serde_json::to_string(&word.additional).unwrap(),
-                    &word.id,
-                ),
-            )
-            .unwrap_or_else(|e| {
-                println!("{}", e);
-                0
-            });
+        write_word(word, connection).unwrap_or_else(|e| {
+            println!("{}", e);
+            0
+        });
+    }
+}
+
+/// Saves the words in one transaction: all of them or, on the first
+/// error, none.
+pub fn update_words(words: &mut [WordData], connection: &Connection) -> rusqlite::Result<()> {
+    let transaction = connection.unchecked_transaction()?;
+    let mut ids = vec![];
+    for word in words.iter() {
+        ids.push(match word.id {
+            0 => insert_word(word, &transaction)?,
+            id => {
+                write_word(word, &transaction)?;
+                id
+            }
+        });
     }
+    transaction.commit()?;
+    // new words get their ids only once they are surely stored
+    for (word, id) in words.iter_mut().zip(ids) {
+        word.id = id;
+    }
+    Ok(())
+}
+
+fn insert_word(word: &WordData, connection: &Connection) -> rusqlite::Result<u32> {
+    connection.query_row(
+        "INSERT INTO words (key, value, tags, more, group_id) VALUES (?1, ?2, ?3, ?4, ?5\
+        ) RETURNING id",
+        (
+            &word.key,
+            &word.value,
+            &word.tags,
+            serde_json::to_string(&word.additional).unwrap(),
+            &word.group_id,
+        ),
+        |row| row.get(0),
+    )
+}
+
+fn write_word(word: &WordData, connection: &Connection) -> rusqlite::Result<usize> {
+    connection.execute(
+        "UPDATE words SET key = ?1, value = ?2, tags = ?3, more = ?4 WHERE id = ?5",
+        (
+            &word.key,
+            &word.value,
+            &word.tags,
+            serde_json::to_string(&word.additional).unwrap(),
+            &word.id,
+        ),
+    )
 }
 
 pub fn delete_word(word: &WordData, connection: &Connection) {
@@ -165,13 +393,15 @@ pub fn load_words(connection: &Connection) -> Vec<WordData> {
 
 pub fn load_word_groups(connection: &Connection) -> Vec<WordGroup> {
     let mut stmt = connection
-        .prepare("SELECT id, name FROM word_group")
+        .prepare("SELECT id, name, fields FROM word_group")
         .unwrap();
     let group_iter = stmt
         .query_map([], |row| {
+            let fields: String = row.get(2)?;
             Ok(WordGroup {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                fields: serde_json::from_str::<Vec<FieldSpec>>(&fields).unwrap_or_default(),
             })
         })
         .unwrap();
@@ -187,8 +417,8 @@ pub fn load_word_groups(connection: &Connection) -> Vec<WordGroup> {
 pub fn add_group(group: &mut WordGroup, connection: &Connection) {
     let index = connection
         .query_row(
-            "INSERT INTO word_group (name) VALUES (?1) RETURNING id",
-            (&group.name,),
+            "INSERT INTO word_group (name, fields) VALUES (?1, ?2) RETURNING id",
+            (&group.name, serde_json::to_string(&group.fields).unwrap()),
             |row| row.get(0),
         )
         .unwrap_or_else(|e| {
@@ -205,8 +435,12 @@ pub fn update_group(group: &mut WordGroup, connection: &Connection) {
     } else {
         connection
             .execute(
-                "UPDATE word_group SET name = ?1 WHERE id = ?5",
-                (&group.name, &group.id),
+                "UPDATE word_group SET name = ?1, fields = ?2 WHERE id = ?3",
+                (
+                    &group.name,
+                    serde_json::to_string(&group.fields).unwrap(),
+                    &group.id,
+                ),
             )
             .unwrap_or_else(|e| {
                 println!("{}", e);
@@ -226,3 +460,104 @@ pub fn delete_group(group: &WordGroup, connection: &Connection) {
             0
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::answer_check::{AnswerRules, ANSWER_RULES};
+    use crate::data_provider::jmdict::{find_jmdict, jmdict_size, save_jmdict};
+    use crate::data_provider::settings::{load_setting, save_setting};
+    use crate::jmdict::JmdictEntry;
+
+    fn database() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute("PRAGMA foreign_keys = ON;", []).unwrap();
+        create_tables(&connection);
+        migrate(&connection);
+        connection
+    }
+
+    fn word(key: &str, group_id: u32) -> WordData {
+        WordData {
+            key: key.to_string(),
+            group_id,
+            ..WordData::new()
+        }
+    }
+
+    #[test]
+    fn words_are_saved_together() {
+        let connection = database();
+        let mut words = vec![word("猫", 1), word("犬", 1)];
+
+        update_words(&mut words, &connection).unwrap();
+        words[0].additional.insert("reading".to_string(), "ねこ".to_string());
+        update_words(&mut words, &connection).unwrap();
+
+        let stored = load_words(&connection);
+        assert_eq!(stored.len(), 2);
+        assert!(words.iter().all(|w| w.id != 0));
+        assert_eq!(stored[0].additional.get("reading").unwrap(), "ねこ");
+    }
+
+    #[test]
+    fn failed_word_rolls_back_the_others() {
+        let connection = database();
+        let mut words = vec![word("猫", 1), word("犬", 999)];
+
+        assert!(update_words(&mut words, &connection).is_err());
+
+        assert!(load_words(&connection).is_empty());
+        assert!(words.iter().all(|w| w.id == 0));
+    }
+
+    #[test]
+    fn answer_rules_are_kept_between_runs() {
+        let connection = database();
+        let default: AnswerRules = load_setting(ANSWER_RULES, &connection);
+        assert_eq!(default, AnswerRules::default());
+
+        let rules = AnswerRules {
+            kana_equivalence: true,
+            typo_tolerance: false,
+            ..AnswerRules::default()
+        };
+        save_setting(ANSWER_RULES, &rules, &connection);
+        save_setting(ANSWER_RULES, &rules, &connection);
+
+        let loaded: AnswerRules = load_setting(ANSWER_RULES, &connection);
+        assert_eq!(loaded, rules);
+    }
+
+    fn entry(id: u32, kanji: &str) -> JmdictEntry {
+        JmdictEntry {
+            id,
+            kanji: vec![kanji.to_string()],
+            ..JmdictEntry::default()
+        }
+    }
+
+    #[test]
+    fn jmdict_import_replaces_the_old_one() {
+        let connection = database();
+        save_jmdict(&[entry(1, "猫"), entry(2, "犬")], &connection).unwrap();
+        save_jmdict(&[entry(3, "鳥")], &connection).unwrap();
+
+        assert_eq!(jmdict_size(&connection), 1);
+        assert!(find_jmdict("猫", &connection).is_empty());
+        assert_eq!(find_jmdict("鳥", &connection)[0].id, 3);
+    }
+
+    #[test]
+    fn failed_jmdict_import_keeps_the_old_one() {
+        let connection = database();
+        save_jmdict(&[entry(1, "猫")], &connection).unwrap();
+        // the import table can't be made in place of a view
+        connection
+            .execute_batch("CREATE VIEW jmdict_forms_import AS SELECT 1;")
+            .unwrap();
+
+        assert!(save_jmdict(&[entry(2, "犬")], &connection).is_err());
+        assert_eq!(find_jmdict("猫", &connection)[0].id, 1);
+    }
+}
@@ -1,9 +1,23 @@
+use crate::data_provider::jmdict::{jmdict_size, lookup_jmdict};
+use crate::data_provider::settings::save_setting;
 use crate::data_provider::words::{delete_group, delete_word, update_group, update_word};
 use crate::dictionary::DictionaryMessage::{
     ChangeDirection, DeleteGroup, EditGroup, SaveGroup, Test,
 };
-use crate::dictionary_test::DictionaryQuizState;
+use crate::answer_check::{AnswerRules, ANSWER_RULES};
+use crate::dictionary_test::{AnswerMode, DictionaryQuizState};
+use crate::exam::ExamState;
+use crate::fields::{field_errors, field_input, word_fields, FieldKind};
+use crate::group_fields::GroupFieldsState;
+use crate::jmdict::{fill_word, import_jmdict, suggestions_view, GlossLanguage, JmdictEntry, GLOSS_LANGUAGE};
+use crate::kana_input::{kana_input, mode_toggle, KanaInputMode};
 use crate::lang::{WordData, WordGroup};
+use crate::matching::MatchingState;
+use crate::reading_review::ReadingReviewState;
+use crate::media::{image_names, thumbnail};
+use crate::pitch::{find_accent, read_accent_file, set_accent, ACCENT};
+use crate::speed_run::RunLimit;
+use crate::worksheet::{vocabulary_worksheet, worksheet_dir};
 use crate::word::WordState;
 use crate::Page::Word;
 use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
@@ -15,15 +29,18 @@ use iced::widget::space::horizontal;
 use iced::widget::*;
 use iced::{Border, Color, Length, Shadow, Task};
 use rand::random_range;
-use rayon::iter::IndexedParallelIterator;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::ops::Add;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio::task::spawn_blocking;
 use DictionaryMessage::Back;
 
+/// JMdict entries shown for a typed key.
+pub const SUGGESTIONS: usize = 8;
+
 #[derive(Clone)]
 pub struct DictionaryState {
     state: Arc<Mutex<AppState>>,
@@ -31,10 +48,20 @@ pub struct DictionaryState {
     tag_map: HashMap<String, bool>,
     reverse: bool,
     search: String,
-    no_typing: bool,
+    answer_mode: AnswerMode,
     selected_group_index: usize,
     reverse_list: bool,
     auto_save_queue: HashMap<usize, DateTime<Utc>>,
+    run_limit: RunLimit,
+    group_status: String,
+    kana_mode: KanaInputMode,
+    rules: AnswerRules,
+    /// JMdict entries for the key typed last and the row it was typed in.
+    suggestions: Vec<JmdictEntry>,
+    lookup_row: Option<usize>,
+    gloss_language: GlossLanguage,
+    jmdict_status: String,
+    importing: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +70,7 @@ pub enum DictionaryMessage {
     SetTags(usize, String),
     SetKey(usize, String),
     SetValue(usize, String),
+    SetField(usize, String, String),
     SubmitWord(usize),
     WordAction(usize),
     NewWord,
@@ -52,7 +80,8 @@ pub enum DictionaryMessage {
     ResetTags,
     SetReverse(bool),
     Search(String),
-    SetTyping(bool),
+    SetAnswerMode(AnswerMode),
+    SetRunLimit(RunLimit),
     CreateGroup,
     EditGroup(String),
     SaveGroup,
@@ -60,6 +89,20 @@ pub enum DictionaryMessage {
     DeleteGroup,
     ChangeDirection,
     TrySave(usize),
+    SetKanaMode(KanaInputMode),
+    SetRules(AnswerRules),
+    MakeWorksheet,
+    FillAccents,
+    /// (id, reading, accent) of the words the accent dictionary knows.
+    AccentsFilled(Result<Vec<(u32, String, String)>, String>),
+    Matching,
+    Exam,
+    EditFields,
+    ReviewReadings,
+    FillFromJmdict(usize),
+    SetGlossLanguage(GlossLanguage),
+    ImportJmdict,
+    JmdictImported(Result<usize, String>),
 }
 
 impl NavigatedPage<DictionaryMessage> for DictionaryState {
@@ -69,24 +112,46 @@ impl NavigatedPage<DictionaryMessage> for DictionaryState {
         }
         if let Test = message {
             if self.include_map.iter().any(|x| *x) {
-                let mut words = vec![];
-                let dict = &self.state.lock().unwrap().dictionary;
-
-                words = self
-                    .include_map
-                    .iter()
-                    .zip(0..self.include_map.len())
-                    .filter(|(flag, _)| **flag)
-                    .map(|(_, index)| dict[index].clone())
-                    .collect();
+                let words = self.selected_words();
 
                 return Some(Page::DictionaryQuiz(DictionaryQuizState::new(
                     words,
                     self.reverse,
-                    self.no_typing,
+                    self.answer_mode,
+                    self.run_limit,
+                    self.rules,
+                    self.state.clone(),
                 )));
             }
         }
+        if let DictionaryMessage::Exam = message {
+            let pool = self.state.lock().unwrap().dictionary.clone();
+            return Some(Page::Exam(ExamState::new(
+                self.selected_words(),
+                pool,
+                "Словарь".to_string(),
+                self.state.clone(),
+            )));
+        }
+        if let DictionaryMessage::EditFields = message {
+            return Some(Page::GroupFields(GroupFieldsState::new(
+                self.selected_group_index,
+                self.state.clone(),
+            )));
+        }
+        if let DictionaryMessage::ReviewReadings = message {
+            return Some(Page::ReadingReview(ReadingReviewState::new(
+                self.selected_group_index,
+                self.state.clone(),
+            )));
+        }
+        if let DictionaryMessage::Matching = message {
+            return Some(Page::Matching(MatchingState::new(
+                self.selected_words(),
+                None,
+                self.state.clone(),
+            )));
+        }
         if let DictionaryMessage::WordAction(index) = message {
             let word: WordData;
             {
@@ -104,7 +169,16 @@ impl NavigatedPage<DictionaryMessage> for DictionaryState {
 
 impl DictionaryState {
     pub fn new(state: Arc<Mutex<AppState>>) -> Self {
-        let len = state.lock().unwrap().dictionary.len();
+        let (len, kana_mode, gloss_language, rules, jmdict) = {
+            let state = state.lock().unwrap();
+            (
+                state.dictionary.len(),
+                state.kana_input,
+                state.gloss_language,
+                state.answer_rules,
+                jmdict_size(&state.connection),
+            )
+        };
 
         let mut result = DictionaryState {
             include_map: vec![false; len],
@@ -113,9 +187,22 @@ impl DictionaryState {
             tag_map: HashMap::new(),
             reverse: false,
             search: "".to_string(),
-            no_typing: true,
+            answer_mode: AnswerMode::Reveal,
             reverse_list: true,
             auto_save_queue: HashMap::new(),
+            run_limit: RunLimit::Off,
+            group_status: String::new(),
+            kana_mode,
+            rules,
+            suggestions: vec![],
+            lookup_row: None,
+            gloss_language,
+            jmdict_status: if jmdict > 0 {
+                format!("Слов в JMdict: {}", jmdict)
+            } else {
+                String::new()
+            },
+            importing: false,
         };
 
         result.update_tags();
@@ -123,6 +210,17 @@ impl DictionaryState {
         result
     }
 
+    /// The words ticked in the list.
+    fn selected_words(&self) -> Vec<WordData> {
+        let dict = &self.state.lock().unwrap().dictionary;
+        self.include_map
+            .iter()
+            .zip(dict)
+            .filter(|(flag, _)| **flag)
+            .map(|(_, word)| word.clone())
+            .collect()
+    }
+
     pub fn update(&mut self, message: DictionaryMessage) -> Task<RootMessage> {
         match message {
             DictionaryMessage::NewWord => {
@@ -137,8 +235,10 @@ impl DictionaryState {
 
             DictionaryMessage::SetKey(i, v) => {
                 {
-                    let dict = &mut self.state.lock().unwrap().dictionary;
-                    dict[i].key = v;
+                    let state = &mut *self.state.lock().unwrap();
+                    self.suggestions = lookup_jmdict(v.trim(), SUGGESTIONS, &state.connection);
+                    self.lookup_row = Some(i);
+                    state.dictionary[i].key = v;
                 }
                 return self.launch_auto_save_offset(i);
             }
@@ -149,6 +249,13 @@ impl DictionaryState {
                 }
                 return self.launch_auto_save_offset(i);
             }
+            DictionaryMessage::SetField(i, name, v) => {
+                {
+                    let dict = &mut self.state.lock().unwrap().dictionary;
+                    dict[i].additional.insert(name, v);
+                }
+                return self.launch_auto_save_offset(i);
+            }
             DictionaryMessage::SetTags(i, v) => {
                 {
                     let dict = &mut self.state.lock().unwrap().dictionary;
@@ -165,6 +272,8 @@ impl DictionaryState {
                 let word = dict.remove(i);
                 self.include_map.remove(i);
                 self.auto_save_queue.remove(&i);
+                self.suggestions.clear();
+                self.lookup_row = None;
                 delete_word(&word, &state.connection)
             }
             DictionaryMessage::Include(i, b) => self.include_map[i] = b,
@@ -186,16 +295,77 @@ impl DictionaryState {
             DictionaryMessage::Search(s) => {
                 self.search = s;
             }
-            DictionaryMessage::SetTyping(b) => self.no_typing = b,
-            DictionaryMessage::SubmitWord(i) => self.save_word(i),
+            DictionaryMessage::SetAnswerMode(mode) => self.answer_mode = mode,
+            DictionaryMessage::SetRunLimit(limit) => self.run_limit = limit,
+            DictionaryMessage::SubmitWord(i) => {
+                {
+                    let dict = &mut self.state.lock().unwrap().dictionary;
+                    dict[i].key = self.kana_mode.finish(&dict[i].key);
+                }
+                self.save_word(i)
+            }
+            DictionaryMessage::SetRules(rules) => {
+                self.rules = rules;
+                let state = &mut *self.state.lock().unwrap();
+                state.answer_rules = rules;
+                save_setting(ANSWER_RULES, &rules, &state.connection);
+            }
+            DictionaryMessage::SetKanaMode(mode) => {
+                self.kana_mode = mode;
+                self.state.lock().unwrap().kana_input = mode;
+            }
             Back => {}
             Test => {}
+            DictionaryMessage::Matching => {}
+            DictionaryMessage::Exam => {}
+            DictionaryMessage::EditFields => {}
+            DictionaryMessage::ReviewReadings => {}
+            DictionaryMessage::FillFromJmdict(n) => {
+                let (Some(i), Some(entry)) = (self.lookup_row, self.suggestions.get(n)) else {
+                    return Task::none();
+                };
+                {
+                    let dict = &mut self.state.lock().unwrap().dictionary;
+                    let typed = dict[i].key.trim().to_string();
+                    fill_word(&mut dict[i], entry, &typed, self.gloss_language);
+                }
+                self.suggestions.clear();
+                self.lookup_row = None;
+                self.auto_save_queue.remove(&i);
+                self.save_word(i);
+                self.update_tags();
+            }
+            DictionaryMessage::SetGlossLanguage(language) => {
+                self.gloss_language = language;
+                let state = &mut *self.state.lock().unwrap();
+                state.gloss_language = language;
+                save_setting(GLOSS_LANGUAGE, &language.code, &state.connection);
+            }
+            DictionaryMessage::ImportJmdict => {
+                if self.importing {
+                    return Task::none();
+                }
+                self.importing = true;
+                self.jmdict_status = "Импорт JMdict…".to_string();
+                return Task::perform(
+                    async { spawn_blocking(import_jmdict).await.unwrap() },
+                    |result| RootMessage::Dictionary(DictionaryMessage::JmdictImported(result)),
+                );
+            }
+            DictionaryMessage::JmdictImported(result) => {
+                self.importing = false;
+                self.jmdict_status = match result {
+                    Ok(count) => format!("Слов в JMdict: {}", count),
+                    Err(e) => e,
+                };
+            }
             DictionaryMessage::CreateGroup => {
                 let state = &mut self.state.lock().unwrap();
 
                 state.word_groups.push(WordGroup {
                     id: 0,
                     name: format!("Группа слов {}", random_range(100..1000)),
+                    fields: vec![],
                 });
             }
             EditGroup(new) => {
@@ -243,6 +413,74 @@ impl DictionaryState {
             ChangeDirection => {
                 self.reverse_list = !self.reverse_list;
             }
+            DictionaryMessage::MakeWorksheet => {
+                let state = self.state.lock().unwrap();
+                let Some(group) = state.word_groups.get(self.selected_group_index) else {
+                    return Task::none();
+                };
+                let words = state
+                    .dictionary
+                    .iter()
+                    .filter(|word| word.group_id == group.id)
+                    .cloned()
+                    .collect::<Vec<WordData>>();
+
+                self.group_status = match vocabulary_worksheet(group, &words) {
+                    Ok(_) => format!("Сохранено в {}", worksheet_dir().display()),
+                    Err(e) => e.to_string(),
+                };
+            }
+            DictionaryMessage::FillAccents => {
+                let words = {
+                    let state = self.state.lock().unwrap();
+                    let group_id = state.word_groups[self.selected_group_index].id;
+                    state
+                        .dictionary
+                        .iter()
+                        .filter(|w| w.group_id == group_id && w.id != 0)
+                        .filter(|w| w.additional.get(ACCENT).is_none_or(|a| a.is_empty()))
+                        .cloned()
+                        .collect::<Vec<WordData>>()
+                };
+                self.group_status = "Поиск ударений…".to_string();
+                return Task::perform(
+                    async {
+                        spawn_blocking(move || {
+                            let dictionary = read_accent_file()?;
+                            Ok(words
+                                .iter()
+                                .filter_map(|w| {
+                                    let (reading, accent) = find_accent(w, &dictionary)?;
+                                    Some((w.id, reading, accent))
+                                })
+                                .collect())
+                        })
+                        .await
+                        .unwrap()
+                    },
+                    |filled| RootMessage::Dictionary(DictionaryMessage::AccentsFilled(filled)),
+                );
+            }
+            DictionaryMessage::AccentsFilled(filled) => {
+                self.group_status = match filled {
+                    Ok(found) => {
+                        // the words may have been edited during the search, so
+                        // only the accent goes into the current ones
+                        let state = &mut *self.state.lock().unwrap();
+                        let mut filled = 0;
+                        for (id, reading, accent) in &found {
+                            if let Some(word) = state.dictionary.iter_mut().find(|w| w.id == *id)
+                                && set_accent(word, reading, accent)
+                            {
+                                update_word(word, &state.connection);
+                                filled += 1;
+                            }
+                        }
+                        format!("Ударения найдены для {} слов", filled)
+                    }
+                    Err(e) => e,
+                };
+            }
             DictionaryMessage::TrySave(word_index) => {
                 let now = Utc::now();
                 if !self.auto_save_queue.contains_key(&word_index) {
@@ -263,6 +501,12 @@ impl DictionaryState {
         let connection = &state.connection;
         let word = &mut state.dictionary.get(i).unwrap().clone();
 
+        // the Word page doesn't save such a word either
+        let errors = field_errors(word, word_fields(word, &state.word_groups));
+        if let Some(error) = errors.first() {
+            self.group_status = format!("«{}» не сохранено: {}", word.key, error);
+            return;
+        }
         update_word(word, &connection);
         state.dictionary[i] = word.clone();
     }
@@ -284,6 +528,7 @@ impl DictionaryState {
                     button("Назад").on_press(Back),
                     self.groups_panel(),
                     self.words_list(),
+                    self.suggestions_panel(),
                     button("Добавить слово").on_press(DictionaryMessage::NewWord),
                 ]
                 .spacing(5),
@@ -300,7 +545,9 @@ impl DictionaryState {
 
         let mut range = (0..self.include_map.len()).collect::<Vec<_>>();
         let state = self.state.lock().unwrap();
-        let group_id = state.word_groups[self.selected_group_index].id;
+        let group = &state.word_groups[self.selected_group_index];
+        let group_id = group.id;
+        let columns = group.fields.iter().filter(|f| f.in_list).collect::<Vec<_>>();
         let dict = &mut state.dictionary.clone();
 
         if self.reverse_list {
@@ -314,6 +561,11 @@ impl DictionaryState {
             if !self.search.is_empty() {
                 if word.key.contains(&self.search) == false
                     && word.value.contains(&self.search) == false
+                    && !group.fields.iter().any(|f| {
+                        word.additional
+                            .get(&f.name)
+                            .is_some_and(|v| v.contains(&self.search))
+                    })
                 {
                     continue;
                 }
@@ -332,10 +584,11 @@ impl DictionaryState {
                 .push(space().width(10));
 
             line = line.push(
-                text_input("Слово", &word.key)
+                kana_input("Слово", &word.key, self.kana_mode, move |string| {
+                    DictionaryMessage::SetKey(i, string)
+                })
                     .size(20)
                     .width(Length::Fill)
-                    .on_input(move |string| DictionaryMessage::SetKey(i, string))
                     .on_submit(DictionaryMessage::SubmitWord(i)),
             );
             line = line.push(
@@ -352,6 +605,20 @@ impl DictionaryState {
                     .on_input(move |string| DictionaryMessage::SetTags(i, string))
                     .on_submit(DictionaryMessage::SubmitWord(i)),
             );
+            for spec in &columns {
+                let value = word.additional.get(&spec.name).map_or("", |v| v);
+                let name = spec.name.clone();
+                let input = match spec.kind {
+                    // edited on the Word page, the first line is enough here
+                    FieldKind::Multiline => text!("{}", value.lines().next().unwrap_or(""))
+                        .width(Length::Fill)
+                        .into(),
+                    _ => field_input(spec, value, self.kana_mode, move |string| {
+                        DictionaryMessage::SetField(i, name.clone(), string)
+                    }),
+                };
+                line = line.push(container(input).width(Length::Fill));
+            }
 
             let line_button = || {
                 let action = DictionaryMessage::WordAction(i);
@@ -371,6 +638,9 @@ impl DictionaryState {
                     .width(15)
             };
 
+            if let Some(name) = image_names(word).first() {
+                line = line.push(thumbnail(name, 32.0));
+            }
             line = line.push(line_button()).push(space().width(10));
 
             col = col.push(line);
@@ -379,34 +649,121 @@ impl DictionaryState {
         scrollable(col).height(Length::Fill).into()
     }
 
+    /// JMdict entries for the key being typed, filling the row on a click.
+    fn suggestions_panel(&self) -> iced::Element<'_, DictionaryMessage> {
+        let Some(i) = self.lookup_row.filter(|_| !self.suggestions.is_empty()) else {
+            return space().into();
+        };
+        let typed = self.state.lock().unwrap().dictionary[i].key.trim().to_string();
+        iced::widget::column![
+            row![
+                text!("JMdict").width(Length::Fill),
+                pick_list(
+                    GlossLanguage::ALL,
+                    Some(self.gloss_language),
+                    DictionaryMessage::SetGlossLanguage
+                ),
+            ]
+            .align_y(Center),
+            scrollable(suggestions_view(
+                &self.suggestions,
+                &typed,
+                self.gloss_language,
+                DictionaryMessage::FillFromJmdict
+            ))
+            .height(150),
+        ]
+        .spacing(5)
+        .into()
+    }
+
     fn filters(&self) -> iced::Element<'_, DictionaryMessage> {
         let dict = &self.state.lock().unwrap().dictionary;
 
         iced::widget::column![
+            // the search takes meanings as well, so it is left to the system input
             text_input("Поиск", &self.search)
                 .on_input(DictionaryMessage::Search)
                 .width(Length::Fill),
+            row![
+                text!("Ввод слов"),
+                mode_toggle(self.kana_mode, DictionaryMessage::SetKanaMode),
+            ]
+            .spacing(5)
+            .align_y(iced::Center),
             text!("Всего слов: {}", dict.len()),
             text!(
                 "Выбрано слов: {}",
                 self.include_map.iter().filter(|i| **i).count()
             ),
             self.tags_selector(),
-            toggler(self.no_typing)
-                .label("Без набора")
-                .on_toggle(DictionaryMessage::SetTyping),
+            pick_list(
+                AnswerMode::ALL,
+                Some(self.answer_mode),
+                DictionaryMessage::SetAnswerMode
+            )
+            .width(Length::Fill),
             toggler(self.reverse)
                 .label("Обратный тест")
                 .on_toggle(DictionaryMessage::SetReverse),
+            self.rules_selector(),
+            pick_list(
+                RunLimit::ALL,
+                Some(self.run_limit),
+                DictionaryMessage::SetRunLimit
+            )
+            .width(Length::Fill),
             button(text!("Тест").center().width(Length::Fill))
                 .on_press(Test)
                 .width(Length::Fill),
+            button(text!("Пары на время").center().width(Length::Fill))
+                .on_press_maybe(
+                    (self.include_map.iter().filter(|x| **x).count() > 1)
+                        .then_some(DictionaryMessage::Matching)
+                )
+                .width(Length::Fill),
+            button(text!("Экзамен").center().width(Length::Fill))
+                .on_press_maybe(
+                    self.include_map
+                        .iter()
+                        .any(|x| *x)
+                        .then_some(DictionaryMessage::Exam)
+                )
+                .width(Length::Fill),
+            button(text!("Импорт JMdict").center().width(Length::Fill))
+                .on_press(DictionaryMessage::ImportJmdict)
+                .width(Length::Fill),
+            text!("{}", self.jmdict_status),
         ]
         .width(250)
         .spacing(DEFAULT_SPACING)
         .into()
     }
 
+    fn rules_selector(&self) -> iced::Element<'_, DictionaryMessage> {
+        let rules = self.rules;
+        let rule = |label: &'static str, value: bool, set: fn(&mut AnswerRules, bool)| {
+            toggler(value).label(label).on_toggle(move |b| {
+                let mut changed = rules;
+                set(&mut changed, b);
+                DictionaryMessage::SetRules(changed)
+            })
+        };
+
+        iced::widget::column![
+            text!("Засчитывать ответ"),
+            rule("Полная/половинная ширина", rules.fold_width, |r, b| r.fold_width = b),
+            rule("Хирагана = катакана", rules.kana_equivalence, |r, b| {
+                r.kana_equivalence = b
+            }),
+            rule("ー как долгая гласная", rules.long_vowels, |r, b| r.long_vowels = b),
+            rule("Без пометок в скобках", rules.strip_notes, |r, b| r.strip_notes = b),
+            rule("С опечатками", rules.typo_tolerance, |r, b| r.typo_tolerance = b),
+        ]
+        .spacing(5)
+        .into()
+    }
+
     fn tags_selector(&self) -> iced::Element<'_, DictionaryMessage> {
         let mut col = Column::new().width(Length::Fill);
         col = col.push(
@@ -520,7 +877,12 @@ impl DictionaryState {
                     .on_input(EditGroup)
                     .width(250)
                     .on_submit(SaveGroup),
+                text!("{}", self.group_status),
                 horizontal(),
+                button("Поля").on_press(DictionaryMessage::EditFields),
+                button("Чтения").on_press(DictionaryMessage::ReviewReadings),
+                button("Ударения").on_press(DictionaryMessage::FillAccents),
+                button("Лист для печати").on_press(DictionaryMessage::MakeWorksheet),
                 button("Удалить").style(danger).on_press(DeleteGroup),
             ]
             .spacing(DEFAULT_SPACING)
@@ -1,19 +1,29 @@
-use crate::dictionary::{split_with_coma};
 use crate::quiz::Score;
+use crate::speed_run::{finish_run, summary_view, RunLimit, RunRecord, SpeedRun};
 use crate::Page::PreviousPage;
-use crate::{RootMessage, DEFAULT_SPACING};
+use crate::{AppState, RootMessage, DEFAULT_SPACING};
 use crate::{NavigatedPage, Page};
 use iced::border::Radius;
 use iced::widget::container::Style;
-use iced::widget::{button, container, row, space, text, text_input, Row};
+use iced::widget::{button, column, container, pick_list, row, scrollable, space, text, Row};
 use iced::Background::Color;
 use iced::{alignment, Border, Element, Fill, Task, Theme};
 use rand::prelude::SliceRandom;
-use crate::lang::WordData;
+use crate::choices::{record_word_confusion, Choice};
+use crate::answer_check::{check_answer, AnswerMatch, AnswerRules};
+use crate::kana_input::{kana_input, mode_toggle, KanaInputMode};
+use crate::lang::{CardSet, WordData};
+use crate::repetitions::CardKind;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct DictionaryQuizState {
     words: Vec<WordData>,
+    /// Words of the current round: all of them, or the missed ones on a
+    /// mistakes lap.
+    lap_words: Vec<WordData>,
     current_set: Vec<WordData>,
     answer: String,
     view: String,
@@ -22,7 +32,24 @@ pub struct DictionaryQuizState {
     is_help: bool,
     reverse: bool,
     laps: u32,
-    no_typing: bool,
+    answer_mode: AnswerMode,
+    choice: Option<Choice>,
+    shown_at: Instant,
+    limit: RunLimit,
+    speed_run: Option<SpeedRun>,
+    result: Option<(RunRecord, Vec<RunRecord>)>,
+    kana_mode: KanaInputMode,
+    rules: AnswerRules,
+    /// Shown over the next card when the last answer was only close.
+    note: String,
+    current: Option<WordData>,
+    missed: Vec<WordData>,
+    appealed: Vec<WordData>,
+    mistakes_lap: bool,
+    finished: bool,
+    target_set: Option<SetChoice>,
+    push_status: String,
+    state: Arc<Mutex<AppState>>,
 }
 #[derive(Debug, Clone)]
 pub enum DictionaryQuizMessage {
@@ -30,6 +57,50 @@ pub enum DictionaryQuizMessage {
     AnswerChanged(String),
     SubmitAnswer,
     Appeal,
+    Tick,
+    Restart,
+    SetKanaMode(KanaInputMode),
+    Finish,
+    RepeatMistakes,
+    SelectTargetSet(SetChoice),
+    PushMistakes,
+    Choose(usize),
+}
+
+/// How the answer to a card is given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnswerMode {
+    Typing,
+    /// The answer is revealed and the user moves on, nothing is checked.
+    Reveal,
+    Choice,
+}
+
+impl AnswerMode {
+    pub const ALL: [AnswerMode; 3] = [AnswerMode::Typing, AnswerMode::Reveal, AnswerMode::Choice];
+}
+
+impl Display for AnswerMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnswerMode::Typing => write!(f, "Ввод ответа"),
+            AnswerMode::Reveal => write!(f, "Без набора"),
+            AnswerMode::Choice => write!(f, "Выбор из вариантов"),
+        }
+    }
+}
+
+/// A word card set missed words can be sent to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetChoice {
+    index: usize,
+    name: String,
+}
+
+impl Display for SetChoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
 }
 
 impl NavigatedPage<DictionaryQuizMessage> for DictionaryQuizState {
@@ -45,9 +116,14 @@ impl DictionaryQuizState {
     pub fn new(
         words: Vec<WordData>,
         reverse: bool,
-        no_typing: bool,
+        answer_mode: AnswerMode,
+        limit: RunLimit,
+        rules: AnswerRules,
+        state: Arc<Mutex<AppState>>,
     ) -> DictionaryQuizState {
+        let kana_mode = state.lock().unwrap().kana_input;
         DictionaryQuizState {
+            lap_words: words.clone(),
             words,
             current_set: Vec::new(),
             answer: "".to_string(),
@@ -57,7 +133,23 @@ impl DictionaryQuizState {
             is_help: false,
             reverse,
             laps: 0,
-            no_typing,
+            answer_mode,
+            choice: None,
+            shown_at: Instant::now(),
+            limit,
+            speed_run: SpeedRun::new(limit),
+            result: None,
+            kana_mode,
+            rules,
+            note: String::new(),
+            current: None,
+            missed: vec![],
+            appealed: vec![],
+            mistakes_lap: false,
+            finished: false,
+            target_set: None,
+            push_status: String::new(),
+            state,
         }
     }
 
@@ -65,17 +157,151 @@ impl DictionaryQuizState {
         match message {
             DictionaryQuizMessage::Back => {}
             DictionaryQuizMessage::AnswerChanged(c) => self.answer = c.clone(),
-            DictionaryQuizMessage::SubmitAnswer => self.submit(),
+            DictionaryQuizMessage::SubmitAnswer => {
+                self.answer = self.answer_kana_mode().finish(&self.answer);
+                self.submit()
+            }
+            DictionaryQuizMessage::SetKanaMode(mode) => {
+                self.kana_mode = mode;
+                self.state.lock().unwrap().kana_input = mode;
+            }
             DictionaryQuizMessage::Appeal => self.appeal_answer(),
+            DictionaryQuizMessage::Tick => {}
+            DictionaryQuizMessage::Restart => {
+                self.lap_words = self.words.clone();
+                self.mistakes_lap = false;
+                self.restart(SpeedRun::new(self.limit));
+            }
+            DictionaryQuizMessage::Finish => self.finished = true,
+            DictionaryQuizMessage::RepeatMistakes => {
+                if self.missed.is_empty() {
+                    return Task::none();
+                }
+                self.lap_words = self.missed.clone();
+                self.mistakes_lap = true;
+                // a lap of a few missed words is no speed run
+                self.restart(None);
+            }
+            DictionaryQuizMessage::SelectTargetSet(choice) => self.target_set = Some(choice),
+            DictionaryQuizMessage::PushMistakes => self.push_mistakes(),
+            DictionaryQuizMessage::Choose(index) => self.choose(index),
         }
+        self.check_run();
         Task::none()
     }
 
+    fn restart(&mut self, speed_run: Option<SpeedRun>) {
+        self.score = Default::default();
+        self.laps = 0;
+        self.current_set.clear();
+        self.speed_run = speed_run;
+        self.result = None;
+        self.finished = false;
+        self.missed.clear();
+        self.appealed.clear();
+        self.push_status.clear();
+        self.show_next();
+    }
+
+    fn push_mistakes(&mut self) {
+        let Some(choice) = &self.target_set else {
+            return;
+        };
+        let state = self.state.lock().unwrap();
+        let Some(settings) = state.card_sets.get(choice.index) else {
+            return;
+        };
+        let ids = self
+            .missed
+            .iter()
+            .map(|w| w.id)
+            .filter(|id| *id != 0)
+            .collect::<Vec<u32>>();
+        let count = CardSet::prioritize(settings, &ids, &state);
+        self.push_status = match ids.len() - count {
+            0 => format!("Отправлено в «{}»: {}", settings.name, count),
+            skipped => format!(
+                "Отправлено в «{}»: {}, не подходят под фильтр набора: {}",
+                settings.name, count, skipped
+            ),
+        };
+    }
+
+    /// Whether a timed run is going on and needs the clock ticking.
+    pub fn is_running(&self) -> bool {
+        self.speed_run.is_some() && self.result.is_none() && !self.finished
+    }
+
+    fn check_run(&mut self) {
+        let Some(run) = &self.speed_run else {
+            return;
+        };
+        if self.result.is_none() && run.is_finished() {
+            self.result = Some(finish_run(run, "dictionary", &self.state));
+        }
+    }
+
+    fn record_run_answer(&mut self, correct: bool) {
+        if let Some(run) = &mut self.speed_run {
+            run.answer(correct, self.shown_at.elapsed());
+        }
+    }
+
     pub fn view(&self) -> Element<'_, DictionaryQuizMessage> {
+        if let Some((record, history)) = &self.result {
+            return container(
+                iced::widget::column![
+                    summary_view(record, history),
+                    self.mistakes_view(),
+                    row![
+                        button("Закончить").on_press(DictionaryQuizMessage::Back),
+                        button("Ещё раз").on_press(DictionaryQuizMessage::Restart),
+                    ]
+                    .spacing(DEFAULT_SPACING),
+                ]
+                .spacing(DEFAULT_SPACING)
+                .align_x(alignment::Horizontal::Center),
+            )
+            .center_y(Fill)
+            .center_x(Fill)
+            .into();
+        }
+        if self.finished {
+            return container(
+                iced::widget::column![
+                    text!("Итоги").size(34),
+                    row![
+                        text!("Ответов {}", self.score.total),
+                        text!("Верно {}", self.score.correct),
+                        text!("Ошибок {}", self.score.fail),
+                    ]
+                    .spacing(DEFAULT_SPACING),
+                    self.mistakes_view(),
+                    row![
+                        button("Закончить").on_press(DictionaryQuizMessage::Back),
+                        button("Ещё раз").on_press(DictionaryQuizMessage::Restart),
+                    ]
+                    .spacing(DEFAULT_SPACING),
+                ]
+                .spacing(DEFAULT_SPACING)
+                .align_x(alignment::Horizontal::Center),
+            )
+            .center_y(Fill)
+            .center_x(Fill)
+            .into();
+        }
+
         container(
             iced::widget::column![
                 self.laps(),
+                text!("{}", if self.mistakes_lap { "Повтор ошибок" } else { "" }),
+                text!(
+                    "{}",
+                    self.speed_run.as_ref().map(|r| r.progress()).unwrap_or_default()
+                )
+                .size(20),
                 iced::widget::column![
+                    text!("{}", self.note).size(16),
                     text!("{}", self.view).size(54),
                     text!(
                         "{}",
@@ -88,11 +314,7 @@ impl DictionaryQuizState {
                 ]
                 .align_x(alignment::Horizontal::Center)
                 .spacing(5),
-                text_input("Перевод", &self.answer)
-                    .size(28)
-                    .width(250)
-                    .on_input(DictionaryQuizMessage::AnswerChanged)
-                    .on_submit(DictionaryQuizMessage::SubmitAnswer),
+                self.answer_view(),
                 row![
                     text!("{}", self.score.total.to_string()).size(25),
                     text!("{}", self.score.correct.to_string())
@@ -104,8 +326,9 @@ impl DictionaryQuizState {
                 ]
                 .spacing(DEFAULT_SPACING),
                 row![
-                    button("Закончить").on_press(DictionaryQuizMessage::Back),
-                    self.appeal_button()
+                    button("Закончить").on_press(DictionaryQuizMessage::Finish),
+                    self.appeal_button(),
+                    self.repeat_mistakes_button(),
                 ]
                 .spacing(DEFAULT_SPACING),
             ]
@@ -117,20 +340,97 @@ impl DictionaryQuizState {
         .into()
     }
     fn submit(&mut self) {
+        self.note.clear();
         if self.view == "---" {
             self.show_next();
             return;
         }
 
-        if self.no_typing {
-            self.no_type_submit();
+        match self.answer_mode {
+            AnswerMode::Reveal => self.no_type_submit(),
+            AnswerMode::Typing => self.default_submit(),
+            // too few other words for options: the answer is typed
+            AnswerMode::Choice if self.choice.is_none() => self.default_submit(),
+            // Enter after a wrong pick moves on
+            AnswerMode::Choice if self.is_help => self.show_next(),
+            AnswerMode::Choice => {}
+        }
+    }
+
+    fn answer_view(&self) -> Element<'_, DictionaryQuizMessage> {
+        match (self.answer_mode, &self.choice) {
+            (AnswerMode::Choice, Some(choice)) => choice.view(DictionaryQuizMessage::Choose),
+            (AnswerMode::Choice, None) if self.view == "---" => button("Начать")
+                .on_press(DictionaryQuizMessage::SubmitAnswer)
+                .into(),
+            _ => {
+                let input = kana_input(
+                    "Перевод",
+                    &self.answer,
+                    self.answer_kana_mode(),
+                    DictionaryQuizMessage::AnswerChanged,
+                )
+                .size(28)
+                .width(250)
+                .on_submit(DictionaryQuizMessage::SubmitAnswer);
+                if !self.reverse {
+                    return input.into();
+                }
+                row![input, mode_toggle(self.kana_mode, DictionaryQuizMessage::SetKanaMode)]
+                    .align_y(alignment::Vertical::Center)
+                    .spacing(5)
+                    .into()
+            }
+        }
+    }
+
+    /// Only the word itself is typed in kana; a translation is typed as is.
+    fn answer_kana_mode(&self) -> KanaInputMode {
+        if self.reverse {
+            self.kana_mode
         } else {
-            self.default_submit();
+            KanaInputMode::Off
+        }
+    }
+
+    fn choose(&mut self, index: usize) {
+        self.note.clear();
+        if self.is_help {
+            self.show_next();
+            return;
+        }
+        let Some(choice) = &mut self.choice else {
+            return;
+        };
+        let correct = choice.pick(index);
+        let confused_with = choice.confused_with();
+
+        self.score.total += 1;
+        self.record_run_answer(correct);
+        if correct {
+            self.score.correct += 1;
+            self.show_next();
+            return;
+        }
+        self.score.fail += 1;
+        self.remember_missed();
+        self.is_help = true;
+        if let (Some(word), Some(other)) = (&self.current, confused_with) {
+            record_word_confusion(&mut self.state.lock().unwrap(), word.id, other);
+        }
+    }
+
+    fn remember_missed(&mut self) {
+        if let Some(word) = &self.current
+            && !self.missed.iter().any(|w| w.id == word.id)
+        {
+            self.missed.push(word.clone());
         }
     }
 
     fn no_type_submit(&mut self) {
         if self.is_help {
+            self.record_run_answer(true);
             self.is_help = false;
             self.score.total += 1;
             self.show_next()
@@ -140,24 +440,110 @@ impl DictionaryQuizState {
     }
 
     fn default_submit(&mut self) {
+        let result = check_answer(&self.answer, &self.correct, &self.rules);
+        let is_correct = result != AnswerMatch::Wrong;
         if !self.is_help {
             self.score.total += 1;
+            self.record_run_answer(is_correct);
         }
-        if self.answer == self.correct
-            || split_with_coma(self.correct.as_str()).contains(&self.answer)
-        {
+        if is_correct {
             if self.is_help == false {
                 self.score.correct += 1;
             }
-            self.show_next()
+            let exact = self.correct.clone();
+            self.show_next();
+            if result == AnswerMatch::Close {
+                self.note = format!("Засчитано, точно: {}", exact);
+            }
         } else {
+            if !self.is_help {
+                self.remember_missed();
+            }
             self.score.fail += 1;
             self.is_help = true;
         }
     }
 
+    fn repeat_mistakes_button(&self) -> Element<'_, DictionaryQuizMessage> {
+        if self.missed.is_empty() {
+            return space().into();
+        }
+        button(text!("Только ошибки ({})", self.missed.len()))
+            .on_press(DictionaryQuizMessage::RepeatMistakes)
+            .into()
+    }
+
+    /// Missed and appealed words of the session, with the lap over the
+    /// mistakes and sending them to a card set.
+    fn mistakes_view(&self) -> Element<'_, DictionaryQuizMessage> {
+        if self.missed.is_empty() && self.appealed.is_empty() {
+            return text!("Без ошибок").into();
+        }
+
+        let line = |w: &WordData| format!("{} — {}", w.key, w.value);
+        let mut words = column![].spacing(5);
+        if !self.missed.is_empty() {
+            words = words.push(text!("Ошибки").size(20));
+            for word in &self.missed {
+                words = words.push(text!("{}", line(word)));
+            }
+        }
+        if !self.appealed.is_empty() {
+            words = words.push(text!("Апелляции").size(20));
+            for word in &self.appealed {
+                words = words.push(text!("{}", line(word)));
+            }
+        }
+
+        let sets = self
+            .state
+            .lock()
+            .unwrap()
+            .card_sets
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.id != 0 && s.kind == CardKind::Words)
+            .map(|(index, s)| SetChoice {
+                index,
+                name: s.name.clone(),
+            })
+            .collect::<Vec<SetChoice>>();
+
+        let mut actions = row![].spacing(DEFAULT_SPACING).align_y(alignment::Vertical::Center);
+        if !self.missed.is_empty() {
+            actions = actions.push(
+                button("Повторить ошибки").on_press(DictionaryQuizMessage::RepeatMistakes),
+            );
+            if !sets.is_empty() {
+                actions = actions
+                    .push(pick_list(
+                        sets,
+                        self.target_set.clone(),
+                        DictionaryQuizMessage::SelectTargetSet,
+                    ))
+                    .push(
+                        button("В набор карточек")
+                            .on_press_maybe(
+                                self.target_set
+                                    .as_ref()
+                                    .map(|_| DictionaryQuizMessage::PushMistakes),
+                            ),
+                    );
+            }
+        }
+
+        column![
+            scrollable(words).height(200),
+            actions,
+            text!("{}", self.push_status),
+        ]
+        .spacing(DEFAULT_SPACING)
+        .align_x(alignment::Horizontal::Center)
+        .into()
+    }
+
     fn update_set(&mut self) {
-        self.current_set.append(&mut self.words.clone());
+        self.current_set.append(&mut self.lap_words.clone());
         self.current_set.shuffle(&mut rand::rng());
         if self.score.total != 0 {
             self.laps += 1;
@@ -173,6 +559,7 @@ impl DictionaryQuizState {
         }
 
         let next = self.current_set.pop().unwrap();
+        self.current = Some(next.clone());
         if self.reverse {
             self.view = next.value.clone();
             self.correct = next.key.clone();
@@ -180,6 +567,16 @@ impl DictionaryQuizState {
             self.view = next.key.clone();
             self.correct = next.value.clone();
         }
+        if self.answer_mode == AnswerMode::Choice {
+            let reverse = self.reverse;
+            let answer = |w: &WordData| if reverse { w.key.clone() } else { w.value.clone() };
+            let state = self.state.lock().unwrap();
+            self.choice = Choice::new(&next, &state.dictionary, answer, &state.word_confusions);
+        }
+        self.shown_at = Instant::now();
+        if let Some(run) = &mut self.speed_run {
+            run.start();
+        }
     }
 
     fn laps(&self) -> Element<'_, DictionaryQuizMessage> {
@@ -203,7 +600,7 @@ impl DictionaryQuizState {
     }
 
     fn appeal_button(&self) -> Element<'_, DictionaryQuizMessage> {
-        if self.is_help && self.no_typing == false {
+        if self.is_help && self.answer_mode == AnswerMode::Typing {
             return button("Апелляция")
                 .on_press(DictionaryQuizMessage::Appeal)
                 .into();
@@ -214,6 +611,15 @@ impl DictionaryQuizState {
     fn appeal_answer(&mut self) {
         self.score.correct += 1;
         self.score.fail -= 1;
+        if let Some(run) = &mut self.speed_run {
+            run.appeal();
+        }
+        if let Some(word) = &self.current {
+            self.missed.retain(|w| w.id != word.id);
+            if !self.appealed.iter().any(|w| w.id == word.id) {
+                self.appealed.push(word.clone());
+            }
+        }
         self.answer = self.correct.clone();
         self.show_next()
     }
@@ -0,0 +1,226 @@
+use crate::confusables::drill_groups;
+use crate::lang::KanaSet;
+use crate::quiz::{record_kana_answer, record_kana_confusion, Score};
+use crate::Page::PreviousPage;
+use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
+use iced::widget::*;
+use iced::{alignment, Element, Fill, Task};
+use rand::distr::weighted::WeightedIndex;
+use rand::distr::Distribution;
+use rand::seq::{IndexedRandom, SliceRandom};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiscriminationMode {
+    /// Kana is shown, the romaji is chosen among the look-alikes' readings.
+    Recognition,
+    /// Romaji is shown, the kana is chosen among the look-alikes.
+    ForcedChoice,
+}
+
+impl DiscriminationMode {
+    const ALL: [DiscriminationMode; 2] = [
+        DiscriminationMode::Recognition,
+        DiscriminationMode::ForcedChoice,
+    ];
+}
+
+impl Display for DiscriminationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiscriminationMode::Recognition => write!(f, "Узнавание"),
+            DiscriminationMode::ForcedChoice => write!(f, "Выбор из похожих"),
+        }
+    }
+}
+
+/// Drill on kana that look alike: シ/ツ, ぬ/め and the pairs the user mixed up before.
+pub struct DiscriminationState {
+    set: KanaSet,
+    mode: DiscriminationMode,
+    kana: String,
+    roman: String,
+    options: Vec<(String, String)>,
+    feedback: Option<(bool, String)>,
+    score: Score,
+    shown_at: Instant,
+    state: Arc<Mutex<AppState>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum DiscriminationMessage {
+    Back,
+    Choose(String),
+    SetMode(DiscriminationMode),
+}
+
+impl NavigatedPage<DiscriminationMessage> for DiscriminationState {
+    fn navigate(&self, message: &DiscriminationMessage) -> Option<Page> {
+        if let DiscriminationMessage::Back = message {
+            Some(PreviousPage)
+        } else {
+            None
+        }
+    }
+}
+
+impl DiscriminationState {
+    pub(crate) fn new(set: KanaSet, state: Arc<Mutex<AppState>>) -> DiscriminationState {
+        let mut drill = DiscriminationState {
+            set,
+            mode: DiscriminationMode::Recognition,
+            kana: String::new(),
+            roman: String::new(),
+            options: vec![],
+            feedback: None,
+            score: Default::default(),
+            shown_at: Instant::now(),
+            state,
+        };
+        drill.show_next();
+        drill
+    }
+
+    pub fn update(&mut self, message: DiscriminationMessage) -> Task<RootMessage> {
+        match message {
+            DiscriminationMessage::Back => {}
+            DiscriminationMessage::Choose(kana) => self.choose(kana),
+            DiscriminationMessage::SetMode(mode) => self.mode = mode,
+        }
+        Task::none()
+    }
+
+    fn choose(&mut self, kana: String) {
+        let correct = kana == self.kana;
+        {
+            let mut state = self.state.lock().unwrap();
+            record_kana_answer(&mut state, &self.kana, correct, false, self.shown_at.elapsed());
+            if !correct {
+                record_kana_confusion(&mut state, &self.kana, &kana);
+            }
+        }
+
+        self.score.total += 1;
+        if correct {
+            self.score.correct += 1;
+            self.feedback = Some((true, format!("Верно: {} — {}", self.kana, self.roman)));
+        } else {
+            self.score.fail += 1;
+            let chosen_roman = self
+                .options
+                .iter()
+                .find(|(k, _)| *k == kana)
+                .map(|(_, r)| r.clone())
+                .unwrap_or_default();
+            self.feedback = Some((
+                false,
+                format!(
+                    "Это {} — {}, а не {} — {}",
+                    self.kana, self.roman, kana, chosen_roman
+                ),
+            ));
+        }
+
+        self.show_next();
+    }
+
+    fn show_next(&mut self) {
+        let list = self.set.list();
+        let enabled = list.iter().map(|(k, _)| k.clone()).collect::<Vec<String>>();
+        let groups = drill_groups(&enabled, &self.state.lock().unwrap().kana_confusions);
+        self.options.clear();
+        if groups.is_empty() {
+            return;
+        }
+
+        let mut generator = rand::rng();
+        let weights = groups.iter().map(|(_, weight)| *weight).collect::<Vec<u32>>();
+        let index = WeightedIndex::new(weights).unwrap().sample(&mut generator);
+
+        self.options = groups[index]
+            .0
+            .iter()
+            .filter_map(|kana| list.iter().find(|(k, _)| k == kana).cloned())
+            .collect();
+        self.options.shuffle(&mut generator);
+
+        let (kana, roman) = self.options.choose(&mut generator).unwrap().clone();
+        self.kana = kana;
+        self.roman = roman;
+        self.shown_at = Instant::now();
+    }
+
+    pub fn view(&self) -> Element<'_, DiscriminationMessage> {
+        container(
+            iced::widget::column![
+                pick_list(
+                    DiscriminationMode::ALL,
+                    Some(self.mode),
+                    DiscriminationMessage::SetMode
+                ),
+                self.drill(),
+                self.feedback(),
+                row![
+                    text!("{}", self.score.total.to_string()).size(25),
+                    text!("{}", self.score.correct.to_string())
+                        .size(25)
+                        .color(iced::Color::from_rgb8(60, 170, 60)),
+                    text!("{}", self.score.fail.to_string())
+                        .color(iced::Color::from_rgb8(255, 79, 0))
+                        .size(25),
+                ]
+                .spacing(DEFAULT_SPACING),
+                button("Закончить").on_press(DiscriminationMessage::Back),
+            ]
+            .spacing(DEFAULT_SPACING)
+            .align_x(alignment::Horizontal::Center),
+        )
+        .center_y(Fill)
+        .center_x(Fill)
+        .into()
+    }
+
+    fn drill(&self) -> Element<'_, DiscriminationMessage> {
+        if self.options.is_empty() {
+            return text!("Среди выбранных рядов нет похожих кана").into();
+        }
+
+        let (prompt, recognition) = match self.mode {
+            DiscriminationMode::Recognition => (self.kana.as_str(), true),
+            DiscriminationMode::ForcedChoice => (self.roman.as_str(), false),
+        };
+
+        let mut options = Row::new();
+        for (kana, roman) in &self.options {
+            let label = if recognition { roman } else { kana };
+            options = options.push(
+                button(text!("{}", label).size(32))
+                    .padding(10)
+                    .style(button::secondary)
+                    .on_press(DiscriminationMessage::Choose(kana.clone())),
+            );
+        }
+
+        iced::widget::column![
+            text!("{}", prompt).size(54),
+            options.spacing(DEFAULT_SPACING)
+        ]
+        .spacing(DEFAULT_SPACING)
+        .align_x(alignment::Horizontal::Center)
+        .into()
+    }
+
+    fn feedback(&self) -> Element<'_, DiscriminationMessage> {
+        match &self.feedback {
+            None => space().height(20).into(),
+            Some((true, message)) => text!("{}", message)
+                .color(iced::Color::from_rgb8(60, 170, 60))
+                .into(),
+            Some((false, message)) => text!("{}", message)
+                .color(iced::Color::from_rgb8(255, 79, 0))
+                .into(),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::cloze::EXAMPLES;
+use crate::jmdict::PART_OF_SPEECH;
+use crate::kana_input::{kana_input, KanaInputMode};
+use crate::lang::{WordData, WordGroup};
+use crate::media::IMAGES;
+use crate::pitch::ACCENT;
+use iced::widget::{pick_list, text_input};
+use iced::Element;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Names the card set filter already uses for its own variables, and the
+/// fields other pages keep in `additional`.
+const RESERVED: [&str; 11] = [
+    "key",
+    "value",
+    "tags",
+    "more",
+    "group",
+    "reading",
+    "description",
+    ACCENT,
+    IMAGES,
+    EXAMPLES,
+    PART_OF_SPEECH,
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldKind {
+    #[default]
+    Text,
+    Multiline,
+    Kana,
+    Number,
+    /// One of the options of the field.
+    Choice,
+}
+
+impl FieldKind {
+    pub const ALL: [FieldKind; 5] = [
+        FieldKind::Text,
+        FieldKind::Multiline,
+        FieldKind::Kana,
+        FieldKind::Number,
+        FieldKind::Choice,
+    ];
+}
+
+impl Display for FieldKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldKind::Text => write!(f, "Текст"),
+            FieldKind::Multiline => write!(f, "Многострочный текст"),
+            FieldKind::Kana => write!(f, "Кана"),
+            FieldKind::Number => write!(f, "Число"),
+            FieldKind::Choice => write!(f, "Вариант из списка"),
+        }
+    }
+}
+
+/// A field the words of a group have in `additional`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FieldSpec {
+    pub name: String,
+    pub kind: FieldKind,
+    pub required: bool,
+    /// Choices of a `Choice` field.
+    pub options: Vec<String>,
+    /// Shown as a column of the dictionary list.
+    pub in_list: bool,
+}
+
+impl FieldSpec {
+    /// What is wrong with the value, if anything.
+    pub fn check(&self, value: &str) -> Option<String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return self
+                .required
+                .then(|| format!("«{}» нужно заполнить", self.name));
+        }
+        match self.kind {
+            FieldKind::Number if value.parse::<f64>().is_err() => {
+                Some(format!("«{}» должно быть числом", self.name))
+            }
+            FieldKind::Choice if !self.options.iter().any(|o| o == value) => {
+                Some(format!("«{}»: нет варианта «{}»", self.name, value))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value for the card set filter: numbers as numbers.
+    pub fn filter_value(&self, value: Option<&String>) -> rhai::Dynamic {
+        let value = value.map(|v| v.trim()).unwrap_or_default();
+        match self.kind {
+            FieldKind::Number => value
+                .parse::<f64>()
+                .map(rhai::Dynamic::from_float)
+                .unwrap_or(rhai::Dynamic::UNIT),
+            _ => value.to_string().into(),
+        }
+    }
+}
+
+/// The field name is used as a variable in card set filters and as a
+/// token in card templates, so it has to be a plain identifier.
+pub fn check_field_name(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Some(format!(
+            "«{}»: имя из латинских букв, цифр и _, не с цифры",
+            name
+        ));
+    }
+    if RESERVED.contains(&name) {
+        return Some(format!("«{}» уже занято", name));
+    }
+    // keywords and the words rhai keeps for itself
+    if rhai::Engine::new_raw()
+        .compile(format!("let {0} = 0; {0}", name))
+        .is_err()
+    {
+        return Some(format!("«{}» — слово языка фильтров", name));
+    }
+    None
+}
+
+/// Fields of the group the word belongs to.
+pub fn word_fields<'a>(word: &WordData, groups: &'a [WordGroup]) -> &'a [FieldSpec] {
+    groups
+        .iter()
+        .find(|g| g.id == word.group_id)
+        .map(|g| g.fields.as_slice())
+        .unwrap_or_default()
+}
+
+/// Problems with the word's fields, in schema order.
+pub fn field_errors(word: &WordData, fields: &[FieldSpec]) -> Vec<String> {
+    fields
+        .iter()
+        .filter_map(|spec| spec.check(word.additional.get(&spec.name).map_or("", |v| v)))
+        .collect()
+}
+
+/// A one-line editor matching the field kind. Multiline fields are edited
+/// on the Word page; here they get a plain input.
+pub fn field_input<'a, M: Clone + 'a>(
+    spec: &FieldSpec,
+    value: &str,
+    kana_mode: KanaInputMode,
+    on_input: impl Fn(String) -> M + 'a,
+) -> Element<'a, M> {
+    match spec.kind {
+        FieldKind::Kana => kana_input(&spec.name, value, kana_mode, on_input).into(),
+        FieldKind::Choice => pick_list(
+            spec.options.clone(),
+            spec.options.iter().find(|o| *o == value).cloned(),
+            on_input,
+        )
+        .placeholder(spec.name.clone())
+        .into(),
+        _ => text_input(&spec.name, value).on_input(on_input).into(),
+    }
+}
+
+/// Options typed as a comma separated list.
+pub fn parse_options(text: &str) -> Vec<String> {
+    let mut options: Vec<String> = vec![];
+    for option in text.split(',').map(|o| o.trim()).filter(|o| !o.is_empty()) {
+        if !options.iter().any(|o| o == option) {
+            options.push(option.to_string());
+        }
+    }
+    options
+}
@@ -0,0 +1,205 @@
+use crate::kana::to_hiragana;
+use crate::kanji::is_kanji;
+use iced::alignment::{Horizontal, Vertical};
+use iced::widget::{column, text, Row};
+use iced::Element;
+
+/// A piece of the key with the kana written over it, if any.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ruby {
+    pub base: String,
+    pub ruby: Option<String>,
+}
+
+impl Ruby {
+    fn plain(base: &str) -> Self {
+        Self {
+            base: base.to_string(),
+            ruby: None,
+        }
+    }
+}
+
+/// Kanji, 々 and ヶ take furigana.
+fn takes_ruby(c: char) -> bool {
+    is_kanji(c) || c == '々' || c == 'ヶ'
+}
+
+/// The key split for furigana. A reading in bracket markup, `食[た]べる`,
+/// is used as written; a plain one is aligned with the kana in the key.
+pub fn furigana(key: &str, reading: &str) -> Vec<Ruby> {
+    let reading = reading.trim();
+    if reading.contains('[') {
+        return from_markup(reading);
+    }
+    align(key, reading)
+}
+
+/// The reading without bracket markup: `食[た]べる` is たべる.
+pub fn kana_reading(reading: &str) -> String {
+    if !reading.contains('[') {
+        return reading.to_string();
+    }
+    from_markup(reading)
+        .into_iter()
+        .map(|r| r.ruby.unwrap_or(r.base))
+        .collect()
+}
+
+/// Brackets hold the kana of the kanji run right before them.
+fn from_markup(markup: &str) -> Vec<Ruby> {
+    let mut result = vec![];
+    let mut buffer = String::new();
+    let mut chars = markup.chars();
+    while let Some(c) = chars.next() {
+        if c != '[' {
+            buffer.push(c);
+            continue;
+        }
+        let ruby = chars.by_ref().take_while(|c| *c != ']').collect::<String>();
+        let kept = buffer.trim_end_matches(takes_ruby).len();
+        let (before, base) = if kept == buffer.len() {
+            // no kanji before the bracket, the last character gets the kana
+            let last = buffer.char_indices().last().map(|(i, _)| i).unwrap_or(0);
+            buffer.split_at(last)
+        } else {
+            buffer.split_at(kept)
+        };
+        if !before.is_empty() {
+            result.push(Ruby::plain(before));
+        }
+        if !base.is_empty() {
+            result.push(Ruby {
+                base: base.to_string(),
+                ruby: Some(ruby),
+            });
+        }
+        buffer.clear();
+    }
+    if !buffer.is_empty() {
+        result.push(Ruby::plain(&buffer));
+    }
+    result
+}
+
+/// Kana in the key have to be found in the reading as they are; the kanji
+/// runs between them take what is left. The whole reading goes over the
+/// whole key when they don't line up.
+fn align(key: &str, reading: &str) -> Vec<Ruby> {
+    if reading.is_empty() || !key.chars().any(takes_ruby) {
+        return vec![Ruby::plain(key)];
+    }
+
+    let mut runs: Vec<(String, bool)> = vec![];
+    for c in key.chars() {
+        let kanji = takes_ruby(c);
+        match runs.last_mut() {
+            Some((run, is_kanji)) if *is_kanji == kanji => run.push(c),
+            _ => runs.push((c.to_string(), kanji)),
+        }
+    }
+
+    let kana = to_hiragana(reading).chars().collect::<Vec<char>>();
+    match match_runs(&runs, &kana) {
+        Some(rubies) => {
+            let mut rubies = rubies.into_iter();
+            runs.iter()
+                .map(|(run, kanji)| Ruby {
+                    base: run.clone(),
+                    ruby: if *kanji { rubies.next() } else { None },
+                })
+                .collect()
+        }
+        None => vec![Ruby {
+            base: key.to_string(),
+            ruby: Some(reading.to_string()),
+        }],
+    }
+}
+
+/// Readings of the kanji runs, in order.
+fn match_runs(runs: &[(String, bool)], reading: &[char]) -> Option<Vec<String>> {
+    let Some(((run, kanji), rest)) = runs.split_first() else {
+        return reading.is_empty().then(Vec::new);
+    };
+    if !kanji {
+        let kana = to_hiragana(run).chars().collect::<Vec<char>>();
+        if !reading.starts_with(&kana) {
+            return None;
+        }
+        return match_runs(rest, &reading[kana.len()..]);
+    }
+    for end in 1..=reading.len() {
+        if let Some(mut tail) = match_runs(rest, &reading[end..]) {
+            tail.insert(0, reading[..end].iter().collect());
+            return Some(tail);
+        }
+    }
+    None
+}
+
+/// The key with the kana in small print over the kanji.
+pub fn ruby_view<'a, M: 'a>(rubies: &[Ruby], size: f32) -> Element<'a, M> {
+    let mut line = Row::new().align_y(Vertical::Bottom);
+    for ruby in rubies {
+        line = line.push(
+            column![
+                text!("{}", ruby.ruby.clone().unwrap_or_default()).size(size * 0.45),
+                text!("{}", ruby.base).size(size),
+            ]
+            .align_x(Horizontal::Center),
+        );
+    }
+    line.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ruby(base: &str, ruby: Option<&str>) -> Ruby {
+        Ruby {
+            base: base.to_string(),
+            ruby: ruby.map(|r| r.to_string()),
+        }
+    }
+
+    #[test]
+    fn kana_of_the_key_split_the_reading() {
+        assert_eq!(
+            furigana("食べる", "たべる"),
+            [ruby("食", Some("た")), ruby("べる", None)]
+        );
+        assert_eq!(
+            furigana("引き出し", "ひきだし"),
+            [
+                ruby("引", Some("ひ")),
+                ruby("き", None),
+                ruby("出", Some("だ")),
+                ruby("し", None)
+            ]
+        );
+        assert_eq!(furigana("人々", "ひとびと"), [ruby("人々", Some("ひとびと"))]);
+    }
+
+    #[test]
+    fn unaligned_reading_goes_over_the_whole_key() {
+        assert_eq!(furigana("食べる", "のむ"), [ruby("食べる", Some("のむ"))]);
+        assert_eq!(furigana("ねこ", "ねこ"), [ruby("ねこ", None)]);
+        assert_eq!(furigana("猫", ""), [ruby("猫", None)]);
+    }
+
+    #[test]
+    fn markup_is_used_as_written() {
+        assert_eq!(
+            furigana("", "食[た]べる"),
+            [ruby("食", Some("た")), ruby("べる", None)]
+        );
+        assert_eq!(
+            furigana("", "お茶[ちゃ]"),
+            [ruby("お", None), ruby("茶", Some("ちゃ"))]
+        );
+        assert_eq!(kana_reading("食[た]べ物[もの]"), "たべもの");
+        assert_eq!(kana_reading("たべもの"), "たべもの");
+    }
+}
@@ -0,0 +1,178 @@
+use crate::data_provider::words::update_group;
+use crate::fields::{check_field_name, parse_options, FieldKind, FieldSpec};
+use crate::Page::PreviousPage;
+use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
+use iced::widget::button::danger;
+use iced::widget::{
+    button, checkbox, column, container, pick_list, row, rule, scrollable, text, text_input,
+    Column,
+};
+use iced::{Element, Fill, Task};
+use std::sync::{Arc, Mutex};
+
+/// The fields the words of one group have: their editors on the Word page,
+/// columns of the dictionary list and variables of card set filters.
+pub struct GroupFieldsState {
+    group_index: usize,
+    group_name: String,
+    fields: Vec<FieldSpec>,
+    /// Options of each field as typed, split on save.
+    options: Vec<String>,
+    status: String,
+    state: Arc<Mutex<AppState>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum GroupFieldsMessage {
+    Back,
+    Add,
+    Remove(usize),
+    SetName(usize, String),
+    SetKind(usize, FieldKind),
+    SetOptions(usize, String),
+    SetRequired(usize, bool),
+    SetInList(usize, bool),
+    Save,
+}
+
+impl NavigatedPage<GroupFieldsMessage> for GroupFieldsState {
+    fn navigate(&self, message: &GroupFieldsMessage) -> Option<Page> {
+        match message {
+            GroupFieldsMessage::Back => Some(PreviousPage),
+            _ => None,
+        }
+    }
+}
+
+impl GroupFieldsState {
+    pub fn new(group_index: usize, state: Arc<Mutex<AppState>>) -> Self {
+        let group = state.lock().unwrap().word_groups[group_index].clone();
+        Self {
+            group_index,
+            group_name: group.name,
+            options: group.fields.iter().map(|f| f.options.join(", ")).collect(),
+            fields: group.fields,
+            status: String::new(),
+            state,
+        }
+    }
+
+    pub fn update(&mut self, message: GroupFieldsMessage) -> Task<RootMessage> {
+        match message {
+            GroupFieldsMessage::Back => {}
+            GroupFieldsMessage::Add => {
+                self.fields.push(FieldSpec {
+                    name: format!("field{}", self.fields.len() + 1),
+                    ..FieldSpec::default()
+                });
+                self.options.push(String::new());
+            }
+            GroupFieldsMessage::Remove(i) => {
+                self.fields.remove(i);
+                self.options.remove(i);
+            }
+            GroupFieldsMessage::SetName(i, name) => self.fields[i].name = name.trim().to_string(),
+            GroupFieldsMessage::SetKind(i, kind) => self.fields[i].kind = kind,
+            GroupFieldsMessage::SetOptions(i, options) => self.options[i] = options,
+            GroupFieldsMessage::SetRequired(i, required) => self.fields[i].required = required,
+            GroupFieldsMessage::SetInList(i, in_list) => self.fields[i].in_list = in_list,
+            GroupFieldsMessage::Save => {
+                if let Some(error) = self.error() {
+                    self.status = error;
+                    return Task::none();
+                }
+                for (field, options) in self.fields.iter_mut().zip(&self.options) {
+                    field.options = match field.kind {
+                        FieldKind::Choice => parse_options(options),
+                        _ => vec![],
+                    };
+                }
+
+                let state = &mut *self.state.lock().unwrap();
+                let group = &mut state.word_groups[self.group_index];
+                group.fields = self.fields.clone();
+                update_group(group, &state.connection);
+                self.status = "Сохранено".to_string();
+            }
+        }
+        Task::none()
+    }
+
+    /// The first problem that keeps the schema from being saved.
+    fn error(&self) -> Option<String> {
+        for (i, field) in self.fields.iter().enumerate() {
+            if let Some(error) = check_field_name(&field.name) {
+                return Some(error);
+            }
+            if self.fields[..i].iter().any(|f| f.name == field.name) {
+                return Some(format!("Поле «{}» уже есть", field.name));
+            }
+            if field.kind == FieldKind::Choice && parse_options(&self.options[i]).is_empty() {
+                return Some(format!("«{}»: перечислите варианты через запятую", field.name));
+            }
+        }
+        None
+    }
+
+    pub fn view(&self) -> Element<'_, GroupFieldsMessage> {
+        let mut list = Column::new().spacing(DEFAULT_SPACING);
+        for (i, field) in self.fields.iter().enumerate() {
+            let mut line = row![
+                text_input("Имя", &field.name)
+                    .on_input(move |name| GroupFieldsMessage::SetName(i, name))
+                    .width(200),
+                pick_list(FieldKind::ALL, Some(field.kind), move |kind| {
+                    GroupFieldsMessage::SetKind(i, kind)
+                }),
+            ]
+            .spacing(DEFAULT_SPACING)
+            .align_y(iced::Center);
+            if field.kind == FieldKind::Choice {
+                line = line.push(
+                    text_input("Варианты через запятую", &self.options[i])
+                        .on_input(move |options| GroupFieldsMessage::SetOptions(i, options))
+                        .width(Fill),
+                );
+            }
+            line = line
+                .push(
+                    checkbox(field.required)
+                        .label("Обязательное")
+                        .on_toggle(move |b| GroupFieldsMessage::SetRequired(i, b)),
+                )
+                .push(
+                    checkbox(field.in_list)
+                        .label("В списке слов")
+                        .on_toggle(move |b| GroupFieldsMessage::SetInList(i, b)),
+                )
+                .push(
+                    button("Удалить")
+                        .style(danger)
+                        .on_press(GroupFieldsMessage::Remove(i)),
+                );
+            list = list.push(line);
+        }
+
+        container(
+            column![
+                button("Назад").on_press(GroupFieldsMessage::Back),
+                text!("Поля слов группы «{}»", self.group_name).size(24),
+                text!(
+                    "Имя поля — переменная в фильтре набора карточек и поле стороны карточки"
+                ),
+                rule::horizontal(2),
+                scrollable(list).height(Fill),
+                row![
+                    button("Добавить поле").on_press(GroupFieldsMessage::Add),
+                    button("Сохранить").on_press(GroupFieldsMessage::Save),
+                    text!("{}", self.status),
+                ]
+                .spacing(DEFAULT_SPACING)
+                .align_y(iced::Center),
+            ]
+            .spacing(DEFAULT_SPACING),
+        )
+        .padding(DEFAULT_SPACING)
+        .into()
+    }
+}
@@ -0,0 +1,402 @@
+use crate::stroke_guide::{draw_cells, pen, polyline};
+use crate::strokes::{stroke_length, Stroke, GLYPH_SIZE};
+use iced::widget::canvas;
+use iced::widget::canvas::{Action, Event, Frame, Geometry};
+use iced::{mouse, touch, Color, Point, Rectangle, Renderer, Theme};
+use std::fmt::{Display, Formatter};
+
+const RESAMPLE_POINTS: usize = 32;
+/// Average distance between matched points, in 109×109 box units.
+const GOOD_DISTANCE: f32 = 12.0;
+const INEXACT_DISTANCE: f32 = 22.0;
+pub const PASSING_GRADE: f32 = 60.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StrokeVerdict {
+    Good,
+    Inexact,
+    /// The stroke looks like another one of the character.
+    WrongOrder(usize),
+    Reversed,
+    WrongShape,
+    Missing,
+    Extra,
+}
+
+impl StrokeVerdict {
+    fn score(&self) -> f32 {
+        match self {
+            StrokeVerdict::Good => 1.0,
+            StrokeVerdict::Inexact => 0.6,
+            StrokeVerdict::WrongOrder(_) | StrokeVerdict::Reversed => 0.3,
+            StrokeVerdict::WrongShape | StrokeVerdict::Missing | StrokeVerdict::Extra => 0.0,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self, StrokeVerdict::Good | StrokeVerdict::Inexact)
+    }
+}
+
+impl Display for StrokeVerdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StrokeVerdict::Good => write!(f, "верно"),
+            StrokeVerdict::Inexact => write!(f, "неточно"),
+            StrokeVerdict::WrongOrder(n) => write!(f, "похоже на черту {}", n + 1),
+            StrokeVerdict::Reversed => write!(f, "не в ту сторону"),
+            StrokeVerdict::WrongShape => write!(f, "другая форма"),
+            StrokeVerdict::Missing => write!(f, "не хватает"),
+            StrokeVerdict::Extra => write!(f, "лишняя"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HandwritingResult {
+    pub verdicts: Vec<StrokeVerdict>,
+    /// 0–100.
+    pub grade: f32,
+}
+
+impl HandwritingResult {
+    pub fn is_passed(&self) -> bool {
+        self.grade >= PASSING_GRADE
+    }
+}
+
+/// Compares drawn strokes with the reference ones stroke by stroke: count,
+/// order, direction and shape, the latter with DTW on resampled points.
+pub fn recognize(drawn: &[Stroke], reference: &[Stroke]) -> HandwritingResult {
+    let drawn = fit_to(drawn, reference);
+    let drawn = drawn
+        .iter()
+        .map(|s| resample(s, RESAMPLE_POINTS))
+        .collect::<Vec<Stroke>>();
+    let reference = reference
+        .iter()
+        .map(|s| resample(s, RESAMPLE_POINTS))
+        .collect::<Vec<Stroke>>();
+
+    let mut verdicts = vec![];
+    for i in 0..drawn.len().max(reference.len()) {
+        let verdict = match (drawn.get(i), reference.get(i)) {
+            (Some(stroke), Some(target)) => judge(stroke, target, i, &reference),
+            (Some(_), None) => StrokeVerdict::Extra,
+            _ => StrokeVerdict::Missing,
+        };
+        verdicts.push(verdict);
+    }
+
+    let grade = if verdicts.is_empty() {
+        0.0
+    } else {
+        verdicts.iter().map(|v| v.score()).sum::<f32>() / verdicts.len() as f32 * 100.0
+    };
+    HandwritingResult { verdicts, grade }
+}
+
+fn judge(stroke: &[Point], target: &[Point], index: usize, reference: &[Stroke]) -> StrokeVerdict {
+    let forward = dtw(stroke, target);
+    let reversed = stroke.iter().rev().cloned().collect::<Stroke>();
+    let backward = dtw(&reversed, target);
+
+    // short strokes stay close to the target even when drawn backwards,
+    // so direction is judged by which way fits clearly better
+    if backward <= INEXACT_DISTANCE && backward < forward / 2.0 {
+        return StrokeVerdict::Reversed;
+    }
+    if forward <= GOOD_DISTANCE {
+        return StrokeVerdict::Good;
+    }
+
+    let other = reference
+        .iter()
+        .enumerate()
+        .filter(|(j, _)| *j != index)
+        .map(|(j, r)| (j, dtw(stroke, r)))
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((j, distance)) = other
+        && distance <= GOOD_DISTANCE
+        && distance < forward
+    {
+        return StrokeVerdict::WrongOrder(j);
+    }
+
+    if forward <= INEXACT_DISTANCE {
+        return StrokeVerdict::Inexact;
+    }
+    StrokeVerdict::WrongShape
+}
+
+/// Moves and scales the drawing so its bounding box matches the reference one;
+/// nobody writes exactly in the middle of the cell.
+fn fit_to(drawn: &[Stroke], reference: &[Stroke]) -> Vec<Stroke> {
+    let (Some(from), Some(to)) = (bounds(drawn), bounds(reference)) else {
+        return drawn.to_vec();
+    };
+    let scale = (to.width.max(to.height) / from.width.max(from.height).max(1.0)).min(4.0);
+    let from_center = from.center();
+    let to_center = to.center();
+
+    drawn
+        .iter()
+        .map(|stroke| {
+            stroke
+                .iter()
+                .map(|p| {
+                    Point::new(
+                        (p.x - from_center.x) * scale + to_center.x,
+                        (p.y - from_center.y) * scale + to_center.y,
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn bounds(strokes: &[Stroke]) -> Option<Rectangle> {
+    let mut points = strokes.iter().flatten();
+    let first = points.next()?;
+    let (mut min, mut max) = (*first, *first);
+    for p in points {
+        min = Point::new(min.x.min(p.x), min.y.min(p.y));
+        max = Point::new(max.x.max(p.x), max.y.max(p.y));
+    }
+    Some(Rectangle::new(
+        min,
+        iced::Size::new(max.x - min.x, max.y - min.y),
+    ))
+}
+
+/// `count` points evenly spaced along the polyline.
+fn resample(stroke: &[Point], count: usize) -> Stroke {
+    let Some(first) = stroke.first() else {
+        return vec![];
+    };
+    let length = stroke_length(stroke);
+    if length == 0.0 {
+        return vec![*first; count];
+    }
+
+    let step = length / (count - 1) as f32;
+    let mut result = vec![*first];
+    let mut walked = 0.0;
+    let mut next = step;
+    for w in stroke.windows(2) {
+        let segment = w[0].distance(w[1]);
+        while next <= walked + segment && result.len() < count {
+            let t = (next - walked) / segment;
+            result.push(Point::new(
+                w[0].x + (w[1].x - w[0].x) * t,
+                w[0].y + (w[1].y - w[0].y) * t,
+            ));
+            next += step;
+        }
+        walked += segment;
+    }
+    while result.len() < count {
+        result.push(*stroke.last().unwrap());
+    }
+    result
+}
+
+/// Dynamic time warping distance, averaged over the warping path length.
+fn dtw(a: &[Point], b: &[Point]) -> f32 {
+    let (n, m) = (a.len(), b.len());
+    if n == 0 || m == 0 {
+        return f32::INFINITY;
+    }
+
+    let mut cost = vec![vec![(f32::INFINITY, 0usize); m + 1]; n + 1];
+    cost[0][0] = (0.0, 0);
+    for i in 1..=n {
+        for j in 1..=m {
+            let distance = a[i - 1].distance(b[j - 1]);
+            let best = [cost[i - 1][j], cost[i][j - 1], cost[i - 1][j - 1]]
+                .into_iter()
+                .min_by(|x, y| x.0.total_cmp(&y.0))
+                .unwrap();
+            cost[i][j] = (best.0 + distance, best.1 + 1);
+        }
+    }
+    let (total, steps) = cost[n][m];
+    total / steps as f32
+}
+
+/// Canvas the kana is written on. Finished strokes are owned by the page and
+/// come back as `strokes`; the one being drawn lives in the canvas state.
+pub struct DrawingPad<'a, M> {
+    pub strokes: &'a [Stroke],
+    pub reference: Option<&'a [Stroke]>,
+    pub verdicts: Option<&'a [StrokeVerdict]>,
+    pub cells: usize,
+    pub on_stroke: fn(Stroke) -> M,
+}
+
+#[derive(Default)]
+pub struct PadState {
+    current: Option<Stroke>,
+}
+
+impl<M> DrawingPad<'_, M> {
+    fn scale(&self, bounds: Rectangle) -> f32 {
+        (bounds.width / (GLYPH_SIZE * self.cells as f32)).min(bounds.height / GLYPH_SIZE)
+    }
+}
+
+impl<M> canvas::Program<M> for DrawingPad<'_, M> {
+    type State = PadState;
+
+    fn update(
+        &self,
+        state: &mut PadState,
+        event: &Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Option<Action<M>> {
+        let scale = self.scale(bounds);
+        let to_glyph = |p: Point| Point::new(p.x / scale, p.y / scale);
+        let position = match event {
+            Event::Touch(
+                touch::Event::FingerPressed { position, .. }
+                | touch::Event::FingerMoved { position, .. },
+            ) => Some(Point::new(position.x - bounds.x, position.y - bounds.y)),
+            _ => cursor.position_in(bounds),
+        };
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+            | Event::Touch(touch::Event::FingerPressed { .. }) => {
+                let position = position?;
+                state.current = Some(vec![to_glyph(position)]);
+                Some(Action::request_redraw().and_capture())
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. })
+            | Event::Touch(touch::Event::FingerMoved { .. }) => {
+                let current = state.current.as_mut()?;
+                current.push(to_glyph(position?));
+                Some(Action::request_redraw().and_capture())
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+            | Event::Touch(touch::Event::FingerLifted { .. } | touch::Event::FingerLost { .. }) => {
+                let stroke = state.current.take()?;
+                if stroke.len() < 2 {
+                    return Some(Action::request_redraw());
+                }
+                Some(Action::publish((self.on_stroke)(stroke)).and_capture())
+            }
+            _ => None,
+        }
+    }
+
+    fn draw(
+        &self,
+        state: &PadState,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let scale = self.scale(bounds);
+        let palette = theme.extended_palette();
+
+        draw_cells(&mut frame, self.cells, scale, palette.background.strong.color);
+
+        if let Some(reference) = self.reference {
+            for stroke in reference {
+                frame.stroke(
+                    &polyline(stroke, scale),
+                    pen(palette.background.weak.color, 6.0 * scale),
+                );
+            }
+        }
+
+        for (i, stroke) in self.strokes.iter().enumerate() {
+            let color = match self.verdicts.and_then(|v| v.get(i)) {
+                None => palette.background.base.text,
+                Some(v) if v.is_ok() => Color::from_rgb8(60, 170, 60),
+                Some(_) => Color::from_rgb8(255, 79, 0),
+            };
+            frame.stroke(&polyline(stroke, scale), pen(color, 4.0 * scale));
+        }
+        if let Some(current) = &state.current {
+            frame.stroke(
+                &polyline(current, scale),
+                pen(palette.primary.base.color, 4.0 * scale),
+            );
+        }
+
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &PadState,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        if cursor.is_over(bounds) {
+            mouse::Interaction::Crosshair
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Strokes of 十: across, then down.
+    fn cross() -> Vec<Stroke> {
+        vec![
+            vec![Point::new(20.0, 50.0), Point::new(90.0, 50.0)],
+            vec![Point::new(55.0, 15.0), Point::new(55.0, 100.0)],
+        ]
+    }
+
+    #[test]
+    fn resampled_points_are_evenly_spaced() {
+        let points = resample(&[Point::new(0.0, 0.0), Point::new(31.0, 0.0)], 32);
+        assert_eq!(points.len(), 32);
+        assert!(points.windows(2).all(|w| (w[0].distance(w[1]) - 1.0).abs() < 0.01));
+        assert_eq!(dtw(&points, &points), 0.0);
+    }
+
+    #[test]
+    fn reference_is_good_against_itself() {
+        let result = recognize(&cross(), &cross());
+        assert_eq!(result.verdicts, [StrokeVerdict::Good, StrokeVerdict::Good]);
+        assert_eq!(result.grade, 100.0);
+        assert!(result.is_passed());
+    }
+
+    #[test]
+    fn stroke_drawn_backwards_is_reversed() {
+        let mut drawn = cross();
+        drawn[0].reverse();
+        let result = recognize(&drawn, &cross());
+        assert_eq!(result.verdicts, [StrokeVerdict::Reversed, StrokeVerdict::Good]);
+    }
+
+    #[test]
+    fn swapped_strokes_are_in_wrong_order() {
+        let mut drawn = cross();
+        drawn.swap(0, 1);
+        let result = recognize(&drawn, &cross());
+        assert_eq!(
+            result.verdicts,
+            [StrokeVerdict::WrongOrder(1), StrokeVerdict::WrongOrder(0)]
+        );
+        assert!(!result.is_passed());
+    }
+
+    #[test]
+    fn missing_stroke_lowers_the_grade() {
+        let result = recognize(&cross()[..1], &cross());
+        assert_eq!(result.verdicts[1], StrokeVerdict::Missing);
+        assert!(result.grade <= 50.0);
+    }
+}
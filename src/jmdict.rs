@@ -0,0 +1,526 @@
+use crate::data_provider::jmdict::save_jmdict;
+use crate::dictionary::app_data_dir;
+use crate::kana::is_kana;
+use crate::lang::WordData;
+use iced::widget::{button, column, row, text, Column};
+use iced::{Element, Fill};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+
+/// JMdict as published by EDRDG, looked up in the app data dir in this order.
+pub const JMDICT_FILES: [&str; 4] = ["JMdict_e.xml", "JMdict_e", "JMdict.xml", "JMdict"];
+/// The JSON of jmdict-simplified is named `jmdict-eng-3.6.1.json` and alike.
+const JSON_PREFIX: &str = "jmdict";
+/// Field of `additional` with the part of speech.
+pub const PART_OF_SPEECH: &str = "pos";
+/// Tag of the words JMdict marks as common.
+pub const COMMON_TAG: &str = "common";
+/// Priority marks that make an entry common, as in jmdict-simplified.
+const COMMON_PRIORITIES: [&str; 5] = ["news1", "ichi1", "spec1", "spec2", "gai1"];
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+/// `appliesToKanji` of jmdict-simplified for a reading of every written form.
+const ANY_KANJI: &str = "*";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JmdictEntry {
+    /// `ent_seq` of JMdict.
+    pub id: u32,
+    pub kanji: Vec<String>,
+    pub readings: Vec<String>,
+    pub common: bool,
+    pub senses: Vec<Sense>,
+    /// Readings that belong only to some written forms (`re_restr`), or to
+    /// none of them (`re_nokanji`, an empty list).
+    #[serde(default)]
+    pub restricted: HashMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sense {
+    pub pos: Vec<String>,
+    /// Language code and gloss.
+    pub glosses: Vec<(String, String)>,
+}
+
+/// Key of the gloss language in the settings table, its code is stored.
+pub const GLOSS_LANGUAGE: &str = "gloss_language";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GlossLanguage {
+    /// ISO 639-2 code, as in `xml:lang` of JMdict.
+    pub code: &'static str,
+    name: &'static str,
+}
+
+impl GlossLanguage {
+    pub const ENGLISH: GlossLanguage = GlossLanguage { code: "eng", name: "Английский" };
+    pub const RUSSIAN: GlossLanguage = GlossLanguage { code: "rus", name: "Русский" };
+    pub const ALL: [GlossLanguage; 6] = [
+        GlossLanguage::RUSSIAN,
+        GlossLanguage::ENGLISH,
+        GlossLanguage { code: "ger", name: "Немецкий" },
+        GlossLanguage { code: "fre", name: "Французский" },
+        GlossLanguage { code: "spa", name: "Испанский" },
+        GlossLanguage { code: "dut", name: "Нидерландский" },
+    ];
+
+    /// The language of a saved code; Russian for an unknown one.
+    pub fn from_code(code: &str) -> GlossLanguage {
+        GlossLanguage::ALL
+            .into_iter()
+            .find(|l| l.code == code)
+            .unwrap_or(GlossLanguage::RUSSIAN)
+    }
+}
+
+impl Display for GlossLanguage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl JmdictEntry {
+    /// Every written form and reading, as indexed for the lookup.
+    pub fn forms(&self) -> impl Iterator<Item = &String> {
+        self.kanji.iter().chain(&self.readings)
+    }
+
+    /// Readings of the written form; all of them for a form in kana.
+    pub fn readings_for(&self, form: &str) -> Vec<&String> {
+        if !self.kanji.iter().any(|k| k == form) {
+            return self.readings.iter().collect();
+        }
+        self.readings
+            .iter()
+            .filter(|r| {
+                self.restricted
+                    .get(*r)
+                    .is_none_or(|forms| forms.iter().any(|f| f == form))
+            })
+            .collect()
+    }
+
+    /// The form the typed text is the start of, kanji first.
+    pub fn form_for(&self, typed: &str) -> String {
+        self.forms()
+            .find(|f| f.starts_with(typed))
+            .or(self.forms().next())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Glosses of each sense in the language, English when it has none.
+    pub fn meanings(&self, language: GlossLanguage) -> Vec<String> {
+        let has_language = self
+            .senses
+            .iter()
+            .any(|s| s.glosses.iter().any(|(lang, _)| lang == language.code));
+        let code = if has_language { language.code } else { GlossLanguage::ENGLISH.code };
+        self.senses
+            .iter()
+            .map(|sense| {
+                sense
+                    .glosses
+                    .iter()
+                    .filter(|(lang, _)| lang == code)
+                    .map(|(_, gloss)| gloss.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            })
+            .filter(|meaning| !meaning.is_empty())
+            .collect()
+    }
+
+    /// Part of speech of the first sense.
+    pub fn part_of_speech(&self) -> String {
+        self.senses
+            .first()
+            .map(|s| s.pos.join(", "))
+            .unwrap_or_default()
+    }
+}
+
+/// Key, reading, translation and part of speech from the entry. The
+/// reading is left out for a key written in kana.
+pub fn fill_word(word: &mut WordData, entry: &JmdictEntry, typed: &str, language: GlossLanguage) {
+    word.key = entry.form_for(typed);
+    word.value = entry.meanings(language).join("; ");
+    if !is_kana(&word.key)
+        && let Some(reading) = entry.readings_for(&word.key).first()
+    {
+        word.additional.insert("reading".to_string(), reading.to_string());
+    }
+    let pos = entry.part_of_speech();
+    if !pos.is_empty() {
+        word.additional.insert(PART_OF_SPEECH.to_string(), pos);
+    }
+    let has_tag = word.tags.split(',').any(|t| t.trim() == COMMON_TAG);
+    if entry.common && !has_tag {
+        word.tags = if word.tags.trim().is_empty() {
+            COMMON_TAG.to_string()
+        } else {
+            format!("{}, {}", word.tags.trim(), COMMON_TAG)
+        };
+    }
+}
+
+/// Found entries, each with a button filling the word from it.
+pub fn suggestions_view<'a, M: Clone + 'a>(
+    entries: &[JmdictEntry],
+    typed: &str,
+    language: GlossLanguage,
+    on_fill: impl Fn(usize) -> M,
+) -> Element<'a, M> {
+    let mut list = Column::new().spacing(2);
+    for (i, entry) in entries.iter().enumerate() {
+        let meanings = entry.meanings(language);
+        let form = entry.form_for(typed);
+        let readings = entry.readings_for(&form);
+        let more = if meanings.len() > 2 { "; …" } else { "" };
+        list = list.push(
+            row![
+                button("Заполнить").style(button::text).on_press(on_fill(i)),
+                text!(
+                    "{}【{}】{} {}{}",
+                    form,
+                    readings.iter().map(|r| r.as_str()).collect::<Vec<&str>>().join("・"),
+                    if entry.common { "★" } else { "" },
+                    meanings.iter().take(2).cloned().collect::<Vec<String>>().join("; "),
+                    more
+                )
+                .width(Fill),
+            ]
+            .spacing(5)
+            .align_y(iced::Center),
+        );
+    }
+    column![list].into()
+}
+
+fn jmdict_path() -> Result<PathBuf, String> {
+    let dir = app_data_dir();
+    if let Some(name) = JMDICT_FILES.iter().find(|name| dir.join(name).exists()) {
+        return Ok(dir.join(name));
+    }
+    fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+            name.starts_with(JSON_PREFIX) && name.ends_with(".json")
+        })
+        .ok_or_else(|| {
+            format!(
+                "Положите {} или jmdict-*.json в {}",
+                JMDICT_FILES[0],
+                dir.display()
+            )
+        })
+}
+
+/// Reads JMdict XML or the JSON of jmdict-simplified from the app data dir.
+pub fn read_jmdict_file() -> Result<Vec<JmdictEntry>, String> {
+    let path = jmdict_path()?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let data = fs::read_to_string(&path).map_err(|e| format!("{}: {}", name, e))?;
+    let list = if name.to_lowercase().ends_with(".json") {
+        parse_jmdict_json(&data)?
+    } else {
+        parse_jmdict(&data)?
+    };
+    if list.is_empty() {
+        return Err(format!("В {} нет ни одного слова", name));
+    }
+    Ok(list)
+}
+
+/// Reads the file and stores it through a connection of its own, in small
+/// transactions, so the pages can still use the database in between.
+pub fn import_jmdict() -> Result<usize, String> {
+    let list = read_jmdict_file()?;
+    let connection =
+        Connection::open(app_data_dir().join("data.db")).map_err(|e| e.to_string())?;
+    save_jmdict(&list, &connection)?;
+    Ok(list.len())
+}
+
+/// JMdict XML. Parts of speech are entities of the DTD and come expanded.
+/// A sense without them has the ones of the sense before.
+pub fn parse_jmdict(xml: &str) -> Result<Vec<JmdictEntry>, String> {
+    let document = roxmltree::Document::parse_with_options(
+        xml,
+        roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut result = vec![];
+    for node in document
+        .root_element()
+        .children()
+        .filter(|n| n.has_tag_name("entry"))
+    {
+        let mut entry = JmdictEntry::default();
+        for part in node.children().filter(|n| n.is_element()) {
+            match part.tag_name().name() {
+                "ent_seq" => entry.id = part.text().unwrap_or("").trim().parse().unwrap_or(0),
+                "k_ele" | "r_ele" => {
+                    let mut reading = None;
+                    let mut restrictions = vec![];
+                    let mut no_kanji = false;
+                    for element in part.children().filter(|n| n.is_element()) {
+                        let text = element.text().unwrap_or("").trim().to_string();
+                        match element.tag_name().name() {
+                            "keb" => entry.kanji.push(text),
+                            "reb" => reading = Some(text),
+                            "re_restr" => restrictions.push(text),
+                            "re_nokanji" => no_kanji = true,
+                            "ke_pri" | "re_pri" if COMMON_PRIORITIES.contains(&text.as_str()) => {
+                                entry.common = true
+                            }
+                            _ => {}
+                        }
+                    }
+                    if let Some(reading) = reading {
+                        if no_kanji || !restrictions.is_empty() {
+                            entry.restricted.insert(reading.clone(), restrictions);
+                        }
+                        entry.readings.push(reading);
+                    }
+                }
+                "sense" => {
+                    let mut sense = Sense::default();
+                    for element in part.children().filter(|n| n.is_element()) {
+                        let text = element.text().unwrap_or("").trim().to_string();
+                        match element.tag_name().name() {
+                            "pos" => sense.pos.push(text),
+                            "gloss" => {
+                                let lang = element
+                                    .attribute((XML_NAMESPACE, "lang"))
+                                    .unwrap_or(GlossLanguage::ENGLISH.code);
+                                sense.glosses.push((lang.to_string(), text));
+                            }
+                            _ => {}
+                        }
+                    }
+                    if sense.pos.is_empty()
+                        && let Some(previous) = entry.senses.last()
+                    {
+                        sense.pos = previous.pos.clone();
+                    }
+                    entry.senses.push(sense);
+                }
+                _ => {}
+            }
+        }
+        if entry.id != 0 && !entry.readings.is_empty() {
+            result.push(entry);
+        }
+    }
+    Ok(result)
+}
+
+#[derive(Deserialize)]
+struct JsonDictionary {
+    words: Vec<JsonWord>,
+}
+
+#[derive(Deserialize)]
+struct JsonWord {
+    id: String,
+    kanji: Vec<JsonForm>,
+    kana: Vec<JsonForm>,
+    sense: Vec<JsonSense>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonForm {
+    text: String,
+    common: bool,
+    /// Only readings have it.
+    #[serde(default)]
+    applies_to_kanji: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonSense {
+    part_of_speech: Vec<String>,
+    gloss: Vec<JsonGloss>,
+}
+
+#[derive(Deserialize)]
+struct JsonGloss {
+    lang: String,
+    text: String,
+}
+
+/// The JSON of jmdict-simplified: parts of speech are the short codes.
+pub fn parse_jmdict_json(json: &str) -> Result<Vec<JmdictEntry>, String> {
+    let dictionary = serde_json::from_str::<JsonDictionary>(json).map_err(|e| e.to_string())?;
+    Ok(dictionary
+        .words
+        .into_iter()
+        .filter(|w| !w.kana.is_empty())
+        .map(|w| JmdictEntry {
+            id: w.id.parse().unwrap_or(0),
+            common: w.kanji.iter().chain(&w.kana).any(|f| f.common),
+            restricted: w
+                .kana
+                .iter()
+                .filter_map(|f| {
+                    let forms = f.applies_to_kanji.as_ref()?;
+                    (!forms.iter().any(|k| k == ANY_KANJI)).then(|| (f.text.clone(), forms.clone()))
+                })
+                .collect(),
+            kanji: w.kanji.into_iter().map(|f| f.text).collect(),
+            readings: w.kana.into_iter().map(|f| f.text).collect(),
+            senses: w
+                .sense
+                .into_iter()
+                .map(|s| Sense {
+                    pos: s.part_of_speech,
+                    glosses: s.gloss.into_iter().map(|g| (g.lang, g.text)).collect(),
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE JMdict [
+<!ELEMENT JMdict (entry*)>
+<!ENTITY v1 "Ichidan verb">
+<!ENTITY vt "transitive verb">
+<!ENTITY n "noun (common) (futsuumeishi)">
+]>
+<JMdict>
+<entry>
+<ent_seq>1358280</ent_seq>
+<k_ele><keb>食べる</keb><ke_pri>ichi1</ke_pri></k_ele>
+<k_ele><keb>喰べる</keb></k_ele>
+<r_ele><reb>たべる</reb><re_pri>ichi1</re_pri></r_ele>
+<sense><pos>&v1;</pos><pos>&vt;</pos><gloss>to eat</gloss><gloss xml:lang="rus">есть</gloss></sense>
+<sense><gloss>to live on</gloss></sense>
+<sense><pos>&n;</pos><gloss>food</gloss></sense>
+</entry>
+<entry>
+<ent_seq>1000</ent_seq>
+<k_ele><keb>稀語</keb><ke_pri>news2</ke_pri></k_ele>
+<r_ele><reb>きご</reb><re_pri>nf40</re_pri></r_ele>
+<sense><pos>&n;</pos><gloss>rare word</gloss></sense>
+</entry>
+<entry>
+<ent_seq>1002</ent_seq>
+<k_ele><keb>日本</keb></k_ele>
+<k_ele><keb>日夲</keb></k_ele>
+<r_ele><reb>にほん</reb></r_ele>
+<r_ele><reb>やまと</reb><re_restr>日本</re_restr></r_ele>
+<r_ele><reb>ジャパン</reb><re_nokanji/></r_ele>
+<sense><pos>&n;</pos><gloss>Japan</gloss></sense>
+</entry>
+<entry>
+<ent_seq>1001</ent_seq>
+<r_ele><reb>ねこ</reb><re_pri>spec1</re_pri></r_ele>
+<sense><pos>&n;</pos><gloss xml:lang="ger">Katze</gloss></sense>
+</entry>
+</JMdict>"#;
+
+    fn entries() -> Vec<JmdictEntry> {
+        parse_jmdict(XML).unwrap()
+    }
+
+    #[test]
+    fn entities_of_the_dtd_are_expanded() {
+        assert_eq!(entries()[0].senses[0].pos, ["Ichidan verb", "transitive verb"]);
+    }
+
+    #[test]
+    fn sense_without_pos_has_the_ones_before() {
+        let senses = &entries()[0].senses;
+
+        assert_eq!(senses[1].pos, senses[0].pos);
+        assert_eq!(senses[2].pos, ["noun (common) (futsuumeishi)"]);
+    }
+
+    #[test]
+    fn gloss_without_lang_is_english() {
+        let entry = &entries()[0];
+
+        assert_eq!(
+            entry.senses[0].glosses,
+            [
+                ("eng".to_string(), "to eat".to_string()),
+                ("rus".to_string(), "есть".to_string())
+            ]
+        );
+        assert_eq!(entry.meanings(GlossLanguage::RUSSIAN), ["есть"]);
+        assert_eq!(entries()[3].senses[0].glosses[0].0, "ger");
+    }
+
+    #[test]
+    fn only_the_common_priorities_make_an_entry_common() {
+        let entries = entries();
+
+        assert!(entries[0].common);
+        assert!(!entries[1].common);
+        assert!(entries[3].common);
+    }
+
+    #[test]
+    fn readings_follow_restrictions_of_the_written_form() {
+        let entry = &entries()[2];
+
+        assert_eq!(entry.readings_for("日本"), ["にほん", "やまと"]);
+        assert_eq!(entry.readings_for("日夲"), ["にほん"]);
+        assert_eq!(entry.readings_for("ジャパン"), ["にほん", "やまと", "ジャパン"]);
+    }
+
+    #[test]
+    fn forms_and_readings_are_kept_in_order() {
+        let entry = &entries()[0];
+
+        assert_eq!(entry.id, 1358280);
+        assert_eq!(entry.kanji, ["食べる", "喰べる"]);
+        assert_eq!(entry.readings, ["たべる"]);
+    }
+
+    #[test]
+    fn json_entries_are_common_when_any_form_is() {
+        let json = r#"{"version": "3.6.1", "words": [
+            {"id": "1", "kanji": [{"common": false, "text": "猫", "tags": []}],
+             "kana": [{"common": true, "text": "ねこ", "tags": [], "appliesToKanji": ["*"]}],
+             "sense": [{"partOfSpeech": ["n"], "gloss": [{"lang": "eng", "text": "cat"}]}]},
+            {"id": "4", "kanji": [{"common": false, "text": "日本", "tags": []}],
+             "kana": [{"common": false, "text": "にほん", "tags": [], "appliesToKanji": ["*"]},
+                      {"common": false, "text": "ジャパン", "tags": [], "appliesToKanji": []}],
+             "sense": [{"partOfSpeech": ["n"], "gloss": [{"lang": "eng", "text": "Japan"}]}]},
+            {"id": "2", "kanji": [],
+             "kana": [{"common": false, "text": "にゃん", "tags": [], "appliesToKanji": []}],
+             "sense": [{"partOfSpeech": ["int"], "gloss": [{"lang": "eng", "text": "meow"}]}]},
+            {"id": "3", "kanji": [], "kana": [], "sense": []}
+        ]}"#;
+
+        let entries = parse_jmdict_json(json).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].common);
+        assert!(!entries[2].common);
+        assert_eq!(entries[1].readings_for("日本"), ["にほん"]);
+        assert_eq!(entries[0].kanji, ["猫"]);
+        assert_eq!(entries[0].senses[0].pos, ["n"]);
+        assert_eq!(entries[0].meanings(GlossLanguage::ENGLISH), ["cat"]);
+    }
+}
@@ -0,0 +1,290 @@
+/// Kana dataset: every row is listed once with its hiragana, katakana and
+/// Hepburn romaji, and belongs to one of the categories the Selector can toggle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KanaCategory {
+    Basic,
+    Voiced,
+    Combo,
+    Small,
+}
+
+impl KanaCategory {
+    pub const ALL: [KanaCategory; 4] = [
+        KanaCategory::Basic,
+        KanaCategory::Voiced,
+        KanaCategory::Combo,
+        KanaCategory::Small,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            KanaCategory::Basic => "Основные",
+            KanaCategory::Voiced => "Дакутэн и хандакутэн",
+            KanaCategory::Combo => "Ёон",
+            KanaCategory::Small => "Маленькие",
+        }
+    }
+}
+
+/// Small kana that are read together with the kana before them.
+const SMALL_KANA: &str = "ゃゅょぁぃぅぇぉゎ";
+
+pub struct KanaRowData {
+    pub category: KanaCategory,
+    /// (hiragana, katakana, romaji)
+    pub chars: &'static [(&'static str, &'static str, &'static str)],
+}
+
+pub const KANA_ROWS: &[KanaRowData] = &[
+    // Ряд «а» (あいうえお)
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[
+            ("あ", "ア", "a"),
+            ("い", "イ", "i"),
+            ("う", "ウ", "u"),
+            ("え", "エ", "e"),
+            ("お", "オ", "o"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[
+            ("か", "カ", "ka"),
+            ("き", "キ", "ki"),
+            ("く", "ク", "ku"),
+            ("け", "ケ", "ke"),
+            ("こ", "コ", "ko"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[
+            ("さ", "サ", "sa"),
+            ("し", "シ", "shi"),
+            ("す", "ス", "su"),
+            ("せ", "セ", "se"),
+            ("そ", "ソ", "so"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[
+            ("た", "タ", "ta"),
+            ("ち", "チ", "chi"),
+            ("つ", "ツ", "tsu"),
+            ("て", "テ", "te"),
+            ("と", "ト", "to"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[
+            ("な", "ナ", "na"),
+            ("に", "ニ", "ni"),
+            ("ぬ", "ヌ", "nu"),
+            ("ね", "ネ", "ne"),
+            ("の", "ノ", "no"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[
+            ("は", "ハ", "ha"),
+            ("ひ", "ヒ", "hi"),
+            ("ふ", "フ", "fu"),
+            ("へ", "ヘ", "he"),
+            ("ほ", "ホ", "ho"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[
+            ("ま", "マ", "ma"),
+            ("み", "ミ", "mi"),
+            ("む", "ム", "mu"),
+            ("め", "メ", "me"),
+            ("も", "モ", "mo"),
+        ],
+    },
+    // Ряд «я» — только 3 символа
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[("や", "ヤ", "ya"), ("ゆ", "ユ", "yu"), ("よ", "ヨ", "yo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[
+            ("ら", "ラ", "ra"),
+            ("り", "リ", "ri"),
+            ("る", "ル", "ru"),
+            ("れ", "レ", "re"),
+            ("ろ", "ロ", "ro"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Basic,
+        chars: &[("わ", "ワ", "wa"), ("を", "ヲ", "wo"), ("ん", "ン", "n")],
+    },
+    // Дакутэн (゛)
+    KanaRowData {
+        category: KanaCategory::Voiced,
+        chars: &[
+            ("が", "ガ", "ga"),
+            ("ぎ", "ギ", "gi"),
+            ("ぐ", "グ", "gu"),
+            ("げ", "ゲ", "ge"),
+            ("ご", "ゴ", "go"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Voiced,
+        chars: &[
+            ("ざ", "ザ", "za"),
+            ("じ", "ジ", "ji"),
+            ("ず", "ズ", "zu"),
+            ("ぜ", "ゼ", "ze"),
+            ("ぞ", "ゾ", "zo"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Voiced,
+        chars: &[
+            ("だ", "ダ", "da"),
+            ("ぢ", "ヂ", "ji"),
+            ("づ", "ヅ", "zu"),
+            ("で", "デ", "de"),
+            ("ど", "ド", "do"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Voiced,
+        chars: &[
+            ("ば", "バ", "ba"),
+            ("び", "ビ", "bi"),
+            ("ぶ", "ブ", "bu"),
+            ("べ", "ベ", "be"),
+            ("ぼ", "ボ", "bo"),
+        ],
+    },
+    // Хандакутэн (゜)
+    KanaRowData {
+        category: KanaCategory::Voiced,
+        chars: &[
+            ("ぱ", "パ", "pa"),
+            ("ぴ", "ピ", "pi"),
+            ("ぷ", "プ", "pu"),
+            ("ぺ", "ペ", "pe"),
+            ("ぽ", "ポ", "po"),
+        ],
+    },
+    // Ёон: слог на «и» + маленькие や/ゆ/よ
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("きゃ", "キャ", "kya"), ("きゅ", "キュ", "kyu"), ("きょ", "キョ", "kyo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("しゃ", "シャ", "sha"), ("しゅ", "シュ", "shu"), ("しょ", "ショ", "sho")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("ちゃ", "チャ", "cha"), ("ちゅ", "チュ", "chu"), ("ちょ", "チョ", "cho")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("にゃ", "ニャ", "nya"), ("にゅ", "ニュ", "nyu"), ("にょ", "ニョ", "nyo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("ひゃ", "ヒャ", "hya"), ("ひゅ", "ヒュ", "hyu"), ("ひょ", "ヒョ", "hyo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("みゃ", "ミャ", "mya"), ("みゅ", "ミュ", "myu"), ("みょ", "ミョ", "myo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("りゃ", "リャ", "rya"), ("りゅ", "リュ", "ryu"), ("りょ", "リョ", "ryo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("ぎゃ", "ギャ", "gya"), ("ぎゅ", "ギュ", "gyu"), ("ぎょ", "ギョ", "gyo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("じゃ", "ジャ", "ja"), ("じゅ", "ジュ", "ju"), ("じょ", "ジョ", "jo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("びゃ", "ビャ", "bya"), ("びゅ", "ビュ", "byu"), ("びょ", "ビョ", "byo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Combo,
+        chars: &[("ぴゃ", "ピャ", "pya"), ("ぴゅ", "ピュ", "pyu"), ("ぴょ", "ピョ", "pyo")],
+    },
+    // Маленькие кана набираются через «x», как в большинстве IME
+    KanaRowData {
+        category: KanaCategory::Small,
+        chars: &[
+            ("ぁ", "ァ", "xa"),
+            ("ぃ", "ィ", "xi"),
+            ("ぅ", "ゥ", "xu"),
+            ("ぇ", "ェ", "xe"),
+            ("ぉ", "ォ", "xo"),
+        ],
+    },
+    KanaRowData {
+        category: KanaCategory::Small,
+        chars: &[("ゃ", "ャ", "xya"), ("ゅ", "ュ", "xyu"), ("ょ", "ョ", "xyo")],
+    },
+    KanaRowData {
+        category: KanaCategory::Small,
+        chars: &[("っ", "ッ", "xtsu"), ("ゎ", "ヮ", "xwa")],
+    },
+];
+
+/// Folds katakana to hiragana, leaving every other character untouched.
+pub fn to_hiragana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Folds hiragana to katakana, the other way round.
+pub fn to_katakana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'ぁ'..='ゖ' => char::from_u32(c as u32 + 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Whether the text is written in kana only (the long vowel mark included).
+pub fn is_kana(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| matches!(c, 'ぁ'..='ゖ' | 'ァ'..='ヺ' | 'ー'))
+}
+
+/// Small ya, yu, yo and vowels belong to the kana before them; っ, ん and ー
+/// are morae of their own.
+pub fn morae(reading: &str) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    for c in reading.chars().filter(|c| !c.is_whitespace()) {
+        let small = to_hiragana(&c.to_string())
+            .chars()
+            .next()
+            .is_some_and(|h| SMALL_KANA.contains(h));
+        match result.last_mut() {
+            Some(last) if small => last.push(c),
+            _ => result.push(c.to_string()),
+        }
+    }
+    result
+}
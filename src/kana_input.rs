@@ -0,0 +1,76 @@
+use crate::romaji::{to_kana_final_in, to_kana_in, Script};
+use iced::widget::{button, text_input, Button, TextInput};
+use std::fmt::{Display, Formatter};
+
+/// Typing Japanese without a system IME: romaji turns into kana as it is typed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KanaInputMode {
+    #[default]
+    Off,
+    /// Hiragana, with syllables typed in capitals as katakana.
+    Hiragana,
+    Katakana,
+}
+
+impl KanaInputMode {
+    pub fn next(self) -> Self {
+        match self {
+            KanaInputMode::Off => KanaInputMode::Hiragana,
+            KanaInputMode::Hiragana => KanaInputMode::Katakana,
+            KanaInputMode::Katakana => KanaInputMode::Off,
+        }
+    }
+
+    fn script(self) -> Option<Script> {
+        match self {
+            KanaInputMode::Off => None,
+            KanaInputMode::Hiragana => Some(Script::ByCase),
+            KanaInputMode::Katakana => Some(Script::Katakana),
+        }
+    }
+
+    /// The text after a keystroke, with an unfinished syllable left as typed.
+    pub fn convert(self, typed: &str) -> String {
+        match self.script() {
+            Some(script) => to_kana_in(typed, script),
+            None => typed.to_string(),
+        }
+    }
+
+    /// The text once the field is submitted: a trailing `n` becomes ん.
+    pub fn finish(self, typed: &str) -> String {
+        match self.script() {
+            Some(script) => to_kana_final_in(typed, script),
+            None => typed.to_string(),
+        }
+    }
+}
+
+impl Display for KanaInputMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KanaInputMode::Off => write!(f, "A"),
+            KanaInputMode::Hiragana => write!(f, "あ"),
+            KanaInputMode::Katakana => write!(f, "ア"),
+        }
+    }
+}
+
+/// A `text_input` converting what is typed according to the mode. Style,
+/// size and `on_submit` are set by the caller as usual.
+pub fn kana_input<'a, M: Clone + 'a>(
+    placeholder: &str,
+    value: &str,
+    mode: KanaInputMode,
+    on_input: impl Fn(String) -> M + 'a,
+) -> TextInput<'a, M> {
+    text_input(placeholder, value).on_input(move |typed| on_input(mode.convert(&typed)))
+}
+
+/// Button switching A → あ → ア → A.
+pub fn mode_toggle<'a, M: Clone + 'a>(
+    mode: KanaInputMode,
+    on_change: fn(KanaInputMode) -> M,
+) -> Button<'a, M> {
+    button(iced::widget::text!("{}", mode)).on_press(on_change(mode.next()))
+}
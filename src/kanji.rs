@@ -0,0 +1,291 @@
+use crate::data_provider::kanji::{add_bare_kanji, load_kanji};
+use crate::dictionary::app_data_dir;
+use crate::lang::WordData;
+use crate::AppState;
+use std::collections::HashMap;
+use std::fs;
+
+pub const KANJIDIC_FILE: &str = "kanjidic2.xml";
+pub const KRADFILE: &str = "kradfile-u";
+
+/// One entry of KANJIDIC2 with the components from KRADFILE.
+#[derive(Clone, Debug, Default)]
+pub struct KanjiData {
+    pub id: u32,
+    pub literal: String,
+    /// Comma separated, as typed in the card filters.
+    pub on_readings: String,
+    pub kun_readings: String,
+    pub meanings: String,
+    pub strokes: u32,
+    pub grade: Option<u32>,
+    pub jlpt: Option<u32>,
+    /// Number of the classical (Kangxi) radical.
+    pub radical: u32,
+    /// Space separated components.
+    pub components: String,
+}
+
+impl KanjiData {
+    pub fn radical_char(&self) -> Option<char> {
+        if self.radical == 0 || self.radical > 214 {
+            return None;
+        }
+        // the Kangxi Radicals block is in the dictionary order
+        char::from_u32(0x2F00 + self.radical - 1)
+    }
+
+    /// Fields shown on cards and available to the card set filters as `more`.
+    pub fn fields(&self) -> HashMap<String, String> {
+        let mut fields = HashMap::from([
+            ("on".to_string(), self.on_readings.clone()),
+            ("kun".to_string(), self.kun_readings.clone()),
+            ("strokes".to_string(), self.strokes.to_string()),
+            ("components".to_string(), self.components.clone()),
+        ]);
+        if let Some(grade) = self.grade {
+            fields.insert("grade".to_string(), grade.to_string());
+        }
+        if let Some(jlpt) = self.jlpt {
+            fields.insert("jlpt".to_string(), jlpt.to_string());
+        }
+        if let Some(radical) = self.radical_char() {
+            fields.insert("radical".to_string(), radical.to_string());
+        }
+        fields
+    }
+
+    /// The kanji as a card of a kanji set: the literal on the front, English
+    /// meanings as the value and everything else in the additional fields.
+    pub fn to_word(&self) -> WordData {
+        WordData {
+            id: self.id,
+            key: self.literal.clone(),
+            value: self.meanings.clone(),
+            tags: String::new(),
+            additional: self.fields(),
+            group_id: 0,
+        }
+    }
+}
+
+/// Gives the kanji of the dictionary words rows of their own, so kanji card
+/// sets have cards before KANJIDIC is imported.
+pub fn add_dictionary_kanji(state: &mut AppState) {
+    let mut missing = vec![];
+    for c in state.dictionary.iter().flat_map(|w| w.key.chars()) {
+        if is_kanji(c) && !state.kanji.contains_key(&c) && !missing.contains(&c) {
+            missing.push(c);
+        }
+    }
+    if missing.is_empty() {
+        return;
+    }
+    add_bare_kanji(&missing, &state.connection);
+    state.kanji = load_kanji(&state.connection);
+}
+
+/// Dictionary words with the kanji, as a meaning for a kanji KANJIDIC
+/// hasn't described.
+pub fn words_with(kanji: char, dictionary: &[WordData]) -> String {
+    dictionary
+        .iter()
+        .filter(|w| w.key.contains(kanji))
+        .take(3)
+        .map(|w| format!("{} — {}", w.key, w.value))
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+pub fn is_kanji(c: char) -> bool {
+    matches!(c as u32, 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F)
+}
+
+/// Known kanji of a word in the order they are written, without repeats.
+pub fn kanji_in<'a>(key: &str, kanji: &'a HashMap<char, KanjiData>) -> Vec<&'a KanjiData> {
+    let mut result: Vec<&KanjiData> = vec![];
+    for c in key.chars().filter(|c| is_kanji(*c)) {
+        if let Some(data) = kanji.get(&c)
+            && !result.iter().any(|k| k.id == data.id)
+        {
+            result.push(data);
+        }
+    }
+    result
+}
+
+/// Kanji that contain every one of the components.
+pub fn with_components<'a>(
+    components: &[char],
+    kanji: &'a HashMap<char, KanjiData>,
+) -> Vec<&'a KanjiData> {
+    let mut result = kanji
+        .values()
+        .filter(|k| components.iter().all(|c| k.components.contains(*c)))
+        .collect::<Vec<&KanjiData>>();
+    result.sort_by_key(|k| (k.strokes, k.literal.clone()));
+    result
+}
+
+/// Reads `kanjidic2.xml`. Only English meanings are kept, they are the ones
+/// without `m_lang`.
+pub fn parse_kanjidic(xml: &str) -> Result<Vec<KanjiData>, String> {
+    let document = roxmltree::Document::parse_with_options(
+        xml,
+        roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut result = vec![];
+    for character in document
+        .root_element()
+        .children()
+        .filter(|n| n.has_tag_name("character"))
+    {
+        let mut kanji = KanjiData::default();
+        let mut on = vec![];
+        let mut kun = vec![];
+        let mut meanings = vec![];
+
+        for node in character.descendants().filter(|n| n.is_element()) {
+            let text = node.text().unwrap_or("").trim();
+            match node.tag_name().name() {
+                "literal" => kanji.literal = text.to_string(),
+                "rad_value" if node.attribute("rad_type") == Some("classical") => {
+                    kanji.radical = text.parse().unwrap_or(0)
+                }
+                // the first count is the accepted one, the rest are common miscounts
+                "stroke_count" if kanji.strokes == 0 => kanji.strokes = text.parse().unwrap_or(0),
+                "grade" => kanji.grade = text.parse().ok(),
+                "jlpt" => kanji.jlpt = text.parse().ok(),
+                "reading" => match node.attribute("r_type") {
+                    Some("ja_on") => on.push(text.to_string()),
+                    Some("ja_kun") => kun.push(text.to_string()),
+                    _ => {}
+                },
+                "meaning" if node.attribute("m_lang").is_none() => meanings.push(text.to_string()),
+                _ => {}
+            }
+        }
+
+        if kanji.literal.is_empty() {
+            continue;
+        }
+        kanji.on_readings = on.join(", ");
+        kanji.kun_readings = kun.join(", ");
+        kanji.meanings = meanings.join(", ");
+        result.push(kanji);
+    }
+
+    if result.is_empty() {
+        return Err("В файле нет ни одного кандзи".to_string());
+    }
+    Ok(result)
+}
+
+/// Reads KRADFILE in UTF-8 (`kradfile-u`): `亜 : ｜ 一 口` per line.
+pub fn parse_kradfile(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (kanji, components) = line.split_once(" : ")?;
+            Some((kanji.trim().to_string(), components.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Reads KANJIDIC2 and, when present, KRADFILE put next to the database.
+pub fn read_kanji_files() -> Result<Vec<KanjiData>, String> {
+    let dir = app_data_dir();
+    let xml = fs::read_to_string(dir.join(KANJIDIC_FILE))
+        .map_err(|e| format!("{}: {}", KANJIDIC_FILE, e))?;
+    let mut list = parse_kanjidic(&xml)?;
+
+    if let Ok(text) = fs::read_to_string(dir.join(KRADFILE)) {
+        let components = parse_kradfile(&text);
+        for kanji in &mut list {
+            if let Some(found) = components.get(&kanji.literal) {
+                kanji.components = found.clone();
+            }
+        }
+    }
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KANJIDIC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<kanjidic2>
+<header><file_version>4</file_version></header>
+<character>
+<literal>日</literal>
+<radical>
+<rad_value rad_type="classical">72</rad_value>
+<rad_value rad_type="nelson_c">73</rad_value>
+</radical>
+<misc>
+<grade>1</grade>
+<stroke_count>4</stroke_count>
+<stroke_count>3</stroke_count>
+<jlpt>4</jlpt>
+</misc>
+<reading_meaning>
+<rmgroup>
+<reading r_type="pinyin">ri4</reading>
+<reading r_type="ja_on">ニチ</reading>
+<reading r_type="ja_on">ジツ</reading>
+<reading r_type="ja_kun">ひ</reading>
+<reading r_type="ja_kun">-か</reading>
+<meaning>day</meaning>
+<meaning>sun</meaning>
+<meaning m_lang="fr">jour</meaning>
+</rmgroup>
+<nanori>あき</nanori>
+</reading_meaning>
+</character>
+<character>
+<literal>曙</literal>
+<radical><rad_value rad_type="classical">72</rad_value></radical>
+<misc><stroke_count>17</stroke_count></misc>
+</character>
+</kanjidic2>"#;
+
+    #[test]
+    fn kanjidic_character_becomes_kanji() {
+        let list = parse_kanjidic(KANJIDIC).unwrap();
+        assert_eq!(list.len(), 2);
+
+        let day = &list[0];
+        assert_eq!(day.literal, "日");
+        assert_eq!(day.on_readings, "ニチ, ジツ");
+        assert_eq!(day.kun_readings, "ひ, -か");
+        assert_eq!(day.meanings, "day, sun");
+        assert_eq!(day.grade, Some(1));
+        assert_eq!(day.jlpt, Some(4));
+        assert_eq!(day.strokes, 4);
+        assert_eq!(day.radical, 72);
+
+        let dawn = &list[1];
+        assert_eq!((dawn.grade, dawn.jlpt, dawn.strokes), (None, None, 17));
+        assert!(dawn.meanings.is_empty());
+    }
+
+    #[test]
+    fn kanjidic_without_characters_is_an_error() {
+        assert!(parse_kanjidic("<kanjidic2><header/></kanjidic2>").is_err());
+        assert!(parse_kanjidic("<kanjidic2>").is_err());
+    }
+
+    #[test]
+    fn kradfile_lines_give_components() {
+        let components = parse_kradfile("# KRADFILE\n亜 : ｜ 一 口\n明 : 日 月\n");
+        assert_eq!(components.len(), 2);
+        assert_eq!(components["亜"], "｜ 一 口");
+        assert_eq!(components["明"], "日 月");
+    }
+}
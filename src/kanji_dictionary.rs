@@ -0,0 +1,260 @@
+use crate::data_provider::kanji::{load_kanji, save_kanji};
+use crate::dictionary::app_data_dir;
+use crate::kanji::{is_kanji, read_kanji_files, with_components, KanjiData, KANJIDIC_FILE, KRADFILE};
+use crate::Page::PreviousPage;
+use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
+use iced::alignment::Vertical::Center;
+use iced::widget::{button, column, container, row, rule, scrollable, space, text, text_input, Column};
+use iced::{Element, Fill, Length, Task};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::task::spawn_blocking;
+
+const MAX_RESULTS: usize = 200;
+
+pub struct KanjiDictionaryState {
+    state: Arc<Mutex<AppState>>,
+    search: String,
+    components: String,
+    selected: Option<char>,
+    importing: bool,
+    status: String,
+}
+
+#[derive(Clone)]
+pub enum KanjiDictionaryMessage {
+    Back,
+    Search(String),
+    SetComponents(String),
+    AddComponent(String),
+    Select(char),
+    Import,
+    Imported(Result<Vec<KanjiData>, String>),
+}
+
+impl NavigatedPage<KanjiDictionaryMessage> for KanjiDictionaryState {
+    fn navigate(&self, message: &KanjiDictionaryMessage) -> Option<Page> {
+        if let KanjiDictionaryMessage::Back = message {
+            Some(PreviousPage)
+        } else {
+            None
+        }
+    }
+}
+
+impl KanjiDictionaryState {
+    pub fn new(state: Arc<Mutex<AppState>>) -> Self {
+        Self {
+            state,
+            search: String::new(),
+            components: String::new(),
+            selected: None,
+            importing: false,
+            status: String::new(),
+        }
+    }
+
+    /// Opened from a word: kanji sharing the component.
+    pub fn for_component(component: String, state: Arc<Mutex<AppState>>) -> Self {
+        Self {
+            components: component,
+            ..Self::new(state)
+        }
+    }
+
+    pub fn update(&mut self, message: KanjiDictionaryMessage) -> Task<RootMessage> {
+        match message {
+            KanjiDictionaryMessage::Back => {}
+            KanjiDictionaryMessage::Search(search) => self.search = search,
+            KanjiDictionaryMessage::SetComponents(components) => self.components = components,
+            KanjiDictionaryMessage::AddComponent(component) => {
+                if !self.components.contains(&component) {
+                    self.components = format!("{} {}", self.components, component)
+                        .trim()
+                        .to_string();
+                }
+            }
+            KanjiDictionaryMessage::Select(literal) => self.selected = Some(literal),
+            KanjiDictionaryMessage::Import => {
+                if self.importing {
+                    return Task::none();
+                }
+                self.importing = true;
+                self.status = "Импорт…".to_string();
+                return Task::perform(
+                    async { spawn_blocking(read_kanji_files).await.unwrap() },
+                    |result| RootMessage::KanjiDictionary(KanjiDictionaryMessage::Imported(result)),
+                );
+            }
+            KanjiDictionaryMessage::Imported(result) => {
+                self.importing = false;
+                match result {
+                    Ok(list) => {
+                        let mut state = self.state.lock().unwrap();
+                        save_kanji(&list, &state.connection);
+                        state.kanji = load_kanji(&state.connection);
+                        self.status = format!("Загружено кандзи: {}", list.len());
+                    }
+                    Err(e) => self.status = e,
+                }
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, KanjiDictionaryMessage> {
+        let state = self.state.lock().unwrap();
+        let found = self.found(&state);
+
+        let mut list = Column::new().width(Fill);
+        for kanji in found.iter().take(MAX_RESULTS) {
+            let Some(literal) = kanji.literal.chars().next() else {
+                continue;
+            };
+            list = list.push(
+                button(text!(
+                    "{}  {}  {}",
+                    kanji.literal,
+                    kanji.on_readings,
+                    kanji.meanings
+                ))
+                .style(button::text)
+                .on_press(KanjiDictionaryMessage::Select(literal)),
+            );
+        }
+        if found.len() > MAX_RESULTS {
+            list = list.push(text!("…и ещё {}", found.len() - MAX_RESULTS));
+        }
+
+        let details: Element<'_, KanjiDictionaryMessage> =
+            match self.selected.and_then(|c| state.kanji.get(&c)) {
+                Some(kanji) => {
+                    let words = state
+                        .dictionary
+                        .iter()
+                        .filter(|w| w.key.contains(&kanji.literal))
+                        .map(|w| format!("{} — {}", w.key, w.value))
+                        .collect::<Vec<String>>();
+                    column![
+                        kanji_details(kanji, KanjiDictionaryMessage::AddComponent),
+                        rule::horizontal(2),
+                        text!("Слова из словаря"),
+                        text!("{}", words.join("\n")),
+                    ]
+                    .spacing(DEFAULT_SPACING)
+                    .into()
+                }
+                None => space().into(),
+            };
+
+        container(
+            column![
+                button("Назад").on_press(KanjiDictionaryMessage::Back),
+                row![
+                    button("Импорт").on_press(KanjiDictionaryMessage::Import),
+                    text!(
+                        "{} и {} из {}",
+                        KANJIDIC_FILE,
+                        KRADFILE,
+                        app_data_dir().display()
+                    ),
+                    text!("{}", self.status),
+                ]
+                .align_y(Center)
+                .spacing(DEFAULT_SPACING),
+                row![
+                    text_input("Кандзи, чтение или значение", &self.search)
+                        .on_input(KanjiDictionaryMessage::Search),
+                    text_input("Компоненты", &self.components)
+                        .on_input(KanjiDictionaryMessage::SetComponents),
+                ]
+                .spacing(DEFAULT_SPACING),
+                row![
+                    column![
+                        text!("Всего в базе: {}", state.kanji.len()),
+                        scrollable(list).height(Fill),
+                    ]
+                    .spacing(DEFAULT_SPACING)
+                    .width(Length::FillPortion(1)),
+                    scrollable(details).width(Length::FillPortion(1)),
+                ]
+                .spacing(DEFAULT_SPACING)
+                .height(Fill),
+            ]
+            .spacing(DEFAULT_SPACING),
+        )
+        .padding(10)
+        .into()
+    }
+
+    /// Kanji matching the search and containing every typed component. With
+    /// both fields empty, the kanji met in the word dictionary.
+    fn found<'a>(&self, state: &'a AppState) -> Vec<&'a KanjiData> {
+        let components = self
+            .components
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<Vec<char>>();
+        let search = self.search.trim().to_lowercase();
+
+        let mut found = with_components(&components, &state.kanji);
+        if !search.is_empty() {
+            found.retain(|k| {
+                search.chars().filter(|c| is_kanji(*c)).any(|c| k.literal.starts_with(c))
+                    || k.on_readings.contains(&search)
+                    || k.kun_readings.contains(&search)
+                    || k.meanings.to_lowercase().contains(&search)
+            });
+        } else if components.is_empty() {
+            let used = state
+                .dictionary
+                .iter()
+                .flat_map(|w| w.key.chars().filter(|c| is_kanji(*c)))
+                .collect::<HashSet<char>>();
+            found.retain(|k| k.literal.chars().any(|c| used.contains(&c)));
+        }
+        found
+    }
+}
+
+/// Readings, meanings and makeup of a kanji. Components are buttons, e.g. to
+/// look up other kanji sharing them.
+pub(crate) fn kanji_details<'a, M: Clone + 'a>(
+    kanji: &KanjiData,
+    on_component: fn(String) -> M,
+) -> Element<'a, M> {
+    let mut makeup = row![].spacing(5).align_y(Center);
+    if let Some(radical) = kanji.radical_char() {
+        makeup = makeup.push(text!("Ключ {} ({})", radical, kanji.radical));
+    }
+    for component in kanji.components.split_whitespace() {
+        makeup = makeup.push(
+            button(text!("{}", component))
+                .style(button::secondary)
+                .on_press(on_component(component.to_string())),
+        );
+    }
+
+    let mut levels = vec![format!("Черт: {}", kanji.strokes)];
+    if let Some(grade) = kanji.grade {
+        levels.push(format!("Класс: {}", grade));
+    }
+    if let Some(jlpt) = kanji.jlpt {
+        levels.push(format!("JLPT: {}", jlpt));
+    }
+
+    row![
+        text!("{}", kanji.literal).size(64),
+        column![
+            text!("Он: {}", kanji.on_readings),
+            text!("Кун: {}", kanji.kun_readings),
+            text!("{}", kanji.meanings),
+            text!("{}", levels.join(", ")),
+            makeup,
+        ]
+        .spacing(5),
+    ]
+    .align_y(Center)
+    .spacing(DEFAULT_SPACING)
+    .into()
+}
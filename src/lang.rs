@@ -1,7 +1,9 @@
 use crate::data_provider::card_stats::{
     add_stat, delete_stat, load_stats_of_set, update_stat_score,
 };
-use crate::repetitions::CardSetSettings;
+use crate::fields::FieldSpec;
+use crate::kana::{KanaCategory, KANA_ROWS};
+use crate::repetitions::{CardKind, CardSetSettings};
 use crate::AppState;
 use chrono::{DateTime, Utc};
 use rand::distr::weighted::WeightedIndex;
@@ -10,8 +12,9 @@ use rand::rng;
 use rand::rngs::ThreadRng;
 use serde::{Deserialize, Serialize};
 use std::cmp::min;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 const MAX_HISTORY_LEN: usize = 20;
 const MAX_HISTORY_LEN_PART: f32 = 0.33;
@@ -21,8 +24,14 @@ const FADE_PER_DAY: f32 = 0.95;
 pub struct KanaSet {
     name: String,
     pub(crate) chars_type: KanaType,
-    pub(crate) dictionary: Vec<Vec<(String, String)>>,
-    pub(crate) include_map: [bool; 10],
+    pub(crate) dictionary: Vec<KanaRow>,
+    pub(crate) include_map: Vec<bool>,
+}
+
+#[derive(Clone, Debug)]
+pub struct KanaRow {
+    pub category: KanaCategory,
+    pub chars: Vec<(String, String)>,
 }
 
 #[derive(Clone, Debug)]
@@ -33,189 +42,82 @@ pub enum KanaType {
 
 impl KanaSet {
     pub fn hiragana() -> Self {
-        Self {
-            name: "Хирагана".to_string(),
-            chars_type: KanaType::Hiragana,
-            dictionary: vec![
-                vec![
-                    (String::from("ぁ"), String::from("a")),
-                    (String::from("ぃ"), String::from("i")),
-                    (String::from("ぅ"), String::from("u")),
-                    (String::from("ぇ"), String::from("e")),
-                    (String::from("ぉ"), String::from("o")),
-                ],
-                vec![
-                    (String::from("さ"), String::from("sa")),
-                    (String::from("し"), String::from("shi")),
-                    (String::from("す"), String::from("su")),
-                    (String::from("せ"), String::from("se")),
-                    (String::from("そ"), String::from("so")),
-                ],
-                vec![
-                    (String::from("か"), String::from("ka")),
-                    (String::from("き"), String::from("ki")),
-                    (String::from("く"), String::from("ku")),
-                    (String::from("け"), String::from("ke")),
-                    (String::from("こ"), String::from("ko")),
-                ],
-                // Ряд «та» (たちつてと)
-                vec![
-                    (String::from("た"), String::from("ta")),
-                    (String::from("ち"), String::from("chi")),
-                    (String::from("つ"), String::from("tsu")),
-                    (String::from("て"), String::from("te")),
-                    (String::from("と"), String::from("to")),
-                ],
-                // Ряд «на» (なにぬねの)
-                vec![
-                    (String::from("な"), String::from("na")),
-                    (String::from("に"), String::from("ni")),
-                    (String::from("ぬ"), String::from("nu")),
-                    (String::from("ね"), String::from("ne")),
-                    (String::from("の"), String::from("no")),
-                ],
-                // Ряд «ха» (はひふへほ)
-                vec![
-                    (String::from("は"), String::from("ha")),
-                    (String::from("ひ"), String::from("hi")),
-                    (String::from("ふ"), String::from("fu")),
-                    (String::from("へ"), String::from("he")),
-                    (String::from("ほ"), String::from("ho")),
-                ],
-                // Ряд «ма» (まみむめも)
-                vec![
-                    (String::from("ま"), String::from("ma")),
-                    (String::from("み"), String::from("mi")),
-                    (String::from("む"), String::from("mu")),
-                    (String::from("め"), String::from("me")),
-                    (String::from("も"), String::from("mo")),
-                ],
-                // Ряд «я» (やゆよ) — только 3 символа
-                vec![
-                    (String::from("や"), String::from("ya")),
-                    (String::from("ゆ"), String::from("yu")),
-                    (String::from("よ"), String::from("yo")),
-                ],
-                // Ряд «ра» (らりるれろ)
-                vec![
-                    (String::from("ら"), String::from("ra")),
-                    (String::from("り"), String::from("ri")),
-                    (String::from("る"), String::from("ru")),
-                    (String::from("れ"), String::from("re")),
-                    (String::from("ろ"), String::from("ro")),
-                ],
-                vec![
-                    (String::from("わ"), String::from("wa")),
-                    (String::from("を"), String::from("wo")),
-                    (String::from("ん"), String::from("n")),
-                ],
-            ],
-            include_map: [true; 10],
-        }
+        Self::from_rows("Хирагана", KanaType::Hiragana)
     }
 
     pub fn katakana() -> Self {
-        Self {
-            name: "Катакана".to_string(),
-            chars_type: KanaType::Katakana,
-            dictionary: vec![
-                vec![
-                    (String::from("ァ"), String::from("a")),
-                    (String::from("ィ"), String::from("i")),
-                    (String::from("ゥ"), String::from("u")),
-                    (String::from("ェ"), String::from("e")),
-                    (String::from("ォ"), String::from("o")),
-                ],
-                vec![
-                    (String::from("サ"), String::from("sa")),
-                    (String::from("シ"), String::from("shi")),
-                    (String::from("ス"), String::from("su")),
-                    (String::from("セ"), String::from("se")),
-                    (String::from("ソ"), String::from("so")),
-                ],
-                vec![
-                    (String::from("カ"), String::from("ka")),
-                    (String::from("キ"), String::from("ki")),
-                    (String::from("ク"), String::from("ku")),
-                    (String::from("ケ"), String::from("ke")),
-                    (String::from("コ"), String::from("ko")),
-                ],
-                // Ряд «та» (タチツテト)
-                vec![
-                    (String::from("タ"), String::from("ta")),
-                    (String::from("チ"), String::from("chi")),
-                    (String::from("ツ"), String::from("tsu")),
-                    (String::from("テ"), String::from("te")),
-                    (String::from("ト"), String::from("to")),
-                ],
-                // Ряд «на» (ナニヌネノ)
-                vec![
-                    (String::from("ナ"), String::from("na")),
-                    (String::from("ニ"), String::from("ni")),
-                    (String::from("ヌ"), String::from("nu")),
-                    (String::from("ネ"), String::from("ne")),
-                    (String::from("ノ"), String::from("no")),
-                ],
-                // Ряд «ха» (ハヒフヘホ)
-                vec![
-                    (String::from("ハ"), String::from("ha")),
-                    (String::from("ヒ"), String::from("hi")),
-                    (String::from("フ"), String::from("fu")),
-                    (String::from("ヘ"), String::from("he")),
-                    (String::from("ホ"), String::from("ho")),
-                ],
-                // Ряд «ма» (マミムメモ)
-                vec![
-                    (String::from("マ"), String::from("ma")),
-                    (String::from("ミ"), String::from("mi")),
-                    (String::from("ム"), String::from("mu")),
-                    (String::from("メ"), String::from("me")),
-                    (String::from("モ"), String::from("mo")),
-                ],
-                // Ряд «я» (ヤユヨ) — только 3 символа
-                vec![
-                    (String::from("ヤ"), String::from("ya")),
-                    (String::from("ユ"), String::from("yu")),
-                    (String::from("ヨ"), String::from("yo")),
-                ],
-                // Ряд «ра» (ラリルレロ)
-                vec![
-                    (String::from("ラ"), String::from("ra")),
-                    (String::from("リ"), String::from("ri")),
-                    (String::from("ル"), String::from("ru")),
-                    (String::from("レ"), String::from("re")),
-                    (String::from("ロ"), String::from("ro")),
-                ],
-                vec![
-                    (String::from("ワ"), String::from("wa")),
-                    (String::from("ヲ"), String::from("wo")),
-                    (String::from("ン"), String::from("n")),
-                ],
-            ],
-            include_map: [true; 10],
-        }
+        Self::from_rows("Катакана", KanaType::Katakana)
     }
 
-    /*    pub fn next(&mut self) -> (String, String) {
-        let current_set = self.list();
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-        let mut rand = rand::rng();
-        let index: u32 = rand.random();
-        current_set[index as usize % current_set.len()].clone()
-    }*/
+    fn from_rows(name: &str, chars_type: KanaType) -> Self {
+        let dictionary = KANA_ROWS
+            .iter()
+            .map(|row| KanaRow {
+                category: row.category,
+                chars: row
+                    .chars
+                    .iter()
+                    .map(|(hiragana, katakana, roman)| {
+                        let kana = match chars_type {
+                            KanaType::Hiragana => hiragana,
+                            KanaType::Katakana => katakana,
+                        };
+                        (kana.to_string(), roman.to_string())
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<KanaRow>>();
+        let include_map = dictionary
+            .iter()
+            .map(|row| row.category == KanaCategory::Basic)
+            .collect();
 
-    pub fn list(&self) -> Vec<(String, String)> {
-        let mut current_set: Vec<(String, String)> = Vec::new();
+        Self {
+            name: name.to_string(),
+            chars_type,
+            dictionary,
+            include_map,
+        }
+    }
 
-        for i in 0..10 {
-            if self.include_map[i] {
-                self.dictionary[i].iter().for_each(|v| {
-                    current_set.push(v.clone());
-                });
+    /// Enables or disables every row of the category.
+    pub fn set_category(&mut self, category: KanaCategory, include: bool) {
+        for (row, flag) in self.dictionary.iter().zip(self.include_map.iter_mut()) {
+            if row.category == category {
+                *flag = include;
             }
         }
+    }
+
+    pub fn is_category_included(&self, category: KanaCategory) -> bool {
+        self.dictionary
+            .iter()
+            .zip(&self.include_map)
+            .filter(|(row, _)| row.category == category)
+            .all(|(_, flag)| *flag)
+    }
+
+    pub fn list(&self) -> Vec<(String, String)> {
+        self.dictionary
+            .iter()
+            .zip(&self.include_map)
+            .filter(|(_, flag)| **flag)
+            .flat_map(|(row, _)| row.chars.iter().cloned())
+            .collect()
+    }
 
-        current_set
+    /// Enabled kana that can stand on their own as a syllable.
+    pub fn syllables(&self) -> Vec<(String, String)> {
+        self.dictionary
+            .iter()
+            .zip(&self.include_map)
+            .filter(|(row, flag)| **flag && row.category != KanaCategory::Small)
+            .flat_map(|(row, _)| row.chars.iter().cloned())
+            .collect()
     }
 }
 
@@ -230,6 +132,56 @@ impl PartialEq<Self> for KanaSet {
     }
 }
 
+/// Lifetime results for one kana across all quiz sessions.
+#[derive(Clone, Debug, Default)]
+pub struct KanaStatistics {
+    pub kana: String,
+    pub attempts: u32,
+    pub errors: u32,
+    pub helps: u32,
+    pub total_time_ms: i64,
+}
+
+impl KanaStatistics {
+    pub fn new(kana: &str) -> Self {
+        Self {
+            kana: kana.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, correct: bool, helped: bool, time: Duration) {
+        self.attempts += 1;
+        if !correct {
+            self.errors += 1;
+        }
+        if helped {
+            self.helps += 1;
+        }
+        self.total_time_ms += time.as_millis() as i64;
+    }
+
+    pub fn accuracy(&self) -> Option<f32> {
+        if self.attempts == 0 {
+            return None;
+        }
+        let missed = (self.errors + self.helps).min(self.attempts);
+        Some(1.0 - missed as f32 / self.attempts as f32)
+    }
+
+    /// Smoothed share of missed answers, so unseen kana still get drilled.
+    pub fn error_rate(&self) -> f32 {
+        (self.errors + self.helps + 1) as f32 / (self.attempts + 2) as f32
+    }
+
+    pub fn average_time_ms(&self) -> Option<i64> {
+        if self.attempts == 0 {
+            return None;
+        }
+        Some(self.total_time_ms / self.attempts as i64)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WordData {
     pub id: u32,
@@ -257,6 +209,8 @@ impl WordData {
 pub struct WordGroup {
     pub id: u32,
     pub name: String,
+    /// Fields of `additional` the words of the group have.
+    pub fields: Vec<FieldSpec>,
 }
 
 #[derive(Clone, PartialEq)]
@@ -316,6 +270,7 @@ pub struct CardSet {
     generator: ThreadRng,
     state: Arc<Mutex<AppState>>,
     history: Vec<usize>,
+    kind: CardKind,
 }
 
 impl CardSet {
@@ -341,14 +296,14 @@ impl CardSet {
                 }
             })
             .for_each(|mut new_statistic| {
-                add_stat(&mut new_statistic, &state_locked.connection);
+                add_stat(&mut new_statistic, settings.kind, &state_locked.connection);
                 current_set.push(new_statistic);
             });
 
         let mut index = 0;
         for stat in current_set.clone() {
             if !word_ids.contains(&stat.word_id) {
-                delete_stat(&stat, &state_locked.connection);
+                delete_stat(&stat, settings.kind, &state_locked.connection);
                 current_set.remove(index);
             } else {
                 index += 1;
@@ -369,6 +324,7 @@ impl CardSet {
             generator: rng(),
             state: state_for,
             history: vec![],
+            kind: settings.kind,
         }
     }
 
@@ -398,7 +354,7 @@ impl CardSet {
         self.last_weights
             .update_weights(&[(self.current_word_index.unwrap(), &new_weight)])
             .unwrap();
-        { update_stat_score(word, &self.state.lock().unwrap().connection) }
+        update_stat_score(word, self.kind, &self.state.lock().unwrap().connection)
     }
 
     fn history_len(&self) -> usize {
@@ -411,4 +367,56 @@ impl CardSet {
     pub fn len(&self) -> usize {
         self.set.len()
     }
+
+    pub fn words(&self) -> &[WordData] {
+        &self.words
+    }
+
+    /// Drops the score of the words in the set to the lowest, so Repetition
+    /// shows them first. Words the set has no card for yet get one; words
+    /// its filter doesn't take are left alone. Returns how many were queued.
+    pub fn prioritize(settings: &CardSetSettings, word_ids: &[u32], state: &AppState) -> usize {
+        let in_set = settings
+            .get_word_list(state)
+            .iter()
+            .map(|w| w.id)
+            .collect::<HashSet<u32>>();
+        let stats = load_stats_of_set(settings, &state.connection);
+        let mut count = 0;
+        for word_id in word_ids.iter().filter(|id| in_set.contains(id)) {
+            match stats.iter().find(|s| s.word_id == *word_id) {
+                Some(stat) => {
+                    let mut stat = stat.clone();
+                    stat.score = 1;
+                    update_stat_score(&stat, settings.kind, &state.connection);
+                }
+                None => {
+                    let mut stat = CardStatistics {
+                        id: 0,
+                        word_id: *word_id,
+                        set_id: settings.id,
+                        last_open: Utc::now(),
+                        score: 1,
+                    };
+                    add_stat(&mut stat, settings.kind, &state.connection);
+                }
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Counts a failed answer for each of the words that already has a card
+    /// in the set, as if it was opened in Repetition and not remembered.
+    pub fn mark_failed(settings: &CardSetSettings, word_ids: &[u32], state: &AppState) -> usize {
+        let stats = load_stats_of_set(settings, &state.connection);
+        let mut count = 0;
+        for stat in stats.iter().filter(|s| word_ids.contains(&s.word_id)) {
+            let mut stat = stat.clone();
+            stat.update(WordOpenMode::None);
+            update_stat_score(&stat, settings.kind, &state.connection);
+            count += 1;
+        }
+        count
+    }
 }
@@ -1,38 +1,87 @@
 #![windows_subsystem = "windows"]
+mod answer_check;
+mod choices;
+mod cloze;
+mod confusables;
 mod data_provider;
 mod dictionary;
 mod dictionary_test;
+mod discrimination;
+mod exam;
+mod fields;
+mod furigana;
+mod group_fields;
+mod handwriting;
+mod jmdict;
+mod kana;
+mod kana_input;
+mod kanji;
+mod kanji_dictionary;
 mod lang;
+mod matching;
+mod media;
+mod pitch;
 mod quiz;
 mod randomizer;
+mod reading_drill;
+mod reading_review;
 mod repetition;
 mod repetitions;
+mod reverse_quiz;
+mod romaji;
+mod romanization;
 mod selector;
+mod speed_run;
+mod stroke_guide;
+mod strokes;
 mod word;
+mod worksheet;
 mod writing;
 
 use crate::data_provider::card_sets::load_sets;
+use crate::confusables::Confusions;
+use crate::data_provider::kana_stats::{load_kana_confusions, load_kana_stats};
+use crate::data_provider::kanji::load_kanji;
+use crate::data_provider::settings::load_setting;
+use crate::data_provider::word_confusions::load_word_confusions;
+use crate::choices::WordConfusions;
 use crate::data_provider::words::{create_db, load_word_groups, load_words};
 use crate::dictionary::{app_data_dir, DictionaryMessage, DictionaryState};
 use crate::dictionary_test::{DictionaryQuizMessage, DictionaryQuizState};
-use crate::lang::{WordData, WordGroup};
+use crate::discrimination::{DiscriminationMessage, DiscriminationState};
+use crate::exam::{ExamMessage, ExamState};
+use crate::group_fields::{GroupFieldsMessage, GroupFieldsState};
+use crate::answer_check::{AnswerRules, ANSWER_RULES};
+use crate::jmdict::{GlossLanguage, GLOSS_LANGUAGE};
+use crate::kana_input::KanaInputMode;
+use crate::kanji::KanjiData;
+use crate::kanji_dictionary::{KanjiDictionaryMessage, KanjiDictionaryState};
+use crate::lang::{KanaStatistics, WordData, WordGroup};
+use crate::matching::{MatchingMessage, MatchingState};
 use crate::quiz::*;
 use crate::randomizer::randomizer::{RandomizerMessage, RandomizerState};
+use crate::reading_drill::{ReadingMessage, ReadingState};
+use crate::reading_review::{ReadingReviewMessage, ReadingReviewState};
 use crate::repetition::{RepetitionMessage, RepetitionState};
 use crate::repetitions::{CardSetSettings, RepetitionsMessage, RepetitionsState};
+use crate::reverse_quiz::{ReverseQuizMessage, ReverseQuizState};
 use crate::selector::*;
 use crate::word::{WordMessage, WordState};
 use crate::writing::{WritingMessage, WritingState};
 use crate::Page::{
-    Dictionary, DictionaryQuiz, Quiz, Randomizer, Repetition, Repetitions, Selector, Word, Writing,
+    Dictionary, DictionaryQuiz, Discrimination, Exam, GroupFields, KanjiDictionary, Matching,
+    Quiz, Randomizer, Reading, ReadingReview, Repetition, Repetitions, ReverseQuiz, Selector,
+    Word, Writing,
 };
 use crate::RootMessage::Keyboard;
 use iced::keyboard::Event;
 use iced::widget::text;
-use iced::{keyboard, Element, Subscription};
+use iced::{keyboard, time, Element, Subscription};
 use iced::{Font, Task};
 use rusqlite::Connection;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 const DEFAULT_SPACING: f32 = 10.0;
 
@@ -45,14 +94,29 @@ fn main() -> iced::Result {
         .run()
 }
 
-fn subscription(_state: &ScreenState) -> Subscription<RootMessage> {
-    keyboard::listen().map(|e| Keyboard(e))
+fn subscription(state: &ScreenState) -> Subscription<RootMessage> {
+    let keyboard = keyboard::listen().map(|e| Keyboard(e));
+    let tick = match state.stack.last() {
+        Some(Quiz(page)) if page.is_running() => time::every(Duration::from_secs(1))
+            .map(|_| RootMessage::Quiz(QuizMessage::Tick)),
+        Some(DictionaryQuiz(page)) if page.is_running() => time::every(Duration::from_secs(1))
+            .map(|_| RootMessage::DictionaryQuiz(DictionaryQuizMessage::Tick)),
+        Some(Matching(page)) if page.is_running() => time::every(Duration::from_secs(1))
+            .map(|_| RootMessage::Matching(MatchingMessage::Tick)),
+        Some(Writing(page)) if page.is_animating() => time::every(Duration::from_millis(30))
+            .map(|_| RootMessage::Writing(WritingMessage::Tick)),
+        _ => return keyboard,
+    };
+    Subscription::batch([keyboard, tick])
 }
 
 #[derive(Clone)]
 pub enum RootMessage {
     Selector(SelectorMessage),
     Quiz(QuizMessage),
+    ReverseQuiz(ReverseQuizMessage),
+    Discrimination(DiscriminationMessage),
+    Reading(ReadingMessage),
     Writing(WritingMessage),
     Dictionary(DictionaryMessage),
     DictionaryQuiz(DictionaryQuizMessage),
@@ -60,12 +124,20 @@ pub enum RootMessage {
     Repetitions(RepetitionsMessage),
     Repetition(RepetitionMessage),
     Word(WordMessage),
+    KanjiDictionary(KanjiDictionaryMessage),
+    Matching(MatchingMessage),
+    Exam(ExamMessage),
+    GroupFields(GroupFieldsMessage),
+    ReadingReview(ReadingReviewMessage),
     Keyboard(Event),
 }
 
 pub enum Page {
     Selector(SelectorState),
     Quiz(QuizState),
+    ReverseQuiz(ReverseQuizState),
+    Discrimination(DiscriminationState),
+    Reading(ReadingState),
     Writing(WritingState),
     Dictionary(DictionaryState),
     DictionaryQuiz(DictionaryQuizState),
@@ -73,6 +145,11 @@ pub enum Page {
     Repetitions(RepetitionsState),
     Repetition(RepetitionState),
     Word(WordState),
+    KanjiDictionary(KanjiDictionaryState),
+    Matching(MatchingState),
+    Exam(ExamState),
+    GroupFields(GroupFieldsState),
+    ReadingReview(ReadingReviewState),
     PreviousPage,
 }
 
@@ -84,6 +161,16 @@ pub struct AppState {
     pub dictionary: Vec<WordData>,
     pub card_sets: Vec<CardSetSettings>,
     pub word_groups: Vec<WordGroup>,
+    pub kana_stats: HashMap<String, KanaStatistics>,
+    pub kana_confusions: Confusions,
+    pub word_confusions: WordConfusions,
+    pub kanji: HashMap<char, KanjiData>,
+    /// Last chosen romaji → kana mode, shared by the pages with Japanese input.
+    pub kana_input: KanaInputMode,
+    /// Language of the JMdict meanings filled into words.
+    pub gloss_language: GlossLanguage,
+    /// What counts as a right typed answer in quizzes and exams.
+    pub answer_rules: AnswerRules,
     pub connection: Connection,
 }
 
@@ -96,12 +183,25 @@ impl Default for ScreenState {
         let list = load_words(&connection);
         let sets = load_sets(&connection);
         let groups = load_word_groups(&connection);
+        let kana_stats = load_kana_stats(&connection);
+        let kana_confusions = load_kana_confusions(&connection);
+        let kanji = load_kanji(&connection);
+        let word_confusions = load_word_confusions(&connection);
+        let answer_rules: AnswerRules = load_setting(ANSWER_RULES, &connection);
+        let gloss_code: String = load_setting(GLOSS_LANGUAGE, &connection);
 
         let state = Arc::new(Mutex::new(AppState {
             dictionary: list,
             card_sets: sets,
             connection,
             word_groups: groups,
+            kana_stats,
+            kana_confusions,
+            kanji,
+            word_confusions,
+            kana_input: KanaInputMode::Off,
+            gloss_language: GlossLanguage::from_code(&gloss_code),
+            answer_rules,
         }));
         ScreenState {
             stack: vec![Selector(SelectorState::new(state.clone()))],
@@ -124,13 +224,21 @@ impl ScreenState {
             self.stack,
             Selector,
             Quiz,
+            ReverseQuiz,
+            Discrimination,
+            Reading,
             Writing,
             Dictionary,
             DictionaryQuiz,
             Randomizer,
             Repetitions,
             Repetition,
-            Word
+            Word,
+            KanjiDictionary,
+            Matching,
+            Exam,
+            GroupFields,
+            ReadingReview
         );
         Task::none()
     }
@@ -139,13 +247,21 @@ impl ScreenState {
             self.stack,
             Quiz,
             Selector,
+            ReverseQuiz,
+            Discrimination,
+            Reading,
             Writing,
             Dictionary,
             DictionaryQuiz,
             Randomizer,
             Repetitions,
             Repetition,
-            Word
+            Word,
+            KanjiDictionary,
+            Matching,
+            Exam,
+            GroupFields,
+            ReadingReview
         )
     }
 
@@ -0,0 +1,364 @@
+use crate::choices::record_word_confusion;
+use crate::lang::{CardSet, WordData};
+use crate::repetitions::{CardKind, CardSetSettings};
+use crate::Page::PreviousPage;
+use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
+use iced::widget::{button, column, container, pick_list, row, scrollable, text, Column, Row};
+use iced::{alignment, Element, Fill, Length, Task};
+use rand::prelude::SliceRandom;
+use rand::rng;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SIZES: [usize; 5] = [4, 6, 8, 10, 12];
+const TILES_PER_ROW: usize = 4;
+
+/// Warm-up: pairs of keys and values laid out shuffled, to be matched
+/// against the clock.
+pub struct MatchingState {
+    words: Vec<WordData>,
+    pairs: Vec<WordData>,
+    size: usize,
+    layout: MatchingLayout,
+    keys: Vec<usize>,
+    values: Vec<usize>,
+    tiles: Vec<Tile>,
+    selected: Option<Tile>,
+    /// The last mismatch, shown red until the next pick.
+    wrong: Option<(Tile, Tile)>,
+    matched_keys: HashSet<usize>,
+    matched_values: HashSet<usize>,
+    /// Mismatches per pair.
+    errors: HashMap<usize, u32>,
+    mistakes: u32,
+    started: Instant,
+    finished_in: Option<Duration>,
+    /// The set the words come from; words matched wrong lose score in it.
+    card_set: Option<CardSetSettings>,
+    status: String,
+    state: Arc<Mutex<AppState>>,
+}
+
+/// One side of a pair on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pair: usize,
+    key: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchingLayout {
+    Columns,
+    Tiles,
+}
+
+impl MatchingLayout {
+    const ALL: [MatchingLayout; 2] = [MatchingLayout::Columns, MatchingLayout::Tiles];
+}
+
+impl Display for MatchingLayout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchingLayout::Columns => write!(f, "Две колонки"),
+            MatchingLayout::Tiles => write!(f, "Плитки"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MatchingMessage {
+    Back,
+    Pick(Tile),
+    SetSize(usize),
+    SetLayout(MatchingLayout),
+    Restart,
+    Tick,
+}
+
+impl NavigatedPage<MatchingMessage> for MatchingState {
+    fn navigate(&self, message: &MatchingMessage) -> Option<Page> {
+        match message {
+            MatchingMessage::Back => Some(PreviousPage),
+            _ => None,
+        }
+    }
+}
+
+impl MatchingState {
+    pub fn new(
+        words: Vec<WordData>,
+        card_set: Option<CardSetSettings>,
+        state: Arc<Mutex<AppState>>,
+    ) -> Self {
+        let words = words
+            .into_iter()
+            .filter(|w| !w.key.is_empty() && !w.value.is_empty())
+            .collect();
+        let mut page = Self {
+            words,
+            pairs: vec![],
+            size: SIZES[1],
+            layout: MatchingLayout::Columns,
+            keys: vec![],
+            values: vec![],
+            tiles: vec![],
+            selected: None,
+            wrong: None,
+            matched_keys: HashSet::new(),
+            matched_values: HashSet::new(),
+            errors: HashMap::new(),
+            mistakes: 0,
+            started: Instant::now(),
+            finished_in: None,
+            card_set,
+            status: String::new(),
+            state,
+        };
+        page.deal();
+        page
+    }
+
+    pub fn update(&mut self, message: MatchingMessage) -> Task<RootMessage> {
+        match message {
+            MatchingMessage::Back => {}
+            MatchingMessage::Tick => {}
+            MatchingMessage::Pick(tile) => self.pick(tile),
+            MatchingMessage::SetSize(size) => {
+                self.size = size;
+                self.deal();
+            }
+            MatchingMessage::SetLayout(layout) => self.layout = layout,
+            MatchingMessage::Restart => self.deal(),
+        }
+        Task::none()
+    }
+
+    /// Whether the game is on and the clock needs ticking.
+    pub fn is_running(&self) -> bool {
+        self.finished_in.is_none() && self.pairs.len() > 1
+    }
+
+    /// A new game of random pairs from the words.
+    fn deal(&mut self) {
+        let mut generator = rng();
+        let mut words = self.words.clone();
+        words.shuffle(&mut generator);
+        words.truncate(self.size);
+        self.pairs = words;
+
+        self.keys = (0..self.pairs.len()).collect();
+        self.keys.shuffle(&mut generator);
+        self.values = (0..self.pairs.len()).collect();
+        self.values.shuffle(&mut generator);
+        self.tiles = (0..self.pairs.len())
+            .flat_map(|pair| [Tile { pair, key: true }, Tile { pair, key: false }])
+            .collect();
+        self.tiles.shuffle(&mut generator);
+
+        self.selected = None;
+        self.wrong = None;
+        self.matched_keys.clear();
+        self.matched_values.clear();
+        self.errors.clear();
+        self.mistakes = 0;
+        self.status.clear();
+        self.finished_in = None;
+        self.started = Instant::now();
+    }
+
+    fn is_matched(&self, tile: Tile) -> bool {
+        if tile.key {
+            self.matched_keys.contains(&tile.pair)
+        } else {
+            self.matched_values.contains(&tile.pair)
+        }
+    }
+
+    fn pick(&mut self, tile: Tile) {
+        if self.finished_in.is_some() || self.is_matched(tile) {
+            return;
+        }
+        self.wrong = None;
+        let Some(selected) = self.selected.take() else {
+            self.selected = Some(tile);
+            return;
+        };
+        if selected.key == tile.key {
+            // another tile of the same side instead, or the same one to unselect
+            if selected != tile {
+                self.selected = Some(tile);
+            }
+            return;
+        }
+
+        let (key, value) = if tile.key { (tile, selected) } else { (selected, tile) };
+        let (key_word, value_word) = (&self.pairs[key.pair], &self.pairs[value.pair]);
+        // words sharing a key or a value are interchangeable
+        if key.pair == value.pair
+            || key_word.key == value_word.key
+            || key_word.value == value_word.value
+        {
+            self.matched_keys.insert(key.pair);
+            self.matched_values.insert(value.pair);
+            if self.matched_keys.len() == self.pairs.len() {
+                self.finish();
+            }
+        } else {
+            self.mistake(key.pair, value.pair);
+            self.wrong = Some((key, value));
+        }
+    }
+
+    fn mistake(&mut self, key: usize, value: usize) {
+        self.mistakes += 1;
+        *self.errors.entry(key).or_insert(0) += 1;
+        *self.errors.entry(value).or_insert(0) += 1;
+        let kind = self.card_set.as_ref().map(|s| s.kind).unwrap_or(CardKind::Words);
+        if kind == CardKind::Words {
+            let mut state = self.state.lock().unwrap();
+            record_word_confusion(&mut state, self.pairs[key].id, self.pairs[value].id);
+        }
+    }
+
+    fn finish(&mut self) {
+        self.finished_in = Some(self.started.elapsed());
+        let Some(settings) = &self.card_set else {
+            return;
+        };
+        let ids = self
+            .errors
+            .keys()
+            .map(|pair| self.pairs[*pair].id)
+            .filter(|id| *id != 0)
+            .collect::<Vec<u32>>();
+        if ids.is_empty() {
+            return;
+        }
+        let count = CardSet::mark_failed(settings, &ids, &self.state.lock().unwrap());
+        self.status = format!("Ошибки отмечены в наборе «{}»: {}", settings.name, count);
+    }
+
+    pub fn view(&self) -> Element<'_, MatchingMessage> {
+        let elapsed = self.finished_in.unwrap_or_else(|| self.started.elapsed());
+        let board: Element<'_, MatchingMessage> = if self.pairs.len() < 2 {
+            text!("Нужно хотя бы две пары с ключом и значением").into()
+        } else {
+            match self.layout {
+                MatchingLayout::Columns => row![
+                    self.side_column(&self.keys, true),
+                    self.side_column(&self.values, false),
+                ]
+                .spacing(DEFAULT_SPACING * 3.0)
+                .into(),
+                MatchingLayout::Tiles => self.tile_grid(),
+            }
+        };
+
+        container(
+            column![
+                row![
+                    button("Назад").on_press(MatchingMessage::Back),
+                    pick_list(SIZES, Some(self.size), MatchingMessage::SetSize),
+                    pick_list(
+                        MatchingLayout::ALL,
+                        Some(self.layout),
+                        MatchingMessage::SetLayout
+                    ),
+                    button("Заново").on_press(MatchingMessage::Restart),
+                ]
+                .spacing(DEFAULT_SPACING),
+                row![
+                    text!("{}:{:02}", elapsed.as_secs() / 60, elapsed.as_secs() % 60).size(25),
+                    text!("Пар {} / {}", self.matched_keys.len(), self.pairs.len()).size(25),
+                    text!("Ошибок {}", self.mistakes)
+                        .size(25)
+                        .color(iced::Color::from_rgb8(255, 79, 0)),
+                ]
+                .spacing(DEFAULT_SPACING * 2.0),
+                scrollable(board).height(Length::FillPortion(3)),
+                self.summary(elapsed),
+            ]
+            .spacing(DEFAULT_SPACING)
+            .align_x(alignment::Horizontal::Center),
+        )
+        .padding(10)
+        .center_x(Fill)
+        .into()
+    }
+
+    fn side_column(&self, order: &[usize], key: bool) -> Element<'_, MatchingMessage> {
+        let mut col = Column::new().spacing(5).width(300);
+        for pair in order {
+            col = col.push(self.tile_button(Tile { pair: *pair, key }));
+        }
+        col.into()
+    }
+
+    fn tile_grid(&self) -> Element<'_, MatchingMessage> {
+        let mut grid = Column::new().spacing(5).width(800);
+        for chunk in self.tiles.chunks(TILES_PER_ROW) {
+            let mut line = Row::new().spacing(5);
+            for tile in chunk {
+                line = line.push(self.tile_button(*tile));
+            }
+            grid = grid.push(line);
+        }
+        grid.into()
+    }
+
+    fn tile_button(&self, tile: Tile) -> Element<'_, MatchingMessage> {
+        let word = &self.pairs[tile.pair];
+        let label = if tile.key { &word.key } else { &word.value };
+        let matched = self.is_matched(tile);
+        let wrong = self.wrong.is_some_and(|(k, v)| k == tile || v == tile);
+        let style = if matched {
+            button::success
+        } else if wrong {
+            button::danger
+        } else if self.selected == Some(tile) {
+            button::primary
+        } else {
+            button::secondary
+        };
+        button(text!("{}", label).size(20).center().width(Fill))
+            .width(Fill)
+            .height(60)
+            .style(style)
+            .on_press_maybe((!matched).then_some(MatchingMessage::Pick(tile)))
+            .into()
+    }
+
+    /// Time and the pairs matched wrong, once the board is cleared.
+    fn summary(&self, elapsed: Duration) -> Element<'_, MatchingMessage> {
+        if self.finished_in.is_none() {
+            return column![].into();
+        }
+        let mut missed = self.errors.iter().collect::<Vec<(&usize, &u32)>>();
+        missed.sort_by(|a, b| b.1.cmp(a.1));
+        let lines = missed
+            .iter()
+            .map(|(pair, count)| {
+                let word = &self.pairs[**pair];
+                format!("{} — {} ×{}", word.key, word.value, count)
+            })
+            .collect::<Vec<String>>();
+
+        column![
+            text!(
+                "Готово за {:.1} с, ошибок {}",
+                elapsed.as_secs_f32(),
+                self.mistakes
+            )
+            .size(25),
+            text!("{}", lines.join("\n")),
+            text!("{}", self.status),
+            button("Ещё раз").on_press(MatchingMessage::Restart),
+        ]
+        .spacing(DEFAULT_SPACING)
+        .align_x(alignment::Horizontal::Center)
+        .height(Length::FillPortion(2))
+        .into()
+    }
+}
@@ -0,0 +1,78 @@
+use crate::dictionary::app_data_dir;
+use crate::lang::WordData;
+use iced::widget::image::Handle;
+use iced::widget::{image, space};
+use iced::{ContentFit, Element};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+/// Field of `additional` with the attached images: file names in the media
+/// dir separated by spaces. A file is named by the hash of its content, so
+/// the same picture attached twice is stored once.
+pub const IMAGES: &str = "images";
+const EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+pub fn media_dir() -> PathBuf {
+    app_data_dir().join("media")
+}
+
+pub fn image_names(word: &WordData) -> Vec<String> {
+    word.additional
+        .get(IMAGES)
+        .map(|names| names.split_whitespace().map(|n| n.to_string()).collect())
+        .unwrap_or_default()
+}
+
+pub fn set_image_names(word: &mut WordData, names: &[String]) {
+    word.additional.insert(IMAGES.to_string(), names.join(" "));
+}
+
+/// Copies the picture into the media dir and returns its file name there.
+pub fn import_image(path: &Path) -> Result<String, String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .filter(|e| EXTENSIONS.contains(&e.as_str()))
+        .ok_or_else(|| format!("Нужна картинка: {}", EXTENSIONS.join(", ")))?;
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let name = format!("{}.{}", hex::encode(Sha256::digest(&data)), extension);
+    let dir = media_dir();
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let target = dir.join(&name);
+    if !target.exists() {
+        fs::write(&target, data).map_err(|e| e.to_string())?;
+    }
+    Ok(name)
+}
+
+/// Handles of the pictures already looked up, `None` for a missing file.
+/// A name is the hash of the content, so what it points to never changes.
+static HANDLES: LazyLock<Mutex<HashMap<String, Option<Handle>>>> = LazyLock::new(Default::default);
+
+fn handle(name: &str) -> Option<Handle> {
+    HANDLES
+        .lock()
+        .unwrap()
+        .entry(name.to_string())
+        .or_insert_with(|| {
+            let path = media_dir().join(name);
+            path.exists().then(|| Handle::from_path(path))
+        })
+        .clone()
+}
+
+/// The picture fitted into a `size` square; nothing if the file is gone.
+pub fn thumbnail<'a, M: 'a>(name: &str, size: f32) -> Element<'a, M> {
+    let Some(handle) = handle(name) else {
+        return space().into();
+    };
+    image(handle)
+        .width(size)
+        .height(size)
+        .content_fit(ContentFit::Contain)
+        .into()
+}
@@ -0,0 +1,272 @@
+use crate::dictionary::app_data_dir;
+use crate::furigana::kana_reading;
+use crate::kana::{is_kana, morae, to_hiragana};
+use crate::lang::WordData;
+use iced::widget::canvas;
+use iced::widget::canvas::{Frame, Geometry, Path, Text};
+use iced::widget::text;
+use iced::{mouse, Element, Font, Pixels, Point, Rectangle, Renderer, Theme};
+use std::collections::HashMap;
+use std::fs;
+
+/// Field of `additional` with the pitch accent: the mora after which the
+/// pitch drops (0 for none) or a pattern such as `LHHL`. Variants are
+/// separated by commas, the first one is drawn.
+pub const ACCENT: &str = "accent";
+/// Tab separated `word, reading, accent` lines in the app data dir, as in
+/// the usual accent dictionaries.
+pub const ACCENT_FILE: &str = "accents.txt";
+
+/// Word → its readings with their accents.
+pub type AccentDictionary = HashMap<String, Vec<(String, String)>>;
+
+/// The kana the accent is counted on: the reading, or the key written in kana.
+pub fn accent_reading(word: &WordData) -> String {
+    match word.additional.get("reading") {
+        Some(reading) if !reading.trim().is_empty() => kana_reading(reading.trim()),
+        _ if is_kana(&word.key) => word.key.clone(),
+        _ => String::new(),
+    }
+}
+
+/// High or low for every mora and the particle after the word.
+pub fn pitch_pattern(accent: &str, morae: usize) -> Result<Vec<bool>, String> {
+    let accent = accent.trim();
+    if let Ok(drop) = accent.parse::<usize>() {
+        if drop > morae {
+            return Err(format!(
+                "Понижение после {}-й моры, а в слове их {}",
+                drop, morae
+            ));
+        }
+        return Ok((0..=morae)
+            .map(|i| match drop {
+                0 => i > 0,
+                1 => i == 0,
+                _ => i > 0 && i < drop,
+            })
+            .collect());
+    }
+
+    let mut pattern = vec![];
+    for c in accent.chars() {
+        match c {
+            'H' | 'h' => pattern.push(true),
+            'L' | 'l' => pattern.push(false),
+            _ => return Err(format!("Ожидается номер моры или узор из H и L, а не «{}»", accent)),
+        }
+    }
+    if pattern.len() == morae {
+        // without the particle: it stays where the word ends
+        let last = pattern.last().copied().unwrap_or(false);
+        pattern.push(last);
+    }
+    if pattern.len() != morae + 1 {
+        return Err(format!("В узоре {} знаков, а в слове {} мор", pattern.len(), morae));
+    }
+    Ok(pattern)
+}
+
+/// Checks every variant of the accent against the reading.
+pub fn validate_accent(accent: &str, reading: &str) -> Result<(), String> {
+    if accent.trim().is_empty() {
+        return Ok(());
+    }
+    let count = morae(reading).len();
+    if count == 0 {
+        return Err("Для ударения нужно чтение каной".to_string());
+    }
+    for variant in accent.split(',') {
+        pitch_pattern(variant, count)?;
+    }
+    Ok(())
+}
+
+/// The pitch line of the word, if it has a valid accent and a reading.
+pub fn pitch_line(word: &WordData) -> Option<PitchLine> {
+    let accent = word.additional.get(ACCENT)?;
+    let morae = morae(&accent_reading(word));
+    let pattern = pitch_pattern(accent.split(',').next()?, morae.len()).ok()?;
+    Some(PitchLine { morae, pattern })
+}
+
+/// The reading with dots over each mora, high or low, joined by a line.
+/// The hollow dot at the end is the particle.
+#[derive(Clone, Debug)]
+pub struct PitchLine {
+    morae: Vec<String>,
+    pattern: Vec<bool>,
+}
+
+impl PitchLine {
+    pub fn view<'a, M: 'a>(self, size: f32) -> Element<'a, M> {
+        let width = size * (self.morae.len() + 1) as f32;
+        canvas(self).width(width).height(size * 2.2).into()
+    }
+}
+
+impl<M> canvas::Program<M> for PitchLine {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.extended_palette();
+        let cell = bounds.width / self.pattern.len() as f32;
+        let size = cell.min(bounds.height / 2.2);
+        let high = size * 0.25;
+        let low = size * 0.85;
+        let points = self
+            .pattern
+            .iter()
+            .enumerate()
+            .map(|(i, h)| Point::new(cell * (i as f32 + 0.5), if *h { high } else { low }))
+            .collect::<Vec<Point>>();
+
+        let line = Path::new(|b| {
+            for (i, p) in points.iter().enumerate() {
+                if i == 0 {
+                    b.move_to(*p);
+                } else {
+                    b.line_to(*p);
+                }
+            }
+        });
+        let color = palette.primary.base.color;
+        frame.stroke(&line, canvas::Stroke::default().with_color(color).with_width(2.0));
+
+        for (i, p) in points.iter().enumerate() {
+            let dot = Path::circle(*p, size * 0.1);
+            if i < self.morae.len() {
+                frame.fill(&dot, color);
+            } else {
+                frame.fill(&dot, palette.background.base.color);
+                frame.stroke(&dot, canvas::Stroke::default().with_color(color).with_width(2.0));
+            }
+        }
+
+        for (i, mora) in self.morae.iter().enumerate() {
+            frame.fill_text(Text {
+                content: mora.clone(),
+                position: Point::new(cell * (i as f32 + 0.5), size * 1.2),
+                color: palette.background.base.text,
+                size: Pixels(size * 0.8),
+                align_x: text::Alignment::Center,
+                font: Font::with_name("Noto Sans JP"),
+                shaping: text::Shaping::Advanced,
+                ..Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+pub fn read_accent_file() -> Result<AccentDictionary, String> {
+    let text = fs::read_to_string(app_data_dir().join(ACCENT_FILE))
+        .map_err(|e| format!("{}: {}", ACCENT_FILE, e))?;
+    Ok(parse_accents(&text))
+}
+
+fn parse_accents(text: &str) -> AccentDictionary {
+    let mut dictionary = AccentDictionary::new();
+    for line in text.lines() {
+        let mut columns = line.split('\t');
+        let (Some(word), Some(reading), Some(accent)) =
+            (columns.next(), columns.next(), columns.next())
+        else {
+            continue;
+        };
+        let word = word.trim();
+        let accent = accent.trim();
+        if word.is_empty() || accent.is_empty() {
+            continue;
+        }
+        // kana words come without a reading
+        let reading = if reading.trim().is_empty() { word } else { reading.trim() };
+        dictionary
+            .entry(word.to_string())
+            .or_default()
+            .push((to_hiragana(reading), accent.to_string()));
+    }
+    dictionary
+}
+
+/// Reading and accent of the word. The entry has to have the same reading;
+/// for a word without one, it has to be the only entry.
+pub fn find_accent(word: &WordData, dictionary: &AccentDictionary) -> Option<(String, String)> {
+    let entries = dictionary.get(word.key.trim())?;
+    let reading = to_hiragana(&accent_reading(word));
+    if reading.is_empty() {
+        return match entries.as_slice() {
+            [entry] => Some(entry.clone()),
+            _ => None,
+        };
+    }
+    entries.iter().find(|(r, _)| *r == reading).cloned()
+}
+
+/// Sets an accent found for the word, and the reading when the word has none.
+/// False when the word changed since and the accent no longer fits it: it got
+/// an accent of its own or another reading.
+pub fn set_accent(word: &mut WordData, reading: &str, accent: &str) -> bool {
+    if word.additional.get(ACCENT).is_some_and(|a| !a.is_empty()) {
+        return false;
+    }
+    let current = to_hiragana(&accent_reading(word));
+    if current.is_empty() {
+        word.additional.insert("reading".to_string(), reading.to_string());
+    } else if current != reading {
+        return false;
+    }
+    word.additional.insert(ACCENT.to_string(), accent.to_string());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const H: bool = true;
+    const L: bool = false;
+
+    #[test]
+    fn small_kana_join_the_mora_before() {
+        assert_eq!(morae("きょう"), ["きょ", "う"]);
+        assert_eq!(morae("がっこう").len(), 4);
+        assert_eq!(morae("しんぶん").len(), 4);
+        assert_eq!(morae("チョコレート"), ["チョ", "コ", "レ", "ー", "ト"]);
+    }
+
+    #[test]
+    fn accent_numbers_make_the_four_patterns() {
+        // heiban, atamadaka, nakadaka and odaka, the last value is the particle
+        assert_eq!(pitch_pattern("0", 3), Ok(vec![L, H, H, H]));
+        assert_eq!(pitch_pattern("1", 3), Ok(vec![H, L, L, L]));
+        assert_eq!(pitch_pattern("2", 3), Ok(vec![L, H, L, L]));
+        assert_eq!(pitch_pattern("3", 3), Ok(vec![L, H, H, L]));
+        assert!(pitch_pattern("4", 3).is_err());
+    }
+
+    #[test]
+    fn patterns_may_leave_out_the_particle() {
+        assert_eq!(pitch_pattern("LHH", 3), Ok(vec![L, H, H, H]));
+        assert_eq!(pitch_pattern("lhhl", 3), Ok(vec![L, H, H, L]));
+        assert!(pitch_pattern("LH", 3).is_err());
+        assert!(pitch_pattern("LHX", 3).is_err());
+    }
+
+    #[test]
+    fn every_variant_is_checked_against_the_reading() {
+        assert_eq!(validate_accent("0, 2", "はし"), Ok(()));
+        assert_eq!(validate_accent("", ""), Ok(()));
+        assert!(validate_accent("0, 3", "はし").is_err());
+        assert!(validate_accent("1", "").is_err());
+    }
+}
@@ -1,19 +1,34 @@
-use crate::lang::KanaSet;
+use crate::data_provider::kana_stats::{save_kana_confusion, save_kana_stat};
+use crate::lang::{KanaSet, KanaStatistics};
+use crate::romanization::{Romanization, RomanizationMode, RomanizationSystem};
+use crate::speed_run::{finish_run, summary_view, RunLimit, RunRecord, SpeedRun};
 use crate::Page::PreviousPage;
-use crate::{NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
+use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
 use iced::widget::*;
 use iced::{alignment, Element, Fill, Task};
+use rand::distr::weighted::WeightedIndex;
+use rand::distr::Distribution;
 use rand::seq::SliceRandom;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct QuizState {
     kana: String,
     current_roman: String,
-    correct_roman: String,
+    correct_roman: Romanization,
+    mode: RomanizationMode,
+    last_systems: Vec<RomanizationSystem>,
     pub(crate) set: KanaSet,
     queue: Vec<(String, String)>,
     score: Score,
     is_help: bool,
+    drill: bool,
+    shown_at: Instant,
+    limit: RunLimit,
+    speed_run: Option<SpeedRun>,
+    result: Option<(RunRecord, Vec<RunRecord>)>,
+    state: Arc<Mutex<AppState>>,
 }
 
 impl NavigatedPage<QuizMessage> for QuizState {
@@ -27,27 +42,45 @@ impl NavigatedPage<QuizMessage> for QuizState {
 }
 
 impl QuizState {
-    pub(crate) fn new() -> QuizState {
-        QuizState {
-            kana: "ぁ".to_string(),
-            correct_roman: "a".to_string(),
+    pub(crate) fn new(
+        set: KanaSet,
+        mode: RomanizationMode,
+        drill: bool,
+        limit: RunLimit,
+        state: Arc<Mutex<AppState>>,
+    ) -> QuizState {
+        let mut quiz = QuizState {
+            kana: String::new(),
+            correct_roman: Romanization::new("", ""),
+            mode,
+            last_systems: vec![],
             is_help: false,
             current_roman: "".to_string(),
-            set: KanaSet::hiragana(),
+            set,
             queue: Vec::new(),
             score: Score {
                 total: 0,
                 correct: 0,
                 fail: 0,
             },
-        }
+            drill,
+            shown_at: Instant::now(),
+            limit,
+            speed_run: SpeedRun::new(limit),
+            result: None,
+            state,
+        };
+        quiz.update_showed();
+        quiz
     }
 }
 
-impl Default for QuizState {
-    fn default() -> Self {
-        QuizState::new()
-    }
+/// Another kana the typed romaji points to.
+enum Guess {
+    Kana(String),
+    /// The romaji can still become one.
+    Unfinished,
+    Unknown,
 }
 
 impl QuizState {
@@ -60,52 +93,173 @@ impl QuizState {
 
                     return Task::none();
                 }
-                self.current_roman = content;
-                if self.correct_roman == self.current_roman {
+                self.current_roman = content.to_lowercase();
+                let systems = self.correct_roman.systems_for(&self.current_roman, self.mode);
+                if !systems.is_empty() {
                     if self.is_help == false {
                         self.score.correct += 1;
                     }
 
+                    self.record_answer(true);
                     self.is_help = false;
                     self.score.total += 1;
-                    self.update_showed()
-                } else if self.current_roman.len() == self.correct_roman.len() {
+                    self.last_systems = systems;
+                    self.update_showed();
+                    self.check_run();
+                } else if !self.correct_roman.is_prefix(&self.current_roman, self.mode) {
+                    let guess = match self.guessed_kana() {
+                        // wait for the whole romaji of the kana being typed
+                        Guess::Unfinished => return Task::none(),
+                        Guess::Kana(guess) => Some(guess),
+                        Guess::Unknown => None,
+                    };
+                    self.record_answer(false);
+                    if let Some(guess) = guess {
+                        record_kana_confusion(&mut self.state.lock().unwrap(), &self.kana, &guess);
+                    }
+                    self.is_help = false;
                     self.score.total += 1;
                     self.score.fail += 1;
-                    self.update_showed()
+                    self.last_systems.clear();
+                    self.update_showed();
+                    self.check_run();
                 }
             }
+            QuizMessage::Tick => self.check_run(),
+            QuizMessage::Restart => {
+                self.score = Default::default();
+                self.speed_run = SpeedRun::new(self.limit);
+                self.result = None;
+                self.is_help = false;
+                self.last_systems.clear();
+                self.update_showed();
+            }
             QuizMessage::Back => todo!(),
         }
         Task::none()
     }
 
+    fn record_answer(&mut self, correct: bool) {
+        if let Some(run) = &mut self.speed_run {
+            run.answer(correct, self.shown_at.elapsed());
+        }
+        let mut state = self.state.lock().unwrap();
+        record_kana_answer(
+            &mut state,
+            &self.kana,
+            correct,
+            self.is_help,
+            self.shown_at.elapsed(),
+        );
+    }
+
+    /// Whether a timed run is going on and needs the clock ticking.
+    pub fn is_running(&self) -> bool {
+        self.speed_run.is_some() && self.result.is_none()
+    }
+
+    fn check_run(&mut self) {
+        let Some(run) = &self.speed_run else {
+            return;
+        };
+        if self.result.is_none() && run.is_finished() {
+            self.result = Some(finish_run(run, "kana", &self.state));
+        }
+    }
+
+    /// The kana the user took the shown one for, judging by the wrong romaji:
+    /// another kana of the set spelled in full, or the start of one.
+    fn guessed_kana(&self) -> Guess {
+        let list = self.set.list();
+        let mut unfinished = false;
+        for (kana, roman) in list.iter().filter(|(kana, _)| *kana != self.kana) {
+            let romanization = Romanization::new(kana, roman);
+            if !romanization.systems_for(&self.current_roman, self.mode).is_empty() {
+                return Guess::Kana(kana.clone());
+            }
+            unfinished |= romanization.is_prefix(&self.current_roman, self.mode);
+        }
+        if unfinished {
+            Guess::Unfinished
+        } else {
+            Guess::Unknown
+        }
+    }
+
     fn update_showed(&mut self) {
         self.current_roman = String::new();
-        let queue = &mut self.queue;
 
-        if queue.is_empty() {
-            for pair in self.set.list() {
-                queue.push(pair);
+        let pair = if self.drill {
+            self.next_weak()
+        } else {
+            let queue = &mut self.queue;
+
+            if queue.is_empty() {
+                for pair in self.set.list() {
+                    queue.push(pair);
+                }
+
+                queue.shuffle(&mut rand::rng());
             }
 
-            queue.shuffle(&mut rand::rng());
-        }
+            self.queue.pop().unwrap()
+        };
 
-        let pair = self.queue.pop().unwrap();
+        self.correct_roman = Romanization::new(&pair.0, &pair.1);
         self.kana = pair.0;
-        self.correct_roman = pair.1;
+        self.shown_at = Instant::now();
+        if let Some(run) = &mut self.speed_run {
+            run.start();
+        }
+    }
+
+    /// Picks kana in proportion to how often they were missed before.
+    fn next_weak(&self) -> (String, String) {
+        let list = self.set.list();
+        let weights = weakness_weights(&self.state.lock().unwrap(), &list);
+        let index = WeightedIndex::new(weights).unwrap();
+        let mut generator = rand::rng();
+
+        loop {
+            let pair = &list[index.sample(&mut generator)];
+            if list.len() == 1 || pair.0 != self.kana {
+                return pair.clone();
+            }
+        }
     }
 
     pub fn view(&self) -> Element<'_, QuizMessage> {
+        if let Some((record, history)) = &self.result {
+            return container(
+                iced::widget::column![
+                    summary_view(record, history),
+                    row![
+                        button("Закончить").on_press(QuizMessage::Back),
+                        button("Ещё раз").on_press(QuizMessage::Restart),
+                    ]
+                    .spacing(DEFAULT_SPACING),
+                ]
+                .spacing(DEFAULT_SPACING)
+                .align_x(alignment::Horizontal::Center),
+            )
+            .center_y(Fill)
+            .center_x(Fill)
+            .into();
+        }
+
         container(
             iced::widget::column![
+                text!(
+                    "{}",
+                    self.speed_run.as_ref().map(|r| r.progress()).unwrap_or_default()
+                )
+                .size(20),
                 row![
-                    text!("{}", self.kana.to_uppercase()).size(54),
+                    text!("{}", self.kana).size(54),
                     text!(
                         "{}",
                         if self.is_help {
-                            self.correct_roman.clone()
+                            self.correct_roman.accepted(self.mode).join(" / ")
                         } else {
                             String::new()
                         }
@@ -117,6 +271,7 @@ impl QuizState {
                     .size(28)
                     .width(150)
                     .on_input(QuizMessage::ContentChanged),
+                self.last_system(),
                 row![
                     text!("{}", self.score.total.to_string()).size(25),
                     text!("{}", self.score.correct.to_string())
@@ -136,10 +291,63 @@ impl QuizState {
         .center_x(Fill)
         .into()
     }
+
+    fn last_system(&self) -> Element<'_, QuizMessage> {
+        if self.last_systems.is_empty() {
+            return space().height(20).into();
+        }
+
+        let systems = self
+            .last_systems
+            .iter()
+            .map(|s| s.title())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        text!("Система: {}", systems).size(16).into()
+    }
+}
+pub(crate) fn record_kana_answer(
+    state: &mut AppState,
+    kana: &str,
+    correct: bool,
+    helped: bool,
+    time: Duration,
+) {
+    let stat = state
+        .kana_stats
+        .entry(kana.to_string())
+        .or_insert_with(|| KanaStatistics::new(kana));
+    stat.record(correct, helped, time);
+    save_kana_stat(stat, &state.connection);
 }
+
+/// Remembers that `kana` was answered as `confused_with`.
+pub(crate) fn record_kana_confusion(state: &mut AppState, kana: &str, confused_with: &str) {
+    if kana == confused_with {
+        return;
+    }
+    let count = state
+        .kana_confusions
+        .entry((kana.to_string(), confused_with.to_string()))
+        .or_insert(0);
+    *count += 1;
+    save_kana_confusion(kana, confused_with, *count, &state.connection);
+}
+
+pub(crate) fn weakness_weights(state: &AppState, list: &[(String, String)]) -> Vec<f32> {
+    list.iter()
+        .map(|(kana, _)| match state.kana_stats.get(kana) {
+            Some(stat) => stat.error_rate(),
+            None => KanaStatistics::new(kana).error_rate(),
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub enum QuizMessage {
     ContentChanged(String),
+    Tick,
+    Restart,
     Back,
 }
 
@@ -0,0 +1,248 @@
+use crate::kana::{is_kana, morae};
+use crate::lang::{KanaSet, KanaType};
+use crate::quiz::Score;
+use crate::romaji::{same_sound, to_kana, to_kana_final, to_romaji};
+use crate::Page::PreviousPage;
+use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
+use iced::widget::*;
+use iced::{alignment, Element, Fill, Task};
+use rand::seq::IndexedRandom;
+use rand::{random_range, RngExt};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MIN_MORAE: usize = 2;
+const MAX_MORAE: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadingSource {
+    /// Pseudo-words made of the enabled `KanaSet` rows.
+    Generated,
+    /// Keys of the dictionary written in kana only.
+    Dictionary,
+}
+
+impl ReadingSource {
+    const ALL: [ReadingSource; 2] = [ReadingSource::Generated, ReadingSource::Dictionary];
+}
+
+impl Display for ReadingSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadingSource::Generated => write!(f, "Случайные слоги"),
+            ReadingSource::Dictionary => write!(f, "Слова из словаря"),
+        }
+    }
+}
+
+/// Reading whole kana strings aloud in romaji, timed.
+pub struct ReadingState {
+    set: KanaSet,
+    source: ReadingSource,
+    dictionary_words: Vec<String>,
+    prompt: String,
+    answer: String,
+    feedback: Option<(bool, String)>,
+    score: Score,
+    read_chars: usize,
+    reading_time: Duration,
+    shown_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub enum ReadingMessage {
+    Back,
+    AnswerChanged(String),
+    SubmitAnswer,
+    Skip,
+    SetSource(ReadingSource),
+}
+
+impl NavigatedPage<ReadingMessage> for ReadingState {
+    fn navigate(&self, message: &ReadingMessage) -> Option<Page> {
+        if let ReadingMessage::Back = message {
+            Some(PreviousPage)
+        } else {
+            None
+        }
+    }
+}
+
+impl ReadingState {
+    pub(crate) fn new(set: KanaSet, state: Arc<Mutex<AppState>>) -> ReadingState {
+        let dictionary_words = state
+            .lock()
+            .unwrap()
+            .dictionary
+            .iter()
+            .map(|word| word.key.trim().to_string())
+            .filter(|key| is_kana(key))
+            .collect();
+
+        let mut drill = ReadingState {
+            set,
+            source: ReadingSource::Generated,
+            dictionary_words,
+            prompt: String::new(),
+            answer: String::new(),
+            feedback: None,
+            score: Default::default(),
+            read_chars: 0,
+            reading_time: Duration::ZERO,
+            shown_at: Instant::now(),
+        };
+        drill.show_next();
+        drill
+    }
+
+    pub fn update(&mut self, message: ReadingMessage) -> Task<RootMessage> {
+        match message {
+            ReadingMessage::Back => {}
+            ReadingMessage::AnswerChanged(content) => {
+                self.answer = content;
+                if same_sound(&to_kana(&self.answer), &self.prompt) {
+                    self.answered(true);
+                }
+            }
+            ReadingMessage::SubmitAnswer => {
+                if self.answer.is_empty() {
+                    return Task::none();
+                }
+                let correct = same_sound(&to_kana_final(&self.answer), &self.prompt);
+                self.answered(correct);
+            }
+            ReadingMessage::Skip => self.answered(false),
+            ReadingMessage::SetSource(source) => {
+                self.source = source;
+                self.show_next();
+            }
+        }
+        Task::none()
+    }
+
+    fn answered(&mut self, correct: bool) {
+        self.score.total += 1;
+        if correct {
+            self.score.correct += 1;
+            self.read_chars += self.prompt.chars().count();
+            self.reading_time += self.shown_at.elapsed();
+        } else {
+            self.score.fail += 1;
+        }
+        self.feedback = Some((correct, format!("{} — {}", self.prompt, to_romaji(&self.prompt))));
+        self.show_next();
+    }
+
+    fn show_next(&mut self) {
+        self.answer = String::new();
+        self.prompt = match self.source {
+            ReadingSource::Dictionary if !self.dictionary_words.is_empty() => self
+                .dictionary_words
+                .choose(&mut rand::rng())
+                .cloned()
+                .unwrap(),
+            _ => self.pseudo_word(),
+        };
+        self.shown_at = Instant::now();
+    }
+
+    fn pseudo_word(&self) -> String {
+        let syllables = self.set.syllables();
+        if syllables.is_empty() {
+            return String::new();
+        }
+
+        let mut generator = rand::rng();
+        let length = random_range(MIN_MORAE..=MAX_MORAE);
+        let mut word = String::new();
+        // きゃ is one mora in two chars, so the length is counted in morae
+        let mut count = 0;
+        while count < length {
+            let (kana, roman) = syllables.choose(&mut generator).unwrap();
+            let is_n = roman == "n";
+            // ん never starts a word and never comes twice in a row
+            if is_n && (word.is_empty() || word.ends_with(kana.as_str())) {
+                continue;
+            }
+            let can_double = roman.starts_with(['k', 's', 't', 'c', 'p', 'g', 'z', 'd', 'b', 'j']);
+            // っ is a mora of its own and has to leave room for the kana
+            if !word.is_empty() && can_double && count + 2 <= length && generator.random_bool(0.1) {
+                word.push(match self.set.chars_type {
+                    KanaType::Hiragana => 'っ',
+                    KanaType::Katakana => 'ッ',
+                });
+            }
+            word.push_str(kana);
+            count = morae(&word).len();
+        }
+        word
+    }
+
+    fn chars_per_minute(&self) -> f32 {
+        let minutes = self.reading_time.as_secs_f32() / 60.0;
+        if minutes == 0.0 {
+            return 0.0;
+        }
+        self.read_chars as f32 / minutes
+    }
+
+    fn accuracy(&self) -> f32 {
+        if self.score.total == 0 {
+            return 0.0;
+        }
+        self.score.correct as f32 / self.score.total as f32 * 100.0
+    }
+
+    pub fn view(&self) -> Element<'_, ReadingMessage> {
+        container(
+            iced::widget::column![
+                pick_list(ReadingSource::ALL, Some(self.source), ReadingMessage::SetSource),
+                text!("{}", self.prompt).size(54),
+                text_input("Романдзи", &self.answer)
+                    .size(28)
+                    .width(300)
+                    .on_input(ReadingMessage::AnswerChanged)
+                    .on_submit(ReadingMessage::SubmitAnswer),
+                self.feedback(),
+                row![
+                    text!("{}", self.score.total.to_string()).size(25),
+                    text!("{}", self.score.correct.to_string())
+                        .size(25)
+                        .color(iced::Color::from_rgb8(60, 170, 60)),
+                    text!("{}", self.score.fail.to_string())
+                        .color(iced::Color::from_rgb8(255, 79, 0))
+                        .size(25),
+                ]
+                .spacing(DEFAULT_SPACING),
+                text!(
+                    "{} знаков в минуту, точность {}%",
+                    self.chars_per_minute().round(),
+                    self.accuracy().round()
+                ),
+                row![
+                    button("Закончить").on_press(ReadingMessage::Back),
+                    button("Пропустить").on_press(ReadingMessage::Skip),
+                ]
+                .spacing(DEFAULT_SPACING),
+            ]
+            .spacing(DEFAULT_SPACING)
+            .align_x(alignment::Horizontal::Center),
+        )
+        .center_y(Fill)
+        .center_x(Fill)
+        .into()
+    }
+
+    fn feedback(&self) -> Element<'_, ReadingMessage> {
+        match &self.feedback {
+            None => space().height(20).into(),
+            Some((true, message)) => text!("{}", message)
+                .color(iced::Color::from_rgb8(60, 170, 60))
+                .into(),
+            Some((false, message)) => text!("{}", message)
+                .color(iced::Color::from_rgb8(255, 79, 0))
+                .into(),
+        }
+    }
+}
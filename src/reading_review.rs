@@ -0,0 +1,240 @@
+use crate::data_provider::jmdict::find_jmdict;
+use crate::data_provider::words::update_words;
+use crate::dictionary::app_data_dir;
+use crate::kanji::is_kanji;
+use crate::lang::WordData;
+use crate::Page::PreviousPage;
+use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
+use iced::widget::{
+    button, checkbox, column, container, pick_list, row, rule, scrollable, text, Column,
+};
+use iced::{Element, Fill, Task};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+/// `word<TAB>reading` lines in the app data dir; several readings are
+/// separated by commas. It is asked before JMdict.
+pub const READINGS_FILE: &str = "readings.txt";
+
+/// Readings for the words of a group that have kanji in the key and no
+/// reading, to be accepted or rejected one by one.
+pub struct ReadingReviewState {
+    group_name: String,
+    proposals: Vec<Proposal>,
+    status: String,
+    state: Arc<Mutex<AppState>>,
+}
+
+struct Proposal {
+    word_id: u32,
+    key: String,
+    value: String,
+    /// Every reading found, the chosen one first.
+    candidates: Vec<String>,
+    reading: String,
+    source: &'static str,
+    accepted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum ReadingReviewMessage {
+    Back,
+    Choose(usize, String),
+    Accept(usize, bool),
+    AcceptAll(bool),
+    Save,
+}
+
+impl NavigatedPage<ReadingReviewMessage> for ReadingReviewState {
+    fn navigate(&self, message: &ReadingReviewMessage) -> Option<Page> {
+        match message {
+            ReadingReviewMessage::Back => Some(PreviousPage),
+            _ => None,
+        }
+    }
+}
+
+impl ReadingReviewState {
+    pub fn new(group_index: usize, state: Arc<Mutex<AppState>>) -> Self {
+        let (reading_list, status) = match fs::read_to_string(app_data_dir().join(READINGS_FILE)) {
+            Ok(text) => (parse_reading_list(&text), String::new()),
+            Err(_) => (HashMap::new(), format!("Без {}: только JMdict", READINGS_FILE)),
+        };
+
+        let (group_name, proposals) = {
+            let state = state.lock().unwrap();
+            let group = &state.word_groups[group_index];
+            let proposals = state
+                .dictionary
+                .iter()
+                .filter(|w| w.group_id == group.id && w.id != 0 && lacks_reading(w))
+                .filter_map(|w| propose(w, &reading_list, &state))
+                .collect::<Vec<Proposal>>();
+            (group.name.clone(), proposals)
+        };
+
+        Self {
+            group_name,
+            proposals,
+            status,
+            state,
+        }
+    }
+
+    pub fn update(&mut self, message: ReadingReviewMessage) -> Task<RootMessage> {
+        match message {
+            ReadingReviewMessage::Back => {}
+            ReadingReviewMessage::Choose(i, reading) => {
+                self.proposals[i].reading = reading;
+                self.proposals[i].accepted = true;
+            }
+            ReadingReviewMessage::Accept(i, accepted) => self.proposals[i].accepted = accepted,
+            ReadingReviewMessage::AcceptAll(accepted) => {
+                self.proposals.iter_mut().for_each(|p| p.accepted = accepted)
+            }
+            ReadingReviewMessage::Save => {
+                let state = &mut *self.state.lock().unwrap();
+                let mut changed = vec![];
+                for proposal in self.proposals.iter().filter(|p| p.accepted) {
+                    if let Some(word) = state.dictionary.iter().find(|w| w.id == proposal.word_id) {
+                        let mut word = word.clone();
+                        word.additional.insert("reading".to_string(), proposal.reading.clone());
+                        changed.push(word);
+                    }
+                }
+                // the list and the proposals change only once the database has the readings
+                if let Err(e) = update_words(&mut changed, &state.connection) {
+                    self.status = format!("Не сохранено: {}", e);
+                    return Task::none();
+                }
+                for word in &changed {
+                    if let Some(old) = state.dictionary.iter_mut().find(|w| w.id == word.id) {
+                        *old = word.clone();
+                    }
+                }
+                self.proposals.retain(|p| !p.accepted);
+                self.status = format!("Сохранено чтений: {}", changed.len());
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, ReadingReviewMessage> {
+        let mut list = Column::new().spacing(5);
+        for (i, proposal) in self.proposals.iter().enumerate() {
+            let reading: Element<'_, ReadingReviewMessage> = if proposal.candidates.len() > 1 {
+                pick_list(
+                    proposal.candidates.clone(),
+                    Some(proposal.reading.clone()),
+                    move |reading| ReadingReviewMessage::Choose(i, reading),
+                )
+                .width(200)
+                .into()
+            } else {
+                text!("{}", proposal.reading).width(200).into()
+            };
+            list = list.push(
+                row![
+                    checkbox(proposal.accepted)
+                        .on_toggle(move |b| ReadingReviewMessage::Accept(i, b)),
+                    text!("{}", proposal.key).size(20).width(150),
+                    reading,
+                    text!("{}", proposal.source).width(80),
+                    text!("{}", proposal.value).width(Fill),
+                ]
+                .spacing(DEFAULT_SPACING)
+                .align_y(iced::Center),
+            );
+        }
+
+        let accepted = self.proposals.iter().filter(|p| p.accepted).count();
+        container(
+            column![
+                button("Назад").on_press(ReadingReviewMessage::Back),
+                text!("Чтения для слов группы «{}»", self.group_name).size(24),
+                text!(
+                    "Найдено: {}, принято: {}. Несколько вариантов — выберите нужный",
+                    self.proposals.len(),
+                    accepted
+                ),
+                row![
+                    button("Принять все").on_press(ReadingReviewMessage::AcceptAll(true)),
+                    button("Отклонить все").on_press(ReadingReviewMessage::AcceptAll(false)),
+                ]
+                .spacing(DEFAULT_SPACING),
+                rule::horizontal(2),
+                scrollable(list).height(Fill),
+                row![
+                    button("Сохранить принятые")
+                        .on_press_maybe((accepted > 0).then_some(ReadingReviewMessage::Save)),
+                    text!("{}", self.status),
+                ]
+                .spacing(DEFAULT_SPACING)
+                .align_y(iced::Center),
+            ]
+            .spacing(DEFAULT_SPACING),
+        )
+        .padding(DEFAULT_SPACING)
+        .into()
+    }
+}
+
+fn lacks_reading(word: &WordData) -> bool {
+    word.key.chars().any(is_kanji)
+        && word
+            .additional
+            .get("reading")
+            .is_none_or(|r| r.trim().is_empty())
+}
+
+/// Readings from the list, else from JMdict. Accepted right away only when
+/// there is a single one.
+fn propose(
+    word: &WordData,
+    reading_list: &HashMap<String, Vec<String>>,
+    state: &AppState,
+) -> Option<Proposal> {
+    let key = word.key.trim();
+    let (candidates, source) = match reading_list.get(key) {
+        Some(readings) => (readings.clone(), READINGS_FILE),
+        None => {
+            let mut readings: Vec<String> = vec![];
+            for entry in find_jmdict(key, &state.connection) {
+                // the key has kanji, so it matched a written form
+                for reading in entry.readings_for(key) {
+                    if !readings.contains(reading) {
+                        readings.push(reading.clone());
+                    }
+                }
+            }
+            (readings, "JMdict")
+        }
+    };
+    Some(Proposal {
+        word_id: word.id,
+        key: key.to_string(),
+        value: word.value.clone(),
+        reading: candidates.first()?.clone(),
+        accepted: candidates.len() == 1,
+        candidates,
+        source,
+    })
+}
+
+fn parse_reading_list(text: &str) -> HashMap<String, Vec<String>> {
+    let mut list: HashMap<String, Vec<String>> = HashMap::new();
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let Some((word, readings)) = line.split_once('\t') else {
+            continue;
+        };
+        let entry = list.entry(word.trim().to_string()).or_default();
+        for reading in readings.split(',').map(|r| r.trim()).filter(|r| !r.is_empty()) {
+            if !entry.iter().any(|r| r == reading) {
+                entry.push(reading.to_string());
+            }
+        }
+    }
+    list.retain(|word, readings| !word.is_empty() && !readings.is_empty());
+    list
+}
@@ -1,11 +1,16 @@
+use crate::choices::{record_word_confusion, Choice, WordConfusions};
+use crate::cloze::{random_example, Example};
 use crate::data_provider::voice::get_voice;
+use crate::furigana::{furigana, kana_reading, ruby_view};
 use crate::lang::{CardSet, CardStatistics, WordData, WordOpenMode};
-use crate::repetitions::CardSetSettings;
+use crate::media::{image_names, thumbnail};
+use crate::pitch::pitch_line;
+use crate::repetitions::{CardKind, CardSetSettings};
 use crate::Page::PreviousPage;
 use crate::{AppState, KeyPressedPage, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
 use iced::alignment::Horizontal::Center;
 use iced::keyboard::key::Physical::Code;
-use iced::widget::{button, column, container, row, rule, space, text, Column};
+use iced::widget::{button, column, container, row, rule, space, text, toggler, Column};
 use iced::{alignment, keyboard, Element, Fill, Left, Task};
 use rodio::MixerDeviceSink;
 use std::collections::HashSet;
@@ -21,6 +26,12 @@ pub struct RepetitionState {
     can_play: bool,
     sink: Arc<MixerDeviceSink>,
     opened: HashSet<u32>,
+    choice_mode: bool,
+    choice: Option<Choice>,
+    /// Sentence of the current card for the `cloze` and `sentence` fields.
+    example: Option<Example>,
+    speak_sentence: bool,
+    state: Arc<Mutex<AppState>>,
 }
 
 impl NavigatedPage<RepetitionMessage> for RepetitionState {
@@ -39,6 +50,7 @@ impl RepetitionState {
         let (word, stat)  = card_set.next();
         let sink_handle = rodio::DeviceSinkBuilder::open_default_sink().unwrap();
 
+        let example = random_example(&word);
         RepetitionState {
             settings: set,
             set: card_set,
@@ -48,6 +60,11 @@ impl RepetitionState {
             can_play: true,
             sink: Arc::new(sink_handle),
             opened: HashSet::new(),
+            choice_mode: false,
+            choice: None,
+            example,
+            speak_sentence: false,
+            state,
         }
     }
 }
@@ -74,6 +91,13 @@ impl RepetitionState {
             RepetitionMessage::PlayFinished => {
                 self.can_play = true;
             }
+            RepetitionMessage::SetChoiceMode(on) => {
+                self.choice_mode = on;
+                self.update_choice();
+            }
+            RepetitionMessage::Choose(index) => return self.pick(index),
+            RepetitionMessage::PlaySentence => return self.play_sentence(),
+            RepetitionMessage::SetSpeakSentence(on) => self.speak_sentence = on,
         }
 
         Task::none()
@@ -81,13 +105,40 @@ impl RepetitionState {
 
     fn next(&mut self) -> Task<RootMessage> {
         if self.open {
-            self.answer(WordOpenMode::None)
+            let mode = self.choice_grade().unwrap_or(WordOpenMode::None);
+            self.answer(mode)
+        } else if self.choice_mode {
+            Task::none()
         } else {
             self.open = true;
+            self.on_reveal()
+        }
+    }
+
+    /// The full sentence is read out once the answer is shown, if asked to.
+    fn on_reveal(&self) -> Task<RootMessage> {
+        if self.speak_sentence {
+            self.play_sentence()
+        } else {
             Task::none()
         }
     }
 
+    fn play_sentence(&self) -> Task<RootMessage> {
+        let Some(example) = &self.example else {
+            return Task::none();
+        };
+        Task::perform(play_sound(self.sink.clone(), example.sentence.clone()), |_| {
+            RootMessage::Repetition(RepetitionMessage::PlayFinished)
+        })
+    }
+
+    fn uses_sentences(&self) -> bool {
+        [&self.settings.forward, &self.settings.backward]
+            .iter()
+            .any(|side| side.split(' ').any(|t| t == "cloze" || t == "sentence"))
+    }
+
     fn answer(&mut self, mode: WordOpenMode) -> Task<RootMessage> {
         if !self.open {
             return Task::none();
@@ -99,6 +150,8 @@ impl RepetitionState {
         let next = self.set.next();
         self.current_word = next.0;
         self.current_statistic = next.1;
+        self.example = random_example(&self.current_word);
+        self.update_choice();
 
         if self.settings.require_speech() {
             return Task::perform(
@@ -109,10 +162,81 @@ impl RepetitionState {
         Task::none()
     }
 
+    /// Options for the current card, shown instead of its back side.
+    fn update_choice(&mut self) {
+        self.choice = None;
+        if !self.choice_mode {
+            return;
+        }
+        let Some(token) = answer_token(&self.settings.backward, &self.current_word) else {
+            return;
+        };
+        let answer = |w: &WordData| side_text(w, token);
+        // kanji cards are not words, their mix-ups are not kept
+        let none = WordConfusions::new();
+        let state = self.state.lock().unwrap();
+        let confusions = match self.settings.kind {
+            CardKind::Words => &state.word_confusions,
+            CardKind::Kanji => &none,
+        };
+        self.choice = Choice::new(&self.current_word, self.set.words(), answer, confusions);
+    }
+
+    fn choose(&mut self, index: usize) {
+        let Some(choice) = &mut self.choice else {
+            return;
+        };
+        if self.open || index >= choice.options.len() {
+            return;
+        }
+        choice.pick(index);
+        if self.settings.kind == CardKind::Words
+            && let Some(other) = choice.confused_with()
+        {
+            record_word_confusion(&mut self.state.lock().unwrap(), self.current_word.id, other);
+        }
+        self.open = true;
+    }
+
+    fn pick(&mut self, index: usize) -> Task<RootMessage> {
+        let was_open = self.open;
+        self.choose(index);
+        if self.open && !was_open {
+            return self.on_reveal();
+        }
+        Task::none()
+    }
+
+    /// A picked option grades the card by itself, so it can't be fudged.
+    fn choice_grade(&self) -> Option<WordOpenMode> {
+        let choice = self.choice.as_ref()?;
+        let picked = choice.picked?;
+        Some(if picked == choice.correct {
+            WordOpenMode::Ok
+        } else {
+            WordOpenMode::None
+        })
+    }
+
     pub fn view(&self) -> Element<'_, RepetitionMessage> {
         container(
             iced::widget::column![
-                button("Назад").on_press(RepetitionMessage::Back),
+                row![
+                    button("Назад").on_press(RepetitionMessage::Back),
+                    toggler(self.choice_mode)
+                        .label("Выбор из вариантов")
+                        .on_toggle(RepetitionMessage::SetChoiceMode),
+                    if self.uses_sentences() {
+                        toggler(self.speak_sentence)
+                            .label("Озвучивать предложение")
+                            .on_toggle(RepetitionMessage::SetSpeakSentence)
+                            .into()
+                    } else {
+                        Element::from(space())
+                    },
+                ]
+                .align_y(alignment::Vertical::Center)
+                .spacing(DEFAULT_SPACING),
                 column![
                     container(self.draw_forward())
                         .width(Fill)
@@ -148,31 +272,48 @@ impl RepetitionState {
     }
 
     fn draw_forward(&self) -> Element<'_, RepetitionMessage> {
-        self.draw_card_view(self.settings.forward.as_str())
+        self.draw_card_view(self.settings.forward.as_str(), true)
 
     }
 
     fn draw_backward(&self) -> Element<'_, RepetitionMessage> {
+        if let Some(choice) = &self.choice {
+            return column![
+                choice.view(RepetitionMessage::Choose),
+                if self.open {
+                    self.draw_card_view(self.settings.backward.as_str(), false)
+                } else {
+                    space().into()
+                },
+            ]
+            .align_x(Center)
+            .spacing(DEFAULT_SPACING)
+            .into();
+        }
         if !self.open {
             return space().into();
         }
 
 
-        self.draw_card_view(self.settings.backward.as_str())
+        self.draw_card_view(self.settings.backward.as_str(), false)
     }
 
-    fn draw_card_view(&self, properties: &str) -> Element<'_, RepetitionMessage> {
+    fn draw_card_view(&self, properties: &str, front: bool) -> Element<'_, RepetitionMessage> {
         let word = &self.current_word;
 
         let mut col = Column::new();
 
         for view_type in properties.split(" ") {
             col = col.push( match view_type {
-                "key" => self.draw_key(word),
+                "key" => self.draw_key(word, front),
                 "value" => self.draw_value(word),
                 "speech" => self.draw_voice(),
                 "reading" => self.draw_reading(word),
-                _ => space().into(),
+                "accent" => self.draw_accent(word),
+                "image" => self.draw_images(word),
+                "cloze" => self.draw_cloze(word),
+                "sentence" => self.draw_sentence(),
+                other => self.draw_additional(word, other),
             })
         }
 
@@ -183,6 +324,11 @@ impl RepetitionState {
         if !self.open {
             return space().into();
         }
+        if let Some(mode) = self.choice_grade() {
+            return button("Дальше")
+                .on_press(RepetitionMessage::Answer(mode))
+                .into();
+        }
 
         column![
             text!("{} очков", self.current_statistic.calculated_score().round() as i32),
@@ -199,8 +345,13 @@ impl RepetitionState {
         .into()
     }
 
-    fn draw_key(&self, word: &WordData) -> Element<'_, RepetitionMessage> {
-        text!("{}", word.key).size(36).into()
+    fn draw_key(&self, word: &WordData, front: bool) -> Element<'_, RepetitionMessage> {
+        match word.additional.get("reading") {
+            Some(reading) if self.settings.furigana.shows(front) && !reading.is_empty() => {
+                ruby_view(&furigana(&word.key, reading), 36.0)
+            }
+            _ => text!("{}", word.key).size(36).into(),
+        }
     }
     fn draw_value(&self, word: &WordData) -> Element<'_, RepetitionMessage> {
         text!("{}", word.value).size(24).into()
@@ -215,7 +366,58 @@ impl RepetitionState {
     fn draw_reading(&self, word: &WordData) -> Element<'_, RepetitionMessage> {
         match word.additional.get("reading") {
             None => space().into(),
-            Some(reading) => text!("{}", reading).size(24).into(),
+            Some(reading) => text!("{}", kana_reading(reading)).size(24).into(),
+        }
+    }
+
+    fn draw_images(&self, word: &WordData) -> Element<'_, RepetitionMessage> {
+        let mut images = row![].spacing(DEFAULT_SPACING);
+        for name in image_names(word) {
+            images = images.push(thumbnail(&name, 200.0));
+        }
+        images.into()
+    }
+
+    fn draw_accent(&self, word: &WordData) -> Element<'_, RepetitionMessage> {
+        match pitch_line(word) {
+            None => space().into(),
+            Some(line) => line.view(24.0),
+        }
+    }
+
+    /// The sentence with the word blanked out; the translation of the word
+    /// when it has no usable example.
+    fn draw_cloze(&self, word: &WordData) -> Element<'_, RepetitionMessage> {
+        match &self.example {
+            None => self.draw_value(word),
+            Some(example) => column![
+                text!("{}", example.cloze).size(30),
+                text!("{}", example.translation).size(18),
+            ]
+            .align_x(Center)
+            .spacing(5)
+            .into(),
+        }
+    }
+
+    fn draw_sentence(&self) -> Element<'_, RepetitionMessage> {
+        match &self.example {
+            None => space().into(),
+            Some(example) => row![
+                text!("{}", example.sentence).size(30),
+                button("▶").on_press(RepetitionMessage::PlaySentence),
+            ]
+            .align_y(alignment::Vertical::Center)
+            .spacing(DEFAULT_SPACING)
+            .into(),
+        }
+    }
+
+    /// Any other field by name, e.g. `on` and `kun` of kanji cards.
+    fn draw_additional(&self, word: &WordData, field: &str) -> Element<'_, RepetitionMessage> {
+        match word.additional.get(field) {
+            None => space().into(),
+            Some(value) => text!("{}", value).size(24).into(),
         }
     }
 }
@@ -233,6 +435,18 @@ impl KeyPressedPage for RepetitionState {
         } = message
         {
             if let Code(code) = pk {
+                if self.choice.is_some() {
+                    return match code {
+                        keyboard::key::Code::Space => self.next(),
+                        keyboard::key::Code::Digit1 => self.pick(0),
+                        keyboard::key::Code::Digit2 => self.pick(1),
+                        keyboard::key::Code::Digit3 => self.pick(2),
+                        keyboard::key::Code::Digit4 => self.pick(3),
+                        keyboard::key::Code::Digit5 => self.pick(4),
+                        keyboard::key::Code::Digit6 => self.pick(5),
+                        _ => Task::none(),
+                    };
+                }
                 return match code {
                     keyboard::key::Code::Space => self.next(),
                     keyboard::key::Code::Digit1 => self.answer(WordOpenMode::None),
@@ -254,10 +468,38 @@ pub enum RepetitionMessage {
     Answer(WordOpenMode),
     Play,
     PlayFinished,
+    SetChoiceMode(bool),
+    Choose(usize),
+    PlaySentence,
+    SetSpeakSentence(bool),
 }
 
-async fn play_sound(sink: Arc<MixerDeviceSink>, text: String) {
-    let data = get_voice(text.as_str()).await;
+/// The first field of the back side the options can be made of, skipping
+/// the ones the word has left empty.
+fn answer_token<'a>(backward: &'a str, word: &WordData) -> Option<&'a str> {
+    backward.split(' ').find(|t| {
+        !t.is_empty()
+            && !matches!(*t, "speech" | "accent" | "image" | "cloze" | "sentence")
+            && !side_text(word, t).trim().is_empty()
+    })
+}
+
+fn side_text(word: &WordData, token: &str) -> String {
+    match token {
+        "key" => word.key.clone(),
+        "value" => word.value.clone(),
+        other => word.additional.get(other).cloned().unwrap_or_default(),
+    }
+}
+
+pub(crate) async fn play_sound(sink: Arc<MixerDeviceSink>, text: String) {
+    let data = match get_voice(text.as_str()).await {
+        Ok(data) => data,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
     spawn_blocking(move || {
         rodio::play(&sink.mixer(), data).unwrap().sleep_until_end();
     })
@@ -1,13 +1,21 @@
 use crate::data_provider::card_sets::{delete_set, update_card_set};
-use crate::lang::WordData;
+use crate::kanji::{add_dictionary_kanji, is_kanji, words_with};
+use crate::exam::ExamState;
+use crate::fields::word_fields;
+use crate::lang::{WordData, WordGroup};
+use crate::matching::MatchingState;
 use crate::repetition::RepetitionState;
 use crate::Page::{PreviousPage, Repetition};
 use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
 use iced::widget::button::danger;
 pub use iced::widget::button::{Catalog, Style};
-use iced::widget::{button, column, container, row, scrollable, space, text, text_input, Column};
+use iced::widget::{
+    button, column, container, pick_list, row, scrollable, space, text, text_input, Column,
+};
 use iced::{Border, Center, Element, Fill, Left, Length, Shadow, Task, Theme};
 use rhai::{Engine, Scope};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
@@ -29,6 +37,21 @@ impl NavigatedPage<RepetitionsMessage> for RepetitionsState {
                 card_set = self.state.lock().unwrap().card_sets[self.selected_set.unwrap()].clone();
             }
             Some(Repetition(RepetitionState::new(card_set, clone) ))
+        } else if let RepetitionsMessage::GoToMatching = message {
+            let state = self.state.lock().unwrap();
+            let card_set = state.card_sets[self.selected_set.unwrap()].clone();
+            let words = card_set.get_word_list(&state);
+            Some(Page::Matching(MatchingState::new(words, Some(card_set), self.state.clone())))
+        } else if let RepetitionsMessage::GoToExam = message {
+            let state = self.state.lock().unwrap();
+            let card_set = state.card_sets[self.selected_set.unwrap()].clone();
+            let words = card_set.get_word_list(&state);
+            Some(Page::Exam(ExamState::new(
+                words.clone(),
+                words,
+                card_set.name,
+                self.state.clone(),
+            )))
         } else {
             None
         }
@@ -37,7 +60,11 @@ impl NavigatedPage<RepetitionsMessage> for RepetitionsState {
 
 impl RepetitionsState {
     pub(crate) fn new(state: Arc<Mutex<AppState>>) -> RepetitionsState {
-        let count = state.lock().unwrap().card_sets.len();
+        let count = {
+            let mut state = state.lock().unwrap();
+            add_dictionary_kanji(&mut state);
+            state.card_sets.len()
+        };
         RepetitionsState {
             selected_set: None,
             correct_filters: vec![true; count],
@@ -53,6 +80,8 @@ impl RepetitionsState {
             RepetitionsMessage::Next => {}
             RepetitionsMessage::Back => {}
             RepetitionsMessage::GoToRepetition => {}
+            RepetitionsMessage::GoToMatching => {}
+            RepetitionsMessage::GoToExam => {}
             RepetitionsMessage::CreateSet => {
                 let index = state.card_sets.len() + 1;
                 state
@@ -89,6 +118,12 @@ impl RepetitionsState {
             RepetitionsMessage::SetBackward(new) => {
                 state.card_sets[self.selected_set.unwrap()].backward = new;
             }
+            RepetitionsMessage::SetFurigana(mode) => {
+                state.card_sets[self.selected_set.unwrap()].furigana = mode;
+            }
+            RepetitionsMessage::SetKind(kind) => {
+                state.card_sets[self.selected_set.unwrap()].kind = kind;
+            }
             RepetitionsMessage::SetFilter(new) => {
                 state.card_sets[self.selected_set.unwrap()].filter = new;
             }
@@ -133,28 +168,48 @@ impl RepetitionsState {
 
     fn launch_button(&self) -> Element<'_, RepetitionsMessage> {
         if let Some(_) = self.selected_set {
-            return button(text!("▷").height(Fill).center())
-                .height(200)
-                .on_press(RepetitionsMessage::GoToRepetition)
-                .into();
+            return column![
+                button(text!("▷").height(Fill).center())
+                    .height(200)
+                    .on_press(RepetitionsMessage::GoToRepetition),
+                button(text!("Пары").center())
+                    .on_press(RepetitionsMessage::GoToMatching),
+                button(text!("Экзамен").center())
+                    .on_press(RepetitionsMessage::GoToExam),
+            ]
+            .spacing(DEFAULT_SPACING)
+            .into();
         }
         space().into()
     }
 
     fn selected_set_view(&self) -> Element<'_, RepetitionsMessage> {
         if let Some(index) = self.selected_set {
-            let sets = &self.state.lock().unwrap().card_sets;
+            let state = self.state.lock().unwrap();
+            let sets = &state.card_sets;
 
             return column![
                 scrollable(
                     column![
                         text_input("Название набора", &sets[index].name)
                             .on_input(RepetitionsMessage::SetName),
+                        pick_list(
+                            CardKind::ALL,
+                            Some(sets[index].kind),
+                            RepetitionsMessage::SetKind
+                        ),
                         text_input("Передняя сторона", &sets[index].forward)
                             .on_input(RepetitionsMessage::SetForward),
                         text_input("Задняя сторона", &sets[index].backward)
                             .on_input(RepetitionsMessage::SetBackward),
-                        text!("Фильтр"),
+                        text!("{}", sets[index].kind.sides_hint()),
+                        text!("{}", group_fields_hint(sets[index].kind, &state.word_groups)),
+                        pick_list(
+                            FuriganaMode::ALL,
+                            Some(sets[index].furigana),
+                            RepetitionsMessage::SetFurigana
+                        ),
+                        text!("{}", sets[index].kind.filter_hint()),
                         text_input("", &sets[index].filter).on_input(RepetitionsMessage::SetFilter),
                         button("Проверить фильтр").on_press(RepetitionsMessage::TryFilter),
                         self.count_view(&sets[index])
@@ -216,6 +271,8 @@ pub enum RepetitionsMessage {
     Next,
     Back,
     GoToRepetition,
+    GoToMatching,
+    GoToExam,
     CreateSet,
     DeleteSet,
     SetName(String),
@@ -224,9 +281,127 @@ pub enum RepetitionsMessage {
     SetForward(String),
     SetBackward(String),
     SetFilter(String),
+    SetKind(CardKind),
+    SetFurigana(FuriganaMode),
     TryFilter,
 }
 
+/// What the cards of a set are made of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardKind {
+    Words,
+    Kanji,
+}
+
+impl CardKind {
+    const ALL: [CardKind; 2] = [CardKind::Words, CardKind::Kanji];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            CardKind::Words => "words",
+            CardKind::Kanji => "kanji",
+        }
+    }
+
+    pub fn from_key(key: &str) -> CardKind {
+        match key {
+            "kanji" => CardKind::Kanji,
+            _ => CardKind::Words,
+        }
+    }
+
+    fn sides_hint(&self) -> &'static str {
+        match self {
+            CardKind::Words => "Поля сторон: key, value, reading, accent, image, speech, cloze, sentence",
+            CardKind::Kanji => {
+                "Поля сторон: key, value, on, kun, strokes, components, grade, jlpt, radical"
+            }
+        }
+    }
+
+    fn filter_hint(&self) -> &'static str {
+        match self {
+            CardKind::Words => "Фильтр: key, value, tags, more, group",
+            CardKind::Kanji => {
+                "Фильтр: key, value, more (on, kun, strokes, grade, jlpt, radical, components), in_dictionary"
+            }
+        }
+    }
+}
+
+/// Fields of the word groups, usable on card sides and in the filter.
+fn group_fields_hint(kind: CardKind, groups: &[WordGroup]) -> String {
+    if kind == CardKind::Kanji {
+        return String::new();
+    }
+    let mut names = vec![];
+    for field in groups.iter().flat_map(|g| &g.fields) {
+        if !names.contains(&field.name) {
+            names.push(field.name.clone());
+        }
+    }
+    if names.is_empty() {
+        return String::new();
+    }
+    format!("Поля групп слов: {}", names.join(", "))
+}
+
+impl Display for CardKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardKind::Words => write!(f, "Слова"),
+            CardKind::Kanji => write!(f, "Кандзи"),
+        }
+    }
+}
+
+/// On which side of a card the key gets its reading over the kanji.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuriganaMode {
+    /// On the front and so on the back as well.
+    Front,
+    Back,
+    Never,
+}
+
+impl FuriganaMode {
+    const ALL: [FuriganaMode; 3] = [FuriganaMode::Front, FuriganaMode::Back, FuriganaMode::Never];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            FuriganaMode::Front => "front",
+            FuriganaMode::Back => "back",
+            FuriganaMode::Never => "never",
+        }
+    }
+
+    pub fn from_key(key: &str) -> FuriganaMode {
+        match key {
+            "front" => FuriganaMode::Front,
+            "back" => FuriganaMode::Back,
+            _ => FuriganaMode::Never,
+        }
+    }
+
+    pub fn shows(&self, front: bool) -> bool {
+        match self {
+            FuriganaMode::Front => true,
+            FuriganaMode::Back => !front,
+            FuriganaMode::Never => false,
+        }
+    }
+}
+
+impl Display for FuriganaMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FuriganaMode::Front => write!(f, "Фуригана на обеих сторонах"),
+            FuriganaMode::Back => write!(f, "Фуригана только на обороте"),
+            FuriganaMode::Never => write!(f, "Без фуриганы"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CardSetSettings {
     pub id: u32,
@@ -234,6 +409,8 @@ pub struct CardSetSettings {
     pub forward: String,
     pub backward: String,
     pub filter: String,
+    pub kind: CardKind,
+    pub furigana: FuriganaMode,
     pub count: Option<usize>,
 }
 
@@ -245,6 +422,8 @@ impl CardSetSettings {
             forward: "".to_string(),
             backward: "".to_string(),
             filter: "true".to_string(),
+            kind: CardKind::Words,
+            furigana: FuriganaMode::Never,
             count: None,
         }
     }
@@ -265,20 +444,23 @@ impl CardSetSettings {
 
         let ast = ast.unwrap();
 
+        if self.kind == CardKind::Kanji {
+            return self.get_kanji_list(state, &engine, &ast);
+        }
+
         let groups = &state.word_groups;
 
         for word in &state.dictionary {
-            let mut more = rhai::Map::new();
-            for iced in &word.additional {
-                more.insert(iced.0.clone().into(), iced.1.clone().into());
-            }
             let mut scope = Scope::new();
             scope
                 .push_constant("key", word.key.clone())
                 .push_constant("value", word.value.clone())
                 .push_constant("tags", word.tags.clone())
-                .push_constant("more", more)
+                .push_constant("more", more_map(word))
                 .push_constant("group", groups.iter().find(|g| g.id == word.group_id).cloned().unwrap().name);
+            for spec in word_fields(word, groups) {
+                scope.push_constant(spec.name.as_str(), spec.filter_value(word.additional.get(&spec.name)));
+            }
 
             let result = engine.eval_ast_with_scope::<bool>(&mut scope, &ast);
             if result.is_ok() && result.unwrap() {
@@ -289,7 +471,47 @@ impl CardSetSettings {
         list
     }
 
+    fn get_kanji_list(&self, state: &AppState, engine: &Engine, ast: &rhai::AST) -> Vec<WordData> {
+        let used = state
+            .dictionary
+            .iter()
+            .flat_map(|word| word.key.chars().filter(|c| is_kanji(*c)))
+            .collect::<HashSet<char>>();
+        let mut kanji = state.kanji.iter().collect::<Vec<_>>();
+        kanji.sort_by_key(|(_, k)| k.id);
+
+        let mut list = vec![];
+        for (literal, kanji) in kanji {
+            let mut word = kanji.to_word();
+            if word.value.is_empty() {
+                word.value = words_with(*literal, &state.dictionary);
+            }
+            let mut scope = Scope::new();
+            scope
+                .push_constant("key", word.key.clone())
+                .push_constant("value", word.value.clone())
+                .push_constant("tags", word.tags.clone())
+                .push_constant("more", more_map(&word))
+                .push_constant("in_dictionary", used.contains(literal));
+
+            let result = engine.eval_ast_with_scope::<bool>(&mut scope, ast);
+            if result.is_ok() && result.unwrap() {
+                list.push(word);
+            }
+        }
+
+        list
+    }
+
     pub fn require_speech(&self) -> bool {
         self.forward == "speech" || self.backward == "speech" 
     }
 }
+
+fn more_map(word: &WordData) -> rhai::Map {
+    let mut more = rhai::Map::new();
+    for iced in &word.additional {
+        more.insert(iced.0.clone().into(), iced.1.clone().into());
+    }
+    more
+}
@@ -0,0 +1,322 @@
+use crate::kana::to_hiragana;
+use crate::lang::KanaSet;
+use crate::confusables::{look_alikes, Confusions};
+use crate::quiz::{record_kana_answer, record_kana_confusion, Score};
+use crate::repetition::play_sound;
+use crate::romaji::{is_complete, same_sound, to_kana, to_kana_final};
+use crate::Page::PreviousPage;
+use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
+use iced::widget::*;
+use iced::{alignment, Element, Fill, Task};
+use rand::seq::SliceRandom;
+use rodio::MixerDeviceSink;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+const CANDIDATES_COUNT: usize = 6;
+
+/// Romaji (or sound) → kana. The answer is typed as romaji and converted on
+/// the fly, or picked from a grid of kana that are easy to mix up with it.
+pub struct ReverseQuizState {
+    set: KanaSet,
+    queue: Vec<(String, String)>,
+    kana: String,
+    roman: String,
+    answer: String,
+    candidates: Vec<String>,
+    score: Score,
+    is_help: bool,
+    audio: bool,
+    can_play: bool,
+    sink: Option<Arc<MixerDeviceSink>>,
+    shown_at: Instant,
+    state: Arc<Mutex<AppState>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ReverseQuizMessage {
+    Back,
+    AnswerChanged(String),
+    SubmitAnswer,
+    Choose(String),
+    Help,
+    SetAudio(bool),
+    Play,
+    PlayFinished,
+}
+
+impl NavigatedPage<ReverseQuizMessage> for ReverseQuizState {
+    fn navigate(&self, message: &ReverseQuizMessage) -> Option<Page> {
+        if let ReverseQuizMessage::Back = message {
+            Some(PreviousPage)
+        } else {
+            None
+        }
+    }
+}
+
+impl ReverseQuizState {
+    pub(crate) fn new(set: KanaSet, state: Arc<Mutex<AppState>>) -> ReverseQuizState {
+        let mut quiz = ReverseQuizState {
+            set,
+            queue: vec![],
+            kana: String::new(),
+            roman: String::new(),
+            answer: String::new(),
+            candidates: vec![],
+            score: Default::default(),
+            is_help: false,
+            audio: false,
+            can_play: true,
+            sink: None,
+            shown_at: Instant::now(),
+            state,
+        };
+        quiz.show_next();
+        quiz
+    }
+
+    pub fn update(&mut self, message: ReverseQuizMessage) -> Task<RootMessage> {
+        match message {
+            ReverseQuizMessage::Back => {}
+            ReverseQuizMessage::AnswerChanged(content) => {
+                if content.contains('`') {
+                    return self.help();
+                }
+                self.answer = to_kana(&content);
+                let answer = to_hiragana(&self.answer);
+                let target = to_hiragana(&self.kana);
+
+                if same_sound(&answer, &target) {
+                    return self.answered(true);
+                }
+                if is_complete(&self.answer) && !target.starts_with(&answer) {
+                    self.record_confusion(&answer);
+                    return self.answered(false);
+                }
+            }
+            ReverseQuizMessage::SubmitAnswer => {
+                if self.answer.is_empty() {
+                    return Task::none();
+                }
+                let answer = to_hiragana(&to_kana_final(&self.answer));
+                let correct = same_sound(&answer, &to_hiragana(&self.kana));
+                if !correct {
+                    self.record_confusion(&answer);
+                }
+                return self.answered(correct);
+            }
+            ReverseQuizMessage::Choose(kana) => {
+                let correct = same_sound(&kana, &self.kana);
+                if !correct {
+                    record_kana_confusion(&mut self.state.lock().unwrap(), &self.kana, &kana);
+                }
+                return self.answered(correct);
+            }
+            ReverseQuizMessage::Help => return self.help(),
+            ReverseQuizMessage::SetAudio(b) => {
+                self.audio = b;
+                if b {
+                    return self.play();
+                }
+            }
+            ReverseQuizMessage::Play => return self.play(),
+            ReverseQuizMessage::PlayFinished => self.can_play = true,
+        }
+        Task::none()
+    }
+
+    /// Records a typed answer as a mix-up when it is another kana of the set.
+    fn record_confusion(&self, answer: &str) {
+        let list = self.set.list();
+        let guess = list.iter().find(|(kana, _)| to_hiragana(kana) == answer);
+        if let Some((guess, _)) = guess {
+            record_kana_confusion(&mut self.state.lock().unwrap(), &self.kana, guess);
+        }
+    }
+
+    fn help(&mut self) -> Task<RootMessage> {
+        if !self.is_help {
+            self.is_help = true;
+            self.score.fail += 1;
+        }
+        Task::none()
+    }
+
+    fn answered(&mut self, correct: bool) -> Task<RootMessage> {
+        {
+            let mut state = self.state.lock().unwrap();
+            record_kana_answer(
+                &mut state,
+                &self.kana,
+                correct,
+                self.is_help,
+                self.shown_at.elapsed(),
+            );
+        }
+
+        self.score.total += 1;
+        if correct && !self.is_help {
+            self.score.correct += 1;
+        } else if !correct {
+            self.score.fail += 1;
+        }
+
+        self.show_next();
+        if self.audio {
+            return self.play();
+        }
+        Task::none()
+    }
+
+    fn show_next(&mut self) {
+        self.answer = String::new();
+        self.is_help = false;
+
+        if self.queue.is_empty() {
+            self.queue = self.set.list();
+            self.queue.shuffle(&mut rand::rng());
+        }
+
+        let (kana, roman) = self.queue.pop().unwrap();
+        self.candidates = candidates(
+            &kana,
+            &self.set.list(),
+            CANDIDATES_COUNT,
+            &self.state.lock().unwrap().kana_confusions,
+        );
+        self.kana = kana;
+        self.roman = roman;
+        self.shown_at = Instant::now();
+    }
+
+    fn play(&mut self) -> Task<RootMessage> {
+        if !self.can_play {
+            return Task::none();
+        }
+        if self.sink.is_none() {
+            match rodio::DeviceSinkBuilder::open_default_sink() {
+                Ok(sink) => self.sink = Some(Arc::new(sink)),
+                Err(e) => {
+                    println!("{}", e);
+                    return Task::none();
+                }
+            }
+        }
+
+        self.can_play = false;
+        Task::perform(
+            play_sound(self.sink.clone().unwrap(), to_hiragana(&self.kana)),
+            |_| RootMessage::ReverseQuiz(ReverseQuizMessage::PlayFinished),
+        )
+    }
+
+    pub fn view(&self) -> Element<'_, ReverseQuizMessage> {
+        container(
+            iced::widget::column![
+                checkbox(self.audio)
+                    .label("Звуковая подсказка")
+                    .on_toggle(ReverseQuizMessage::SetAudio),
+                self.prompt(),
+                text!("{}", if self.is_help { self.kana.as_str() } else { "" }).size(28),
+                text_input("Романдзи → кана", &self.answer)
+                    .size(28)
+                    .width(200)
+                    .on_input(ReverseQuizMessage::AnswerChanged)
+                    .on_submit(ReverseQuizMessage::SubmitAnswer),
+                self.candidates_grid(),
+                row![
+                    text!("{}", self.score.total.to_string()).size(25),
+                    text!("{}", self.score.correct.to_string())
+                        .size(25)
+                        .color(iced::Color::from_rgb8(60, 170, 60)),
+                    text!("{}", self.score.fail.to_string())
+                        .color(iced::Color::from_rgb8(255, 79, 0))
+                        .size(25),
+                ]
+                .spacing(DEFAULT_SPACING),
+                row![
+                    button("Закончить").on_press(ReverseQuizMessage::Back),
+                    button("Подсказка").on_press(ReverseQuizMessage::Help),
+                ]
+                .spacing(DEFAULT_SPACING),
+            ]
+            .spacing(DEFAULT_SPACING)
+            .align_x(alignment::Horizontal::Center),
+        )
+        .center_y(Fill)
+        .center_x(Fill)
+        .into()
+    }
+
+    fn prompt(&self) -> Element<'_, ReverseQuizMessage> {
+        if self.audio {
+            return button(text!("▶").size(40))
+                .on_press(ReverseQuizMessage::Play)
+                .into();
+        }
+        text!("{}", self.roman).size(54).into()
+    }
+
+    fn candidates_grid(&self) -> Element<'_, ReverseQuizMessage> {
+        let mut grid = Row::new();
+        for kana in &self.candidates {
+            grid = grid.push(
+                button(text!("{}", kana).size(32))
+                    .padding(10)
+                    .style(button::secondary)
+                    .on_press(ReverseQuizMessage::Choose(kana.clone())),
+            );
+        }
+        grid.spacing(DEFAULT_SPACING).into()
+    }
+}
+
+/// The kana together with up to `count - 1` others: its look-alikes first,
+/// then kana from the same row or column of the table, shuffled.
+pub(crate) fn candidates(
+    kana: &str,
+    list: &[(String, String)],
+    count: usize,
+    confusions: &Confusions,
+) -> Vec<String> {
+    let enabled = list.iter().map(|(k, _)| k.clone()).collect::<Vec<String>>();
+    let look_alikes = look_alikes(kana, &enabled, confusions);
+
+    let roman = list
+        .iter()
+        .find(|(k, _)| k == kana)
+        .map(|(_, r)| r.as_str())
+        .unwrap_or("");
+    let mut others = list
+        .iter()
+        .filter(|(k, _)| !same_sound(k, kana))
+        .map(|(k, r)| {
+            let look_alike = if look_alikes.contains(k) { 10 } else { 0 };
+            (k.clone(), similarity(roman, r) + look_alike)
+        })
+        .collect::<Vec<(String, u32)>>();
+    others.shuffle(&mut rand::rng());
+    others.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+    let mut result = others
+        .into_iter()
+        .take(count.saturating_sub(1))
+        .map(|(k, _)| k)
+        .collect::<Vec<String>>();
+    result.push(kana.to_string());
+    result.shuffle(&mut rand::rng());
+    result
+}
+
+fn similarity(a: &str, b: &str) -> u32 {
+    let consonant = |r: &str| r.trim_end_matches(['a', 'i', 'u', 'e', 'o']).to_string();
+    let mut score = 0;
+    if consonant(a) == consonant(b) {
+        score += 2;
+    }
+    if a.chars().last() == b.chars().last() {
+        score += 1;
+    }
+    score
+}
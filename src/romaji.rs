@@ -0,0 +1,350 @@
+use crate::kana::{to_hiragana, to_katakana, KANA_ROWS};
+use crate::romanization::Romanization;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Romaji → hiragana for every spelling the romanization module accepts.
+/// A bare `n` is left out on purpose: it is only known to be ん once the
+/// next letter arrives.
+static ROMAJI_TABLE: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+    let mut table = HashMap::new();
+    for row in KANA_ROWS {
+        for (hiragana, _, roman) in row.chars {
+            table
+                .entry(roman.to_string())
+                .or_insert(hiragana.to_string());
+        }
+    }
+    for row in KANA_ROWS {
+        for (hiragana, _, roman) in row.chars {
+            let romanization = Romanization::new(hiragana, roman);
+            for spelling in romanization.all() {
+                table
+                    .entry(spelling.to_string())
+                    .or_insert(hiragana.to_string());
+            }
+        }
+    }
+    for (roman, kana) in EXTENDED {
+        table
+            .entry(roman.to_string())
+            .or_insert(kana.to_string());
+    }
+    // small kana are typed with "l" as well as "x"
+    let small = table
+        .iter()
+        .filter_map(|(roman, kana)| Some((format!("l{}", roman.strip_prefix('x')?), kana.clone())))
+        .collect::<Vec<_>>();
+    for (roman, kana) in small {
+        table.entry(roman).or_insert(kana);
+    }
+    table.remove("n");
+    table.insert("nn".to_string(), "ん".to_string());
+    table.insert("n'".to_string(), "ん".to_string());
+    table
+});
+
+/// Spellings outside the kana tables: short forms of small kana and the
+/// combinations loanwords are written with.
+const EXTENDED: [(&str, &str); 24] = [
+    ("xtu", "っ"),
+    ("xka", "ゕ"),
+    ("xke", "ゖ"),
+    ("fa", "ふぁ"),
+    ("fi", "ふぃ"),
+    ("fe", "ふぇ"),
+    ("fo", "ふぉ"),
+    ("she", "しぇ"),
+    ("je", "じぇ"),
+    ("che", "ちぇ"),
+    ("thi", "てぃ"),
+    ("dhi", "でぃ"),
+    ("twu", "とぅ"),
+    ("dwu", "どぅ"),
+    ("tsa", "つぁ"),
+    ("tso", "つぉ"),
+    ("wi", "うぃ"),
+    ("we", "うぇ"),
+    ("wo", "を"),
+    ("va", "ゔぁ"),
+    ("vi", "ゔぃ"),
+    ("vu", "ゔ"),
+    ("ve", "ゔぇ"),
+    ("vo", "ゔぉ"),
+];
+
+/// Which kana the romaji is converted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Script {
+    Hiragana,
+    Katakana,
+    /// Katakana for syllables typed in capitals, hiragana for the rest.
+    ByCase,
+}
+
+/// Hiragana → Hepburn romaji, the other way round.
+static KANA_TABLE: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+    let mut table = HashMap::new();
+    for row in KANA_ROWS {
+        for (hiragana, _, roman) in row.chars {
+            table.insert(hiragana.to_string(), roman.to_string());
+        }
+    }
+    table
+});
+
+const LONGEST_ROMAJI: usize = 4;
+
+/// Converts romaji to hiragana. Kana already in the text is kept, and a
+/// trailing piece that may still become a kana (`k`, `sh`, `n`) is left as typed.
+pub fn to_kana(input: &str) -> String {
+    to_kana_in(input, Script::Hiragana)
+}
+
+/// Like `to_kana`, but also settles a trailing `n` as ん, for a finished answer.
+pub fn to_kana_final(input: &str) -> String {
+    to_kana_final_in(input, Script::Hiragana)
+}
+
+/// `to_kana` with the choice of script. Vowels with a macron or circumflex
+/// are long: ō is おう in hiragana and オー in katakana.
+pub fn to_kana_in(input: &str, script: Script) -> String {
+    let chars = expand_long_vowels(input, script);
+    let mut result = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i].to_ascii_lowercase();
+        if c == '-' {
+            result.push('ー');
+            i += 1;
+            continue;
+        }
+        if !c.is_ascii_alphabetic() && c != '\'' {
+            result.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let next = chars.get(i + 1).map(|n| n.to_ascii_lowercase());
+        let after = chars.get(i + 2).map(|n| n.to_ascii_lowercase());
+        let doubled = next == Some(c) && is_consonant(c) && c != 'n';
+        if doubled || (c == 't' && next == Some('c') && after == Some('h')) {
+            push_kana(&mut result, "っ", &chars[i..i + 2], script);
+            i += 1;
+            continue;
+        }
+        // "konnichi": the first n is ん, the second one starts に
+        if c == 'n' && next == Some('n') && after.is_some_and(|a| is_vowel(a) || a == 'y') {
+            push_kana(&mut result, "ん", &chars[i..i + 1], script);
+            i += 1;
+            continue;
+        }
+
+        if let Some((kana, len)) = longest_match(&chars[i..]) {
+            push_kana(&mut result, kana, &chars[i..i + len], script);
+            i += len;
+            continue;
+        }
+
+        if c == 'n' && next.is_some_and(|n| n != 'y' && !is_vowel(n)) {
+            push_kana(&mut result, "ん", &chars[i..i + 1], script);
+            i += 1;
+            continue;
+        }
+
+        let rest = chars[i..].iter().collect::<String>().to_ascii_lowercase();
+        if is_pending(&rest) {
+            result.push_str(&chars[i..].iter().collect::<String>());
+            break;
+        }
+
+        result.push(chars[i]);
+        i += 1;
+    }
+
+    result
+}
+
+/// `to_kana_final` with the choice of script.
+pub fn to_kana_final_in(input: &str, script: Script) -> String {
+    let mut result = to_kana_in(input, script);
+    if let Some(n) = result.pop() {
+        match n {
+            'n' | 'N' => push_kana(&mut result, "ん", &[n], script),
+            _ => result.push(n),
+        }
+    }
+    result
+}
+
+fn push_kana(result: &mut String, kana: &str, typed: &[char], script: Script) {
+    let katakana = match script {
+        Script::Hiragana => false,
+        Script::Katakana => true,
+        Script::ByCase => typed.iter().all(|c| !c.is_ascii_lowercase()),
+    };
+    if katakana {
+        result.push_str(&to_katakana(kana));
+    } else {
+        result.push_str(kana);
+    }
+}
+
+fn expand_long_vowels(input: &str, script: Script) -> Vec<char> {
+    let mut chars = vec![];
+    for c in input.chars() {
+        let (vowel, long) = match c {
+            'ā' | 'â' => ('a', 'a'),
+            'ī' | 'î' => ('i', 'i'),
+            'ū' | 'û' => ('u', 'u'),
+            'ē' | 'ê' => ('e', 'i'),
+            'ō' | 'ô' => ('o', 'u'),
+            'Ā' | 'Â' => ('A', 'A'),
+            'Ī' | 'Î' => ('I', 'I'),
+            'Ū' | 'Û' => ('U', 'U'),
+            'Ē' | 'Ê' => ('E', 'I'),
+            'Ō' | 'Ô' => ('O', 'U'),
+            _ => {
+                chars.push(c);
+                continue;
+            }
+        };
+        let katakana = match script {
+            Script::Hiragana => false,
+            Script::Katakana => true,
+            Script::ByCase => vowel.is_ascii_uppercase(),
+        };
+        chars.push(vowel);
+        chars.push(if katakana { '-' } else { long });
+    }
+    chars
+}
+
+/// Whether the text has no romaji left that is still waiting for more letters.
+pub fn is_complete(converted: &str) -> bool {
+    !converted.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+fn longest_match(chars: &[char]) -> Option<(&'static str, usize)> {
+    for len in (1..=LONGEST_ROMAJI.min(chars.len())).rev() {
+        let candidate = chars[..len].iter().collect::<String>().to_ascii_lowercase();
+        if let Some(kana) = ROMAJI_TABLE.get(&candidate) {
+            return Some((kana.as_str(), len));
+        }
+    }
+    None
+}
+
+fn is_pending(rest: &str) -> bool {
+    rest == "n" || ROMAJI_TABLE.keys().any(|key| key.starts_with(rest))
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'i' | 'u' | 'e' | 'o')
+}
+
+fn is_consonant(c: char) -> bool {
+    c.is_ascii_alphabetic() && !is_vowel(c)
+}
+
+/// Hepburn reading of a kana string: yōon are read as one syllable, っ doubles
+/// the next consonant and ー repeats the previous vowel.
+pub fn to_romaji(kana: &str) -> String {
+    let chars = to_hiragana(kana).chars().collect::<Vec<char>>();
+    let mut result = String::new();
+    let mut double_next = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] == 'っ' && i + 1 < chars.len() {
+            double_next = true;
+            i += 1;
+            continue;
+        }
+        if chars[i] == 'ー' {
+            if let Some(vowel) = result.chars().last().filter(|c| is_vowel(*c)) {
+                result.push(vowel);
+            }
+            i += 1;
+            continue;
+        }
+
+        let pair = chars[i..(i + 2).min(chars.len())].iter().collect::<String>();
+        let single = chars[i].to_string();
+        let (roman, len) = match KANA_TABLE.get(&pair) {
+            Some(roman) if pair.chars().count() == 2 => (roman.clone(), 2),
+            _ => (
+                KANA_TABLE.get(&single).cloned().unwrap_or(single.clone()),
+                1,
+            ),
+        };
+
+        if double_next {
+            match roman.strip_prefix("ch") {
+                Some(_) => result.push('t'),
+                None => result.extend(roman.chars().next()),
+            }
+            double_next = false;
+        }
+        result.push_str(&roman);
+        i += len;
+    }
+
+    result
+}
+
+/// Hepburn reads ぢ as じ and づ as ず, so an answer typed as "ji" or "zu"
+/// is right for either kana of such a pair.
+pub fn same_sound(answer: &str, kana: &str) -> bool {
+    answer == kana || to_romaji(answer) == to_romaji(kana)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hiragana(romaji: &str) -> String {
+        to_kana_final_in(romaji, Script::Hiragana)
+    }
+
+    #[test]
+    fn n_is_settled_by_the_next_letter_or_the_end() {
+        assert_eq!(hiragana("konnichiha"), "こんにちは");
+        assert_eq!(hiragana("kan'i"), "かんい");
+        assert_eq!(hiragana("kani"), "かに");
+        assert_eq!(to_kana_in("kon", Script::Hiragana), "こn");
+        assert_eq!(hiragana("kon"), "こん");
+    }
+
+    #[test]
+    fn doubled_consonant_is_small_tsu() {
+        assert_eq!(hiragana("kitte"), "きって");
+        assert_eq!(hiragana("zasshi"), "ざっし");
+        assert_eq!(hiragana("matcha"), "まっちゃ");
+        assert_eq!(hiragana("tchi"), "っち");
+    }
+
+    #[test]
+    fn x_and_l_make_small_kana() {
+        assert_eq!(hiragana("xtu"), "っ");
+        assert_eq!(hiragana("ltu"), "っ");
+        assert_eq!(hiragana("xa"), "ぁ");
+        assert_eq!(hiragana("lya"), "ゃ");
+    }
+
+    #[test]
+    fn macron_is_a_long_vowel() {
+        assert_eq!(hiragana("tōkyō"), "とうきょう");
+        assert_eq!(hiragana("ōsaka"), "おうさか");
+        assert_eq!(to_kana_final_in("kōhī", Script::Katakana), "コーヒー");
+        assert_eq!(to_kana_final_in("rāmen", Script::Katakana), "ラーメン");
+    }
+
+    #[test]
+    fn katakana_mode_writes_katakana() {
+        assert_eq!(to_kana_final_in("terebi", Script::Katakana), "テレビ");
+        assert_eq!(to_kana_in("kon", Script::Katakana), "コn");
+        assert_eq!(to_kana_final_in("NEKOinu", Script::ByCase), "ネコいぬ");
+    }
+}
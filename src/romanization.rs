@@ -0,0 +1,185 @@
+use crate::kana::to_hiragana;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RomanizationSystem {
+    Hepburn,
+    Kunrei,
+    Nihon,
+}
+
+impl RomanizationSystem {
+    pub const ALL: [RomanizationSystem; 3] = [
+        RomanizationSystem::Hepburn,
+        RomanizationSystem::Kunrei,
+        RomanizationSystem::Nihon,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            RomanizationSystem::Hepburn => "Хэпбёрн",
+            RomanizationSystem::Kunrei => "Кунрэй-сики",
+            RomanizationSystem::Nihon => "Нихон-сики",
+        }
+    }
+}
+
+/// Which spellings the quiz accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RomanizationMode {
+    Any,
+    Only(RomanizationSystem),
+}
+
+impl RomanizationMode {
+    pub const ALL: [RomanizationMode; 4] = [
+        RomanizationMode::Any,
+        RomanizationMode::Only(RomanizationSystem::Hepburn),
+        RomanizationMode::Only(RomanizationSystem::Kunrei),
+        RomanizationMode::Only(RomanizationSystem::Nihon),
+    ];
+
+    fn allows(&self, system: RomanizationSystem) -> bool {
+        match self {
+            RomanizationMode::Any => true,
+            RomanizationMode::Only(only) => *only == system,
+        }
+    }
+}
+
+impl Display for RomanizationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RomanizationMode::Any => write!(f, "Любая система"),
+            RomanizationMode::Only(system) => write!(f, "{}", system.title()),
+        }
+    }
+}
+
+/// Syllables spelled differently by the systems, by hiragana:
+/// [Hepburn, Kunrei-shiki, Nihon-shiki]. Everything else is spelled the same.
+const DIFFERENCES: &[(&str, [&str; 3])] = &[
+    ("し", ["shi", "si", "si"]),
+    ("ち", ["chi", "ti", "ti"]),
+    ("つ", ["tsu", "tu", "tu"]),
+    ("ふ", ["fu", "hu", "hu"]),
+    ("じ", ["ji", "zi", "zi"]),
+    ("ぢ", ["ji", "zi", "di"]),
+    ("づ", ["zu", "zu", "du"]),
+    ("を", ["wo", "o", "wo"]),
+    ("しゃ", ["sha", "sya", "sya"]),
+    ("しゅ", ["shu", "syu", "syu"]),
+    ("しょ", ["sho", "syo", "syo"]),
+    ("ちゃ", ["cha", "tya", "tya"]),
+    ("ちゅ", ["chu", "tyu", "tyu"]),
+    ("ちょ", ["cho", "tyo", "tyo"]),
+    ("じゃ", ["ja", "zya", "zya"]),
+    ("じゅ", ["ju", "zyu", "zyu"]),
+    ("じょ", ["jo", "zyo", "zyo"]),
+    ("ぢゃ", ["ja", "zya", "dya"]),
+    ("ぢゅ", ["ju", "zyu", "dyu"]),
+    ("ぢょ", ["jo", "zyo", "dyo"]),
+    ("っ", ["xtsu", "xtu", "xtu"]),
+];
+
+/// All accepted spellings of one kana.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Romanization {
+    spellings: [String; 3],
+}
+
+impl Romanization {
+    /// `hepburn` is used for the kana the systems agree on.
+    pub fn new(kana: &str, hepburn: &str) -> Self {
+        let hiragana = to_hiragana(kana);
+        let spellings = match DIFFERENCES.iter().find(|(k, _)| *k == hiragana) {
+            Some((_, spellings)) => spellings.map(|s| s.to_string()),
+            None => [hepburn.to_string(), hepburn.to_string(), hepburn.to_string()],
+        };
+
+        Self { spellings }
+    }
+
+    pub fn spelling(&self, system: RomanizationSystem) -> &str {
+        match system {
+            RomanizationSystem::Hepburn => &self.spellings[0],
+            RomanizationSystem::Kunrei => &self.spellings[1],
+            RomanizationSystem::Nihon => &self.spellings[2],
+        }
+    }
+
+    pub fn all(&self) -> &[String] {
+        &self.spellings
+    }
+
+    /// Distinct spellings allowed by the mode, Hepburn first.
+    pub fn accepted(&self, mode: RomanizationMode) -> Vec<&str> {
+        let mut accepted: Vec<&str> = vec![];
+        for system in RomanizationSystem::ALL {
+            let spelling = self.spelling(system);
+            if mode.allows(system) && !accepted.contains(&spelling) {
+                accepted.push(spelling);
+            }
+        }
+        accepted
+    }
+
+    /// Systems, allowed by the mode, that spell the kana exactly as `input`.
+    pub fn systems_for(&self, input: &str, mode: RomanizationMode) -> Vec<RomanizationSystem> {
+        RomanizationSystem::ALL
+            .into_iter()
+            .filter(|system| mode.allows(*system) && self.spelling(*system) == input)
+            .collect()
+    }
+
+    /// Whether `input` can still grow into one of the accepted spellings.
+    pub fn is_prefix(&self, input: &str, mode: RomanizationMode) -> bool {
+        self.accepted(mode).iter().any(|s| s.starts_with(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RomanizationSystem::{Hepburn, Kunrei, Nihon};
+
+    #[test]
+    fn any_mode_accepts_every_system() {
+        let any = RomanizationMode::Any;
+        let pairs = [("し", "shi", "si"), ("つ", "tsu", "tu"), ("ふ", "fu", "hu")];
+        for (kana, hepburn, kunrei) in pairs {
+            let romanization = Romanization::new(kana, hepburn);
+            assert_eq!(romanization.accepted(any), [hepburn, kunrei]);
+            assert_eq!(romanization.systems_for(hepburn, any), [Hepburn]);
+            assert_eq!(romanization.systems_for(kunrei, any), [Kunrei, Nihon]);
+        }
+        assert_eq!(Romanization::new("か", "ka").accepted(any), ["ka"]);
+    }
+
+    #[test]
+    fn only_mode_rejects_other_systems() {
+        let kunrei = RomanizationMode::Only(Kunrei);
+        let shi = Romanization::new("し", "shi");
+        assert_eq!(shi.accepted(kunrei), ["si"]);
+        assert!(shi.systems_for("shi", kunrei).is_empty());
+        assert!(!shi.is_prefix("sh", kunrei));
+    }
+
+    #[test]
+    fn prefix_of_a_spelling_waits_for_more() {
+        let shi = Romanization::new("シ", "shi");
+        assert!(shi.is_prefix("s", RomanizationMode::Any));
+        assert!(shi.is_prefix("sh", RomanizationMode::Any));
+        assert!(!shi.is_prefix("t", RomanizationMode::Any));
+    }
+
+    #[test]
+    fn nihon_spells_di_and_du() {
+        let di = Romanization::new("ぢ", "ji");
+        let du = Romanization::new("づ", "zu");
+        assert_eq!(di.systems_for("di", RomanizationMode::Any), [Nihon]);
+        assert_eq!(du.systems_for("du", RomanizationMode::Any), [Nihon]);
+        assert_eq!(di.accepted(RomanizationMode::Any), ["ji", "zi", "di"]);
+        assert!(du.systems_for("du", RomanizationMode::Only(Hepburn)).is_empty());
+    }
+}
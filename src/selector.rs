@@ -1,18 +1,62 @@
+use std::fmt::{Display, Formatter};
 use std::sync::{Arc, Mutex};
 use crate::dictionary::DictionaryState;
-use crate::lang::{KanaSet, KanaType};
+use crate::discrimination::DiscriminationState;
+use crate::kana::KanaCategory;
+use crate::kanji_dictionary::KanjiDictionaryState;
+use crate::lang::{KanaSet, KanaStatistics, KanaType};
 use crate::randomizer::randomizer::RandomizerState;
+use crate::reading_drill::ReadingState;
+use crate::romanization::RomanizationMode;
+use crate::speed_run::RunLimit;
+use crate::worksheet::{kana_worksheet, worksheet_dir};
 use crate::repetitions::RepetitionsState;
 use crate::selector::SelectorMessage::ChangeMode;
 use crate::writing::WritingState;
-use crate::Page::{Quiz, Writing};
+use crate::reverse_quiz::ReverseQuizState;
+use crate::Page::{Discrimination, Quiz, Reading, ReverseQuiz, Writing};
 use crate::{AppState, NavigatedPage, Page, QuizState, RootMessage, DEFAULT_SPACING};
 use iced::widget::*;
-use iced::{alignment, Element, Task};
+use iced::{alignment, Element, Fill, Task};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KanaMode {
+    Quiz,
+    Reverse,
+    Confusables,
+    Reading,
+    Writing,
+}
+
+impl KanaMode {
+    const ALL: [KanaMode; 5] = [
+        KanaMode::Quiz,
+        KanaMode::Reverse,
+        KanaMode::Confusables,
+        KanaMode::Reading,
+        KanaMode::Writing,
+    ];
+}
+
+impl Display for KanaMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KanaMode::Quiz => write!(f, "Кана → романдзи"),
+            KanaMode::Reverse => write!(f, "Романдзи → кана"),
+            KanaMode::Confusables => write!(f, "Похожие кана"),
+            KanaMode::Reading => write!(f, "Чтение слов"),
+            KanaMode::Writing => write!(f, "Режим письма"),
+        }
+    }
+}
 
 pub struct SelectorState {
     pub set: KanaSet,
-    is_writing: bool,
+    mode: KanaMode,
+    romanization: RomanizationMode,
+    drill: bool,
+    run_limit: RunLimit,
+    worksheet_status: String,
     state: Arc<Mutex<AppState>>
 }
 
@@ -21,22 +65,48 @@ pub enum SelectorMessage {
     Change,
     Goto,
     Check(usize, bool),
-    ChangeMode(bool),
+    CheckCategory(KanaCategory, bool),
+    ChangeMode(KanaMode),
+    SetRomanization(RomanizationMode),
+    SetDrill(bool),
+    SetRunLimit(RunLimit),
+    MakeWorksheet,
     ToDictionary,
     ToRandomize,
     ToRepetitions,
+    ToKanji,
 }
 
 impl NavigatedPage<SelectorMessage> for SelectorState {
     fn navigate(&self, message: &SelectorMessage) -> Option<Page> {
         if let SelectorMessage::Goto = message {
-            return if self.is_writing {
-                let writing = WritingState::new(&self.set);
-                Some(Writing(writing))
-            } else {
-                let mut quiz = QuizState::new();
-                quiz.set = self.set.clone();
-                Some(Quiz(quiz))
+            if self.set.list().is_empty() {
+                return None;
+            }
+            return match self.mode {
+                KanaMode::Writing => Some(Writing(WritingState::new(
+                    &self.set,
+                    self.state.clone(),
+                ))),
+                KanaMode::Reverse => Some(ReverseQuiz(ReverseQuizState::new(
+                    self.set.clone(),
+                    self.state.clone(),
+                ))),
+                KanaMode::Confusables => Some(Discrimination(DiscriminationState::new(
+                    self.set.clone(),
+                    self.state.clone(),
+                ))),
+                KanaMode::Reading => Some(Reading(ReadingState::new(
+                    self.set.clone(),
+                    self.state.clone(),
+                ))),
+                KanaMode::Quiz => Some(Quiz(QuizState::new(
+                    self.set.clone(),
+                    self.romanization,
+                    self.drill,
+                    self.run_limit,
+                    self.state.clone(),
+                ))),
             };
         }
         if let SelectorMessage::ToDictionary = message {
@@ -48,6 +118,9 @@ impl NavigatedPage<SelectorMessage> for SelectorState {
         if let SelectorMessage::ToRepetitions = message {
             return Some(Page::Repetitions(RepetitionsState::new(self.state.clone())));
         }
+        if let SelectorMessage::ToKanji = message {
+            return Some(Page::KanjiDictionary(KanjiDictionaryState::new(self.state.clone())));
+        }
         None
     }
 }
@@ -56,7 +129,11 @@ impl SelectorState {
     pub fn new(state: Arc<Mutex<AppState>>) -> Self {
         Self{
             set: Default::default(),
-            is_writing: false,
+            mode: KanaMode::Quiz,
+            romanization: RomanizationMode::Any,
+            drill: false,
+            run_limit: RunLimit::Off,
+            worksheet_status: String::new(),
             state,
         }
     }
@@ -67,7 +144,17 @@ impl SelectorState {
                 KanaType::Hiragana => self.set = KanaSet::katakana(),
             },
             SelectorMessage::Check(i, b) => self.set.include_map[i] = b,
-            ChangeMode(b) => self.is_writing = b,
+            SelectorMessage::CheckCategory(category, b) => self.set.set_category(category, b),
+            ChangeMode(mode) => self.mode = mode,
+            SelectorMessage::SetRomanization(mode) => self.romanization = mode,
+            SelectorMessage::SetDrill(b) => self.drill = b,
+            SelectorMessage::SetRunLimit(limit) => self.run_limit = limit,
+            SelectorMessage::MakeWorksheet => {
+                self.worksheet_status = match kana_worksheet(&self.set) {
+                    Ok(_) => format!("Сохранено в {}", worksheet_dir().display()),
+                    Err(e) => e.to_string(),
+                };
+            }
             _ => {}
         }
         Task::none()
@@ -80,14 +167,32 @@ impl SelectorState {
                     button("Переключить азбуки").on_press(SelectorMessage::Change),
                     button("Словарь").on_press(SelectorMessage::ToDictionary),
                     button("Рандомайзер").on_press(SelectorMessage::ToRandomize),
-                    button("Повторение").on_press(SelectorMessage::ToRepetitions)
+                    button("Повторение").on_press(SelectorMessage::ToRepetitions),
+                    button("Кандзи").on_press(SelectorMessage::ToKanji)
                 ]
                 .spacing(DEFAULT_SPACING),
                 self.rows_selector(),
-                toggler(self.is_writing)
-                    .label("Режим письма")
-                    .on_toggle(ChangeMode),
-                button("К тесту").on_press(SelectorMessage::Goto),
+                row![
+                    pick_list(KanaMode::ALL, Some(self.mode), ChangeMode),
+                    toggler(self.drill)
+                        .label("Тренировка слабых")
+                        .on_toggle(SelectorMessage::SetDrill),
+                    pick_list(
+                        RomanizationMode::ALL,
+                        Some(self.romanization),
+                        SelectorMessage::SetRomanization
+                    ),
+                    pick_list(RunLimit::ALL, Some(self.run_limit), SelectorMessage::SetRunLimit),
+                ]
+                .align_y(alignment::Vertical::Center)
+                .spacing(DEFAULT_SPACING),
+                row![
+                    button("К тесту").on_press(SelectorMessage::Goto),
+                    button("Прописи").on_press(SelectorMessage::MakeWorksheet),
+                    text!("{}", self.worksheet_status),
+                ]
+                .align_y(alignment::Vertical::Center)
+                .spacing(DEFAULT_SPACING),
             ]
             .spacing(DEFAULT_SPACING),
         )
@@ -96,30 +201,94 @@ impl SelectorState {
     }
 
     fn rows_selector(&self) -> Element<'_, SelectorMessage> {
-        let mut row = Row::new();
+        let mut categories = Column::new();
+        let state = self.state.lock().unwrap();
+
+        for category in KanaCategory::ALL {
+            let mut row = Row::new();
 
-        for i in 0..self.set.dictionary.len() {
-            let setup_checked = move |b: bool| -> SelectorMessage { SelectorMessage::Check(i, b) };
+            for (i, kana_row) in self
+                .set
+                .dictionary
+                .iter()
+                .enumerate()
+                .filter(|(_, kana_row)| kana_row.category == category)
+            {
+                let setup_checked =
+                    move |b: bool| -> SelectorMessage { SelectorMessage::Check(i, b) };
 
-            let mut chars_column: Column<'_, _> = Column::new();
-            chars_column =
-                chars_column.push(checkbox(self.set.include_map[i]).on_toggle(setup_checked));
+                let mut chars_column: Column<'_, _> = Column::new();
+                chars_column =
+                    chars_column.push(checkbox(self.set.include_map[i]).on_toggle(setup_checked));
 
-            for v in &self.set.dictionary[i] {
-                chars_column = chars_column.push(
-                    container(text!("{}", v.0.clone().to_uppercase()).size(36))
-                        .padding(20)
+                for v in &kana_row.chars {
+                    chars_column = chars_column.push(
+                        container(
+                            iced::widget::column![
+                                text!("{}", v.0.clone()).size(36),
+                                accuracy_label(state.kana_stats.get(&v.0)),
+                            ]
+                            .align_x(alignment::Horizontal::Center),
+                        )
+                        .padding(iced::Padding::from([10, 20]))
                         .style(container::rounded_box),
+                    );
+                }
+
+                row = row.push(
+                    chars_column
+                        .spacing(DEFAULT_SPACING)
+                        .align_x(alignment::Horizontal::Center),
                 );
             }
 
-            row = row.push(
-                chars_column
-                    .spacing(DEFAULT_SPACING)
-                    .align_x(alignment::Horizontal::Center),
+            categories = categories.push(
+                iced::widget::column![
+                    checkbox(self.set.is_category_included(category))
+                        .label(category.title())
+                        .on_toggle(move |b| SelectorMessage::CheckCategory(category, b)),
+                    scrollable(row.spacing(DEFAULT_SPACING)).horizontal(),
+                ]
+                .spacing(DEFAULT_SPACING),
             );
         }
 
-        row.spacing(DEFAULT_SPACING).into()
+        scrollable(categories.spacing(DEFAULT_SPACING * 2.0))
+            .height(Fill)
+            .into()
     }
 }
+
+fn accuracy_label<'a>(stat: Option<&KanaStatistics>) -> Element<'a, SelectorMessage> {
+    let Some(stat) = stat else {
+        return text!("—").size(12).into();
+    };
+    let Some(accuracy) = stat.accuracy() else {
+        return text!("—").size(12).into();
+    };
+
+    let color = if accuracy >= 0.9 {
+        iced::Color::from_rgb8(60, 170, 60)
+    } else if accuracy >= 0.7 {
+        iced::Color::from_rgb8(220, 160, 0)
+    } else {
+        iced::Color::from_rgb8(255, 79, 0)
+    };
+
+    tooltip(
+        text!("{}%", (accuracy * 100.0).round())
+            .size(12)
+            .color(color),
+        container(text!(
+            "Попыток: {}\nОшибок: {}\nПодсказок: {}\nСреднее время: {} мс",
+            stat.attempts,
+            stat.errors,
+            stat.helps,
+            stat.average_time_ms().unwrap_or(0)
+        ))
+        .padding(5)
+        .style(container::rounded_box),
+        tooltip::Position::Bottom,
+    )
+    .into()
+}
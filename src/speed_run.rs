@@ -0,0 +1,246 @@
+use crate::data_provider::speed_runs::{load_runs, save_run};
+use crate::{AppState, DEFAULT_SPACING};
+use chrono::{DateTime, Utc};
+use iced::border::Radius;
+use iced::widget::container::Style;
+use iced::widget::{column, container, row, space, text, Row};
+use iced::Background::Color;
+use iced::{alignment, Border, Element, Theme};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TREND_LEN: usize = 10;
+const TREND_HEIGHT: f32 = 80.0;
+
+/// When a timed run ends. `Off` keeps the quiz an endless practice loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunLimit {
+    Off,
+    Seconds(u32),
+    Items(u32),
+}
+
+impl RunLimit {
+    pub const ALL: [RunLimit; 5] = [
+        RunLimit::Off,
+        RunLimit::Seconds(60),
+        RunLimit::Seconds(180),
+        RunLimit::Items(20),
+        RunLimit::Items(50),
+    ];
+
+    /// Runs are only compared with runs of the same limit.
+    pub fn key(&self) -> String {
+        match self {
+            RunLimit::Off => "off".to_string(),
+            RunLimit::Seconds(s) => format!("{}s", s),
+            RunLimit::Items(n) => format!("{}i", n),
+        }
+    }
+}
+
+impl Display for RunLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunLimit::Off => write!(f, "Без ограничения"),
+            RunLimit::Seconds(s) => write!(f, "{} секунд", s),
+            RunLimit::Items(n) => write!(f, "{} заданий", n),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RunRecord {
+    pub id: u32,
+    pub mode: String,
+    pub limit: String,
+    pub taken_at: DateTime<Utc>,
+    pub answers: u32,
+    pub correct: u32,
+    pub per_minute: f32,
+    pub accuracy: f32,
+    pub median_ms: i64,
+}
+
+/// A time-boxed run in progress. The clock starts with the first item shown.
+#[derive(Clone, Debug)]
+pub struct SpeedRun {
+    limit: RunLimit,
+    started: Option<Instant>,
+    response_times: Vec<Duration>,
+    correct: u32,
+}
+
+impl SpeedRun {
+    pub fn new(limit: RunLimit) -> Option<SpeedRun> {
+        if limit == RunLimit::Off {
+            return None;
+        }
+        Some(SpeedRun {
+            limit,
+            started: None,
+            response_times: vec![],
+            correct: 0,
+        })
+    }
+
+    pub fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    pub fn answer(&mut self, correct: bool, time: Duration) {
+        self.response_times.push(time);
+        if correct {
+            self.correct += 1;
+        }
+    }
+
+    /// A wrong answer accepted after all.
+    pub fn appeal(&mut self) {
+        if (self.correct as usize) < self.response_times.len() {
+            self.correct += 1;
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.limit {
+            RunLimit::Off => false,
+            RunLimit::Seconds(_) => self.remaining().is_some_and(|r| r.is_zero()),
+            RunLimit::Items(n) => self.response_times.len() >= n as usize,
+        }
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        let RunLimit::Seconds(seconds) = self.limit else {
+            return None;
+        };
+        let elapsed = self.started.map(|s| s.elapsed()).unwrap_or_default();
+        Some(Duration::from_secs(seconds as u64).saturating_sub(elapsed))
+    }
+
+    /// What is left, for the counter above the quiz.
+    pub fn progress(&self) -> String {
+        match self.limit {
+            RunLimit::Off => String::new(),
+            RunLimit::Seconds(_) => format!("{} с", self.remaining().unwrap_or_default().as_secs()),
+            RunLimit::Items(n) => format!("{} / {}", self.response_times.len(), n),
+        }
+    }
+
+    pub fn record(&self, mode: &str) -> RunRecord {
+        let elapsed = match self.limit {
+            RunLimit::Seconds(seconds) => Duration::from_secs(seconds as u64),
+            _ => self.started.map(|s| s.elapsed()).unwrap_or_default(),
+        };
+        let answers = self.response_times.len() as u32;
+        let minutes = elapsed.as_secs_f32() / 60.0;
+
+        let mut times = self.response_times.clone();
+        times.sort();
+        let median_ms = times
+            .get(times.len() / 2)
+            .map(|t| t.as_millis() as i64)
+            .unwrap_or(0);
+
+        RunRecord {
+            id: 0,
+            mode: mode.to_string(),
+            limit: self.limit.key(),
+            taken_at: Utc::now(),
+            answers,
+            correct: self.correct,
+            per_minute: if minutes > 0.0 {
+                answers as f32 / minutes
+            } else {
+                0.0
+            },
+            accuracy: if answers > 0 {
+                self.correct as f32 / answers as f32 * 100.0
+            } else {
+                0.0
+            },
+            median_ms,
+        }
+    }
+}
+
+/// Stores the finished run and loads the runs it is compared with.
+pub(crate) fn finish_run(
+    run: &SpeedRun,
+    mode: &str,
+    state: &Arc<Mutex<AppState>>,
+) -> (RunRecord, Vec<RunRecord>) {
+    let mut record = run.record(mode);
+    let state = state.lock().unwrap();
+    record.id = save_run(&record, &state.connection);
+    let history = load_runs(&record.mode, &record.limit, &state.connection);
+    (record, history)
+}
+
+/// Result of the finished run next to the personal best and the latest runs
+/// with the same limit. `history` is oldest first and includes `record`.
+pub fn summary_view<'a, M: 'a>(record: &RunRecord, history: &[RunRecord]) -> Element<'a, M> {
+    let best = history
+        .iter()
+        .max_by(|a, b| a.per_minute.total_cmp(&b.per_minute));
+    let is_best = best.is_some_and(|b| b.id == record.id);
+
+    let mut trend = Row::new().align_y(alignment::Vertical::Bottom);
+    let latest = &history[history.len().saturating_sub(TREND_LEN)..];
+    let top = latest.iter().map(|r| r.per_minute).fold(1.0f32, f32::max);
+    for run in latest {
+        let current = run.id == record.id;
+        trend = trend.push(
+            container(
+                space()
+                    .width(18)
+                    .height(TREND_HEIGHT * run.per_minute / top + 2.0),
+            )
+            .style(move |x: &Theme| Style {
+                text_color: None,
+                background: Some(Color(if current {
+                    x.palette().success
+                } else {
+                    x.palette().primary
+                })),
+                border: Border {
+                    color: Default::default(),
+                    width: 0.0,
+                    radius: Radius::new(3),
+                },
+                shadow: Default::default(),
+                snap: false,
+            }),
+        );
+    }
+
+    column![
+        text!("Забег окончен").size(34),
+        text!("{:.1} ответов в минуту", record.per_minute).size(24),
+        row![
+            text!("Точность {:.0}%", record.accuracy),
+            text!("Медиана {} мс", record.median_ms),
+            text!("Ответов {}", record.answers),
+        ]
+        .spacing(DEFAULT_SPACING),
+        text!(
+            "{}",
+            match (is_best, best) {
+                (true, _) => "Новый личный рекорд!".to_string(),
+                (false, Some(best)) => format!(
+                    "Личный рекорд: {:.1} в минуту ({})",
+                    best.per_minute,
+                    best.taken_at.format("%d.%m.%Y")
+                ),
+                (false, None) => String::new(),
+            }
+        ),
+        trend.spacing(4),
+    ]
+    .spacing(DEFAULT_SPACING)
+    .align_x(alignment::Horizontal::Center)
+    .into()
+}
@@ -0,0 +1,161 @@
+use crate::strokes::{partial_stroke, text_strokes, Stroke, GLYPH_SIZE};
+use iced::widget::canvas;
+use iced::widget::canvas::{Frame, Geometry, LineCap, LineDash, LineJoin, Path, Text};
+use iced::{mouse, Color, Point, Rectangle, Renderer, Size, Theme};
+
+/// How much of a stroke is drawn on every animation tick.
+const STROKES_PER_TICK: f32 = 0.06;
+
+/// Stroke-by-stroke guide for a kana or a few of them. `progress` counts
+/// strokes drawn: 2.5 is two strokes and half of the third.
+#[derive(Clone, Debug)]
+pub struct StrokeGuide {
+    strokes: Vec<Stroke>,
+    cells: usize,
+    progress: f32,
+}
+
+impl StrokeGuide {
+    pub fn new(text: &str) -> Option<StrokeGuide> {
+        let (strokes, cells) = text_strokes(text)?;
+        Some(StrokeGuide {
+            strokes,
+            cells,
+            progress: 0.0,
+        })
+    }
+
+    pub fn count(&self) -> usize {
+        self.strokes.len()
+    }
+
+    /// Number of the stroke being drawn, starting from one.
+    pub fn current(&self) -> usize {
+        (self.progress.ceil() as usize).min(self.count())
+    }
+
+    pub fn replay(&mut self) {
+        self.progress = 0.0;
+    }
+
+    pub fn step_forward(&mut self) {
+        self.progress = (self.progress.floor() + 1.0).min(self.count() as f32);
+    }
+
+    pub fn step_back(&mut self) {
+        self.progress = (self.progress.ceil() - 1.0).max(0.0);
+    }
+
+    pub fn advance(&mut self) {
+        self.progress = (self.progress + STROKES_PER_TICK).min(self.count() as f32);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress >= self.count() as f32
+    }
+
+    pub fn view<'a, M: 'a>(&'a self, height: f32) -> canvas::Canvas<&'a StrokeGuide, M> {
+        canvas(self)
+            .width(height * self.cells as f32)
+            .height(height)
+    }
+}
+
+/// Draws the cell grid of a genkō-yōshi square: the outline and dashed center lines.
+pub(crate) fn draw_cells(frame: &mut Frame, cells: usize, scale: f32, color: Color) {
+    for cell in 0..cells {
+        let left = cell as f32 * GLYPH_SIZE * scale;
+        let side = GLYPH_SIZE * scale;
+        frame.stroke(
+            &Path::rectangle(Point::new(left, 0.0), Size::new(side, side)),
+            canvas::Stroke::default().with_color(color).with_width(1.0),
+        );
+
+        let center = side / 2.0;
+        let cross = Path::new(|b| {
+            b.move_to(Point::new(left + center, 0.0));
+            b.line_to(Point::new(left + center, side));
+            b.move_to(Point::new(left, center));
+            b.line_to(Point::new(left + side, center));
+        });
+        frame.stroke(
+            &cross,
+            canvas::Stroke {
+                line_dash: LineDash {
+                    segments: &[4.0, 4.0],
+                    offset: 0,
+                },
+                ..canvas::Stroke::default().with_color(color).with_width(1.0)
+            },
+        );
+    }
+}
+
+pub(crate) fn polyline(stroke: &[Point], scale: f32) -> Path {
+    Path::new(|b| {
+        if let Some(first) = stroke.first() {
+            b.move_to(Point::new(first.x * scale, first.y * scale));
+        }
+        for p in stroke.iter().skip(1) {
+            b.line_to(Point::new(p.x * scale, p.y * scale));
+        }
+    })
+}
+
+pub(crate) fn pen(color: Color, width: f32) -> canvas::Stroke<'static> {
+    canvas::Stroke::default()
+        .with_color(color)
+        .with_width(width)
+        .with_line_cap(LineCap::Round)
+        .with_line_join(LineJoin::Round)
+}
+
+impl<M> canvas::Program<M> for StrokeGuide {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let scale = (bounds.width / (GLYPH_SIZE * self.cells as f32)).min(bounds.height / GLYPH_SIZE);
+        let palette = theme.extended_palette();
+
+        draw_cells(&mut frame, self.cells, scale, palette.background.strong.color);
+
+        for stroke in &self.strokes {
+            frame.stroke(
+                &polyline(stroke, scale),
+                pen(palette.background.weak.color, 6.0 * scale),
+            );
+        }
+
+        for (i, stroke) in self.strokes.iter().enumerate() {
+            let part = self.progress - i as f32;
+            if part <= 0.0 {
+                break;
+            }
+            let color = if part < 1.0 {
+                palette.primary.base.color
+            } else {
+                palette.background.base.text
+            };
+            frame.stroke(&polyline(&partial_stroke(stroke, part), scale), pen(color, 4.0 * scale));
+
+            let start = stroke[0];
+            frame.fill_text(Text {
+                content: (i + 1).to_string(),
+                position: Point::new(start.x * scale - 10.0 * scale, start.y * scale - 10.0 * scale),
+                color: palette.danger.base.color,
+                size: (12.0 * scale).into(),
+                ..Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
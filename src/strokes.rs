@@ -0,0 +1,265 @@
+use crate::dictionary::app_data_dir;
+use iced::Point;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+/// Side of the square the strokes are drawn in, the same as in KanjiVG.
+pub const GLYPH_SIZE: f32 = 109.0;
+const CURVE_STEPS: usize = 12;
+
+const BUNDLED: &str = include_str!("../assets/kana_strokes.txt");
+
+/// A stroke as a polyline, from where the pen touches the paper to where it leaves.
+pub type Stroke = Vec<Point>;
+
+static STROKES: LazyLock<HashMap<char, Vec<Stroke>>> = LazyLock::new(|| {
+    BUNDLED
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (kana, paths) = line.split_once(' ')?;
+            Some((kana.chars().next()?, parse_strokes(paths)))
+        })
+        .collect()
+});
+
+/// Strokes of a single kana in writing order. KanjiVG files put into
+/// `<data dir>/kanjivg` (`03042.svg` for あ) take precedence over the bundled set.
+pub fn kana_strokes(kana: char) -> Option<Vec<Stroke>> {
+    load_kanjivg(kana).or_else(|| STROKES.get(&kana).cloned())
+}
+
+/// Folder the KanjiVG files are read from.
+pub fn kanjivg_dir() -> PathBuf {
+    app_data_dir().join("kanjivg")
+}
+
+/// Strokes of a kana string laid out in a row of cells, with the number of cells.
+pub fn text_strokes(text: &str) -> Option<(Vec<Stroke>, usize)> {
+    let mut result = vec![];
+    let mut cells = 0;
+    for kana in text.chars() {
+        let strokes = kana_strokes(kana)?;
+        result.extend(transform(strokes, 1.0, cells as f32 * GLYPH_SIZE, 0.0));
+        cells += 1;
+    }
+    if result.is_empty() {
+        return None;
+    }
+    Some((result, cells))
+}
+
+fn transform(strokes: Vec<Stroke>, scale: f32, dx: f32, dy: f32) -> Vec<Stroke> {
+    strokes
+        .into_iter()
+        .map(|stroke| {
+            stroke
+                .into_iter()
+                .map(|p| Point::new(p.x * scale + dx, p.y * scale + dy))
+                .collect()
+        })
+        .collect()
+}
+
+fn parse_strokes(paths: &str) -> Vec<Stroke> {
+    paths
+        .split('|')
+        .map(|path| parse_path(path.trim()))
+        .filter(|stroke| !stroke.is_empty())
+        .collect()
+}
+
+fn load_kanjivg(kana: char) -> Option<Vec<Stroke>> {
+    let file = kanjivg_dir().join(format!("{:05x}.svg", kana as u32));
+    let svg = fs::read_to_string(file).ok()?;
+
+    let mut strokes = vec![];
+    for element in svg.split("<path").skip(1) {
+        let Some(start) = element.find(" d=\"") else {
+            continue;
+        };
+        let data = &element[start + 4..];
+        let Some(end) = data.find('"') else {
+            continue;
+        };
+        strokes.push(parse_path(&data[..end]));
+    }
+    if strokes.is_empty() {
+        return None;
+    }
+    Some(strokes)
+}
+
+#[derive(Clone, Copy)]
+enum Token {
+    Command(char),
+    Number(f32),
+}
+
+fn tokenize(data: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut number = String::new();
+    let flush = |number: &mut String, tokens: &mut Vec<Token>| {
+        if let Ok(value) = number.parse() {
+            tokens.push(Token::Number(value));
+        }
+        number.clear();
+    };
+
+    for c in data.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            // "1.5.5" is two numbers and "3-2" is "3 -2"
+            '.' if number.contains('.') => {
+                flush(&mut number, &mut tokens);
+                number.push(c);
+            }
+            '.' => number.push(c),
+            '-' => {
+                flush(&mut number, &mut tokens);
+                number.push(c);
+            }
+            c if c.is_ascii_alphabetic() => {
+                flush(&mut number, &mut tokens);
+                tokens.push(Token::Command(c));
+            }
+            _ => flush(&mut number, &mut tokens),
+        }
+    }
+    flush(&mut number, &mut tokens);
+    tokens
+}
+
+/// Flattens an SVG path (M, L, C, S, Q and their relative forms) to a polyline.
+fn parse_path(data: &str) -> Stroke {
+    let tokens = tokenize(data);
+    let mut points = vec![];
+    let mut current = Point::ORIGIN;
+    let mut last_control: Option<Point> = None;
+    let mut command = 'M';
+    let mut i = 0;
+
+    while i < tokens.len() {
+        if let Token::Command(c) = tokens[i] {
+            command = c;
+            i += 1;
+            continue;
+        }
+
+        let count = match command.to_ascii_uppercase() {
+            'M' | 'L' => 2,
+            'S' | 'Q' => 4,
+            'C' => 6,
+            _ => break,
+        };
+        let args = tokens[i..]
+            .iter()
+            .take(count)
+            .map_while(|t| match t {
+                Token::Number(n) => Some(*n),
+                Token::Command(_) => None,
+            })
+            .collect::<Vec<f32>>();
+        if args.len() < count {
+            break;
+        }
+        i += count;
+
+        let relative = command.is_ascii_lowercase();
+        let origin = current;
+        let at = |k: usize| {
+            if relative {
+                Point::new(origin.x + args[k], origin.y + args[k + 1])
+            } else {
+                Point::new(args[k], args[k + 1])
+            }
+        };
+
+        match command.to_ascii_uppercase() {
+            'M' => {
+                current = at(0);
+                points.push(current);
+                last_control = None;
+                // further pairs after a move are lines
+                command = if relative { 'l' } else { 'L' };
+            }
+            'L' => {
+                current = at(0);
+                points.push(current);
+                last_control = None;
+            }
+            'C' => {
+                let (c1, c2, end) = (at(0), at(2), at(4));
+                cubic(&mut points, current, c1, c2, end);
+                last_control = Some(c2);
+                current = end;
+            }
+            'S' => {
+                let c1 = match last_control {
+                    Some(c) => Point::new(2.0 * current.x - c.x, 2.0 * current.y - c.y),
+                    None => current,
+                };
+                let (c2, end) = (at(0), at(2));
+                cubic(&mut points, current, c1, c2, end);
+                last_control = Some(c2);
+                current = end;
+            }
+            _ => {
+                let (control, end) = (at(0), at(2));
+                let c1 = Point::new(
+                    current.x + 2.0 / 3.0 * (control.x - current.x),
+                    current.y + 2.0 / 3.0 * (control.y - current.y),
+                );
+                let c2 = Point::new(
+                    end.x + 2.0 / 3.0 * (control.x - end.x),
+                    end.y + 2.0 / 3.0 * (control.y - end.y),
+                );
+                cubic(&mut points, current, c1, c2, end);
+                last_control = None;
+                current = end;
+            }
+        }
+    }
+
+    points
+}
+
+fn cubic(points: &mut Vec<Point>, p0: Point, p1: Point, p2: Point, p3: Point) {
+    for step in 1..=CURVE_STEPS {
+        let t = step as f32 / CURVE_STEPS as f32;
+        let u = 1.0 - t;
+        let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+        points.push(Point::new(
+            a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+            a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+        ));
+    }
+}
+
+/// Length of a polyline, used to animate strokes at an even pace.
+pub fn stroke_length(stroke: &[Point]) -> f32 {
+    stroke.windows(2).map(|w| w[0].distance(w[1])).sum()
+}
+
+/// The first `fraction` of a polyline by length.
+pub fn partial_stroke(stroke: &[Point], fraction: f32) -> Stroke {
+    let target = stroke_length(stroke) * fraction.clamp(0.0, 1.0);
+    let mut result = stroke.iter().take(1).cloned().collect::<Stroke>();
+    let mut walked = 0.0;
+    for w in stroke.windows(2) {
+        let segment = w[0].distance(w[1]);
+        if walked + segment >= target {
+            let t = if segment > 0.0 { (target - walked) / segment } else { 0.0 };
+            result.push(Point::new(
+                w[0].x + (w[1].x - w[0].x) * t,
+                w[0].y + (w[1].y - w[0].y) * t,
+            ));
+            return result;
+        }
+        walked += segment;
+        result.push(w[1]);
+    }
+    result
+}
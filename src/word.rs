@@ -1,25 +1,57 @@
+use crate::cloze::{example_lines, EXAMPLES};
+use crate::data_provider::jmdict::lookup_jmdict;
+use crate::data_provider::settings::save_setting;
 use crate::data_provider::words::{delete_word, update_word};
+use crate::dictionary::SUGGESTIONS;
+use crate::fields::{field_errors, field_input, word_fields, FieldKind, FieldSpec};
+use crate::jmdict::{fill_word, suggestions_view, GlossLanguage, JmdictEntry, GLOSS_LANGUAGE};
+use crate::kana_input::{kana_input, mode_toggle, KanaInputMode};
+use crate::kanji::kanji_in;
+use crate::kanji_dictionary::{kanji_details, KanjiDictionaryState};
 use crate::lang::WordData;
+use crate::media::{image_names, import_image, set_image_names, thumbnail, IMAGES};
+use crate::pitch::{
+    accent_reading, find_accent, pitch_line, read_accent_file, validate_accent, ACCENT,
+};
 use crate::Page::PreviousPage;
 use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
 use iced::widget::button::danger;
-use iced::widget::{button, column, container, row, rule, scrollable, space, text, text_input};
+use iced::widget::{
+    button, column, container, pick_list, row, rule, scrollable, space, text, text_editor,
+    text_input,
+};
 use iced::{Element, Fill, Task};
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use tokio::task::spawn_blocking;
 
 #[derive(Clone)]
 pub struct WordState {
     state: Arc<Mutex<AppState>>,
     index: usize,
     word: WordData,
+    kana_mode: KanaInputMode,
+    accent_status: String,
+    image_path: String,
+    image_status: String,
+    /// Fields of the word's group.
+    fields: Vec<FieldSpec>,
+    /// Editors of the multiline fields.
+    editors: HashMap<String, text_editor::Content>,
+    /// JMdict entries for the key as typed.
+    suggestions: Vec<JmdictEntry>,
+    gloss_language: GlossLanguage,
 }
 
 impl NavigatedPage<WordMessage> for WordState {
     fn navigate(&self, message: &WordMessage) -> Option<Page> {
-        if let WordMessage::Back = message {
-            Some(PreviousPage)
-        } else {
-            None
+        match message {
+            WordMessage::Back => Some(PreviousPage),
+            WordMessage::SearchComponent(component) => Some(Page::KanjiDictionary(
+                KanjiDictionaryState::for_component(component.clone(), self.state.clone()),
+            )),
+            _ => None,
         }
     }
 }
@@ -30,7 +62,35 @@ impl WordState {
         index: usize,
         state: Arc<Mutex<AppState>>,
     ) -> WordState {
-        WordState { state, index, word }
+        let (kana_mode, fields, gloss_language) = {
+            let state = state.lock().unwrap();
+            (
+                state.kana_input,
+                word_fields(&word, &state.word_groups).to_vec(),
+                state.gloss_language,
+            )
+        };
+        let editors = fields
+            .iter()
+            .filter(|spec| spec.kind == FieldKind::Multiline)
+            .map(|spec| {
+                let value = word.additional.get(&spec.name).map_or("", |v| v);
+                (spec.name.clone(), text_editor::Content::with_text(value))
+            })
+            .collect();
+        WordState {
+            state,
+            index,
+            word,
+            kana_mode,
+            accent_status: String::new(),
+            image_path: String::new(),
+            image_status: String::new(),
+            fields,
+            editors,
+            suggestions: vec![],
+            gloss_language,
+        }
     }
 }
 
@@ -39,6 +99,10 @@ impl WordState {
         match message {
             WordMessage::Back => {}
             WordMessage::Save => {
+                self.word.key = self.kana_mode.finish(&self.word.key);
+                if let Some(reading) = self.word.additional.get_mut("reading") {
+                    *reading = self.kana_mode.finish(reading);
+                }
                 let mut state = self.state.lock().unwrap();
                 state.dictionary[self.index] = self.word.clone();
                 update_word(&mut self.word, &state.connection);
@@ -52,8 +116,23 @@ impl WordState {
             }
             WordMessage::SetTags(n) => self.word.tags = n,
             WordMessage::SetKey(n) => {
+                let connection = &self.state.lock().unwrap().connection;
+                self.suggestions = lookup_jmdict(n.trim(), SUGGESTIONS, connection);
                 self.word.key = n;
             }
+            WordMessage::FillFromJmdict(i) => {
+                if let Some(entry) = self.suggestions.get(i) {
+                    let typed = self.word.key.trim().to_string();
+                    fill_word(&mut self.word, entry, &typed, self.gloss_language);
+                    self.suggestions.clear();
+                }
+            }
+            WordMessage::SetGlossLanguage(language) => {
+                self.gloss_language = language;
+                let state = &mut *self.state.lock().unwrap();
+                state.gloss_language = language;
+                save_setting(GLOSS_LANGUAGE, &language.code, &state.connection);
+            }
             WordMessage::SetValue(n) => {
                 self.word.value = n;
             }
@@ -63,9 +142,87 @@ impl WordState {
 
                 }
             },
+            WordMessage::EditField(key, action) => {
+                if let Some(content) = self.editors.get_mut(&key) {
+                    content.perform(action);
+                    let value = content.text().trim_end_matches('\n').to_string();
+                    self.word.additional.insert(key, value);
+                }
+            }
             WordMessage::AddAdditional(key) => {
                 self.word.additional.insert(key, "".to_string());
             },
+            WordMessage::SetExample(index, line) => {
+                let mut lines = example_lines(&self.word);
+                if let Some(old) = lines.get_mut(index) {
+                    *old = line.replace('\n', " ");
+                }
+                self.set_examples(lines);
+            }
+            WordMessage::AddExample => {
+                let mut lines = example_lines(&self.word);
+                lines.push(String::new());
+                self.set_examples(lines);
+            }
+            WordMessage::RemoveExample(index) => {
+                let mut lines = example_lines(&self.word);
+                if index < lines.len() {
+                    lines.remove(index);
+                }
+                self.set_examples(lines);
+            }
+            WordMessage::FillAccent => {
+                self.accent_status = "Поиск…".to_string();
+                let word = self.word.clone();
+                return Task::perform(
+                    async {
+                        spawn_blocking(move || {
+                            read_accent_file().map(|dictionary| find_accent(&word, &dictionary))
+                        })
+                        .await
+                        .unwrap()
+                    },
+                    |found| RootMessage::Word(WordMessage::AccentFound(found)),
+                );
+            }
+            WordMessage::AccentFound(found) => {
+                self.accent_status = match found {
+                    Ok(Some((reading, accent))) => {
+                        if accent_reading(&self.word).is_empty() {
+                            self.word.additional.insert("reading".to_string(), reading);
+                        }
+                        self.word.additional.insert(ACCENT.to_string(), accent);
+                        String::new()
+                    }
+                    Ok(None) => "Слова нет в словаре ударений".to_string(),
+                    Err(e) => e,
+                };
+            }
+            WordMessage::SetImagePath(path) => self.image_path = path,
+            WordMessage::AttachImage => {
+                match import_image(Path::new(self.image_path.trim().trim_matches('"'))) {
+                    Ok(name) => {
+                        let mut names = image_names(&self.word);
+                        if !names.contains(&name) {
+                            names.push(name);
+                        }
+                        set_image_names(&mut self.word, &names);
+                        self.image_path.clear();
+                        self.image_status.clear();
+                    }
+                    Err(e) => self.image_status = e,
+                }
+            }
+            WordMessage::RemoveImage(name) => {
+                let mut names = image_names(&self.word);
+                names.retain(|n| *n != name);
+                set_image_names(&mut self.word, &names);
+            }
+            WordMessage::SearchComponent(_) => {}
+            WordMessage::SetKanaMode(mode) => {
+                self.kana_mode = mode;
+                self.state.lock().unwrap().kana_input = mode;
+            }
         }
         Task::none()
     }
@@ -78,10 +235,24 @@ impl WordState {
         if !self.word.additional.contains_key("description") {
             fast_add = fast_add.push(button("Описание").style(button::text).on_press(WordMessage::AddAdditional("description".to_string())));
         }
+        if !self.word.additional.contains_key(ACCENT) {
+            fast_add = fast_add.push(button("Ударение").style(button::text).on_press(WordMessage::AddAdditional(ACCENT.to_string())));
+        }
+        if !self.word.additional.contains_key(IMAGES) {
+            fast_add = fast_add.push(button("Картинки").style(button::text).on_press(WordMessage::AddAdditional(IMAGES.to_string())));
+        }
+        if !self.word.additional.contains_key(EXAMPLES) {
+            fast_add = fast_add.push(button("Примеры").style(button::text).on_press(WordMessage::AddExample));
+        }
         let mut col = iced::widget::column![
             button("Назад").on_press(WordMessage::Back),
-            text!("Ключ"),
-            text_input("key", &self.word.key).on_input(WordMessage::SetKey),
+            row![
+                text!("Ключ").width(Fill),
+                mode_toggle(self.kana_mode, WordMessage::SetKanaMode),
+            ]
+            .align_y(iced::Center),
+            kana_input("key", &self.word.key, self.kana_mode, WordMessage::SetKey),
+            self.suggestions_panel(),
             text!("Значение"),
             text_input("value", &self.word.value).on_input(WordMessage::SetValue),
             text!("Теги"),
@@ -90,14 +261,34 @@ impl WordState {
             scrollable(fast_add),
         ];
 
-        for more in &self.word.additional {
-            col = col.push(self.get_view_for_more(more));
+        // the group's fields in their order, then whatever else the word has
+        let mut names = self.fields.iter().map(|f| f.name.clone()).collect::<Vec<String>>();
+        let mut other = self
+            .word
+            .additional
+            .keys()
+            .filter(|key| !names.contains(key))
+            .cloned()
+            .collect::<Vec<String>>();
+        other.sort();
+        names.extend(other);
+        for name in names {
+            let value = self.word.additional.get(&name).cloned().unwrap_or_default();
+            col = col.push(self.get_view_for_more((&name, &value)));
+        }
+        for error in field_errors(&self.word, &self.fields) {
+            col = col.push(text!("{}", error).style(text::danger));
         }
+        col = col.push(self.kanji_breakdown());
         container(
             column![
                 col.spacing(DEFAULT_SPACING).width(Fill).height(Fill),
                 row![
-                    button("Сохранить").on_press(WordMessage::Save),
+                    button("Сохранить").on_press_maybe(
+                        (self.accent_error().is_none()
+                            && field_errors(&self.word, &self.fields).is_empty())
+                        .then_some(WordMessage::Save)
+                    ),
                     button("Удалить")
                         .style(danger)
                         .on_press(WordMessage::Delete),
@@ -110,23 +301,187 @@ impl WordState {
         .into()
     }
 
+    /// JMdict entries for the key, filling the word on a click.
+    fn suggestions_panel(&self) -> Element<'_, WordMessage> {
+        if self.suggestions.is_empty() {
+            return space().into();
+        }
+        column![
+            row![
+                text!("JMdict").width(Fill),
+                pick_list(
+                    GlossLanguage::ALL,
+                    Some(self.gloss_language),
+                    WordMessage::SetGlossLanguage
+                ),
+            ]
+            .align_y(iced::Center),
+            scrollable(suggestions_view(
+                &self.suggestions,
+                self.word.key.trim(),
+                self.gloss_language,
+                WordMessage::FillFromJmdict
+            ))
+            .height(150),
+        ]
+        .spacing(5)
+        .into()
+    }
+
     fn get_view_for_more(&self, value: (&String, &String)) -> Element<'_, WordMessage> {
         match value.0.as_str() {
             "reading" => self.reading_field(value),
             "description" => self.description_field(value),
-            _ => space().into(),
+            EXAMPLES => self.examples_field(),
+            ACCENT => self.accent_field(value.1),
+            IMAGES => self.images_field(),
+            _ => self.custom_field(value),
+        }
+    }
+
+    /// A field of the group's schema, or any other field the word has.
+    fn custom_field(&self, value: (&String, &String)) -> Element<'_, WordMessage> {
+        let key = value.0.clone();
+        let Some(spec) = self.fields.iter().find(|f| f.name == *value.0) else {
+            return column![
+                text!("{}", key),
+                text_input(&key, value.1)
+                    .on_input(move |string| WordMessage::SetAdditional(key.clone(), string)),
+            ]
+            .spacing(DEFAULT_SPACING)
+            .into();
+        };
+
+        let label = text!(
+            "{}{} ({})",
+            spec.name,
+            if spec.required { " *" } else { "" },
+            spec.kind
+        );
+        let input = match self.editors.get(&spec.name) {
+            Some(content) => text_editor(content)
+                .on_action(move |action| WordMessage::EditField(key.clone(), action))
+                .height(120)
+                .into(),
+            None => field_input(spec, value.1, self.kana_mode, move |string| {
+                WordMessage::SetAdditional(key.clone(), string)
+            }),
+        };
+        column![label, input].spacing(DEFAULT_SPACING).into()
+    }
+
+    fn kanji_breakdown(&self) -> Element<'_, WordMessage> {
+        let state = self.state.lock().unwrap();
+        let kanji = kanji_in(&self.word.key, &state.kanji);
+        if kanji.is_empty() {
+            return space().into();
+        }
+
+        let mut breakdown = column![rule::horizontal(2), text!("Кандзи")].spacing(DEFAULT_SPACING);
+        for k in kanji {
+            breakdown = breakdown.push(kanji_details(k, WordMessage::SearchComponent));
         }
+        scrollable(breakdown).into()
     }
 
     fn reading_field(&self, value: (&String, &String)) -> Element<'_, WordMessage> {
         column![
             text!("Чтение слова"),
-            text_input("reading", &value.1)
-                .on_input(|string| WordMessage::SetAdditional("reading".to_string(), string))
+            kana_input("reading", value.1, self.kana_mode, |string| {
+                WordMessage::SetAdditional("reading".to_string(), string)
+            })
         ].spacing(DEFAULT_SPACING)
         .into()
     }
 
+    /// Thumbnails of the attached pictures and a path to attach another one.
+    fn images_field(&self) -> Element<'_, WordMessage> {
+        let mut thumbnails = row![].spacing(DEFAULT_SPACING);
+        for name in image_names(&self.word) {
+            thumbnails = thumbnails.push(
+                column![
+                    thumbnail(&name, 120.0),
+                    button("Убрать")
+                        .style(button::text)
+                        .on_press(WordMessage::RemoveImage(name.clone())),
+                ]
+                .align_x(iced::Center),
+            );
+        }
+        column![
+            text!("Картинки"),
+            scrollable(thumbnails).horizontal(),
+            row![
+                text_input("Путь к файлу картинки", &self.image_path)
+                    .on_input(WordMessage::SetImagePath)
+                    .on_submit(WordMessage::AttachImage),
+                button("Прикрепить")
+                    .style(button::secondary)
+                    .on_press(WordMessage::AttachImage),
+            ]
+            .spacing(5),
+            text!("{}", self.image_status),
+        ]
+        .spacing(DEFAULT_SPACING)
+        .into()
+    }
+
+    fn accent_error(&self) -> Option<String> {
+        let accent = self.word.additional.get(ACCENT)?;
+        validate_accent(accent, &accent_reading(&self.word)).err()
+    }
+
+    /// Downstep number or H/L pattern, checked against the reading and drawn.
+    fn accent_field(&self, accent: &str) -> Element<'_, WordMessage> {
+        let check: Element<'_, WordMessage> = match (self.accent_error(), pitch_line(&self.word)) {
+            (Some(error), _) => text!("{}", error).style(text::danger).into(),
+            (None, Some(line)) => line.view(24.0),
+            (None, None) => space().into(),
+        };
+        column![
+            text!("Ударение: мора перед понижением (0 — без него) или узор из H и L"),
+            row![
+                text_input("0", accent)
+                    .on_input(|string| WordMessage::SetAdditional(ACCENT.to_string(), string)),
+                button("Из словаря ударений")
+                    .style(button::secondary)
+                    .on_press(WordMessage::FillAccent),
+            ]
+            .spacing(5),
+            text!("{}", self.accent_status),
+            check,
+        ]
+        .spacing(DEFAULT_SPACING)
+        .into()
+    }
+
+    /// Example sentences, each with an optional translation after `|`.
+    fn examples_field(&self) -> Element<'_, WordMessage> {
+        let mut col = column![text!("Примеры предложений (перевод после |)")].spacing(5);
+        for (i, line) in example_lines(&self.word).into_iter().enumerate() {
+            col = col.push(
+                row![
+                    text_input("例文 | перевод", &line)
+                        .on_input(move |string| WordMessage::SetExample(i, string)),
+                    button("×")
+                        .style(button::text)
+                        .on_press(WordMessage::RemoveExample(i)),
+                ]
+                .spacing(5),
+            );
+        }
+        col.push(
+            button("Добавить пример")
+                .style(button::text)
+                .on_press(WordMessage::AddExample),
+        )
+        .into()
+    }
+
+    fn set_examples(&mut self, lines: Vec<String>) {
+        self.word.additional.insert(EXAMPLES.to_string(), lines.join("\n"));
+    }
+
     fn description_field(&self, value: (&String, &String)) -> Element<'_, WordMessage> {
         column![
             text!("Описание"),
@@ -145,4 +500,17 @@ pub enum WordMessage {
     SetValue(String),
     AddAdditional(String),
     SetAdditional(String, String),
+    EditField(String, text_editor::Action),
+    FillFromJmdict(usize),
+    SetGlossLanguage(GlossLanguage),
+    SearchComponent(String),
+    SetKanaMode(KanaInputMode),
+    FillAccent,
+    SetImagePath(String),
+    AttachImage,
+    RemoveImage(String),
+    AccentFound(Result<Option<(String, String)>, String>),
+    SetExample(usize, String),
+    AddExample,
+    RemoveExample(usize),
 }
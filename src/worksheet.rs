@@ -0,0 +1,276 @@
+use crate::dictionary::app_data_dir;
+use crate::furigana::kana_reading;
+use crate::lang::{KanaSet, KanaType, WordData, WordGroup};
+use crate::strokes::{text_strokes, Stroke, GLYPH_SIZE};
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+// A4 in millimetres
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const TOP: f32 = 30.0;
+
+const CELL: f32 = 15.0;
+const CELLS_PER_ROW: usize = 12;
+const KANA_ROWS_PER_PAGE: usize = 16;
+
+const WORD_ROW: f32 = 10.0;
+const WORD_ROWS_PER_PAGE: usize = 25;
+/// Widths of the №, word, reading and answer columns.
+const WORD_COLUMNS: [f32; 4] = [10.0, 55.0, 45.0, 70.0];
+
+const FONT: &str = "Noto Sans JP, sans-serif";
+
+/// Genkō-yōshi practice sheets for the enabled kana: a model with numbered
+/// strokes, a few copies to trace over and empty cells. One SVG file per page.
+pub fn kana_worksheet(set: &KanaSet) -> std::io::Result<Vec<PathBuf>> {
+    let list = set.list();
+    let name = match set.chars_type {
+        KanaType::Hiragana => "hiragana",
+        KanaType::Katakana => "katakana",
+    };
+
+    remove_pages(&format!("kana-{}-", name))?;
+    let mut files = vec![];
+    for (page, chunk) in list.chunks(KANA_ROWS_PER_PAGE).enumerate() {
+        let mut body = title(&format!("Прописи: {}", set.name()));
+        for (row, (kana, roman)) in chunk.iter().enumerate() {
+            let y = TOP + row as f32 * (CELL + 2.0);
+            kana_row(&mut body, kana, roman, y);
+        }
+        files.push(save(&format!("kana-{}-{}.svg", name, page + 1), &body)?);
+    }
+    Ok(files)
+}
+
+/// Vocabulary sheets for a word group: word, reading and an empty column to
+/// fill in, followed by the same pages with the answers.
+pub fn vocabulary_worksheet(group: &WordGroup, words: &[WordData]) -> std::io::Result<Vec<PathBuf>> {
+    remove_pages(&format!("words-{}-", group.id))?;
+    let mut files = vec![];
+    for with_answers in [false, true] {
+        for (page, chunk) in words.chunks(WORD_ROWS_PER_PAGE).enumerate() {
+            let heading = if with_answers {
+                format!("{} — ответы", group.name)
+            } else {
+                group.name.clone()
+            };
+            let mut body = title(&heading);
+            word_table(&mut body, chunk, page * WORD_ROWS_PER_PAGE, with_answers);
+
+            let suffix = if with_answers { "-key" } else { "" };
+            let file = format!("words-{}{}-{}.svg", group.id, suffix, page + 1);
+            files.push(save(&file, &body)?);
+        }
+    }
+    Ok(files)
+}
+
+pub fn worksheet_dir() -> PathBuf {
+    app_data_dir().join("worksheets")
+}
+
+/// Pages left from an earlier, longer sheet would be printed along with the new one.
+fn remove_pages(prefix: &str) -> std::io::Result<()> {
+    let Ok(entries) = fs::read_dir(worksheet_dir()) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(prefix) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+fn save(file: &str, body: &str) -> std::io::Result<PathBuf> {
+    let dir = worksheet_dir();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(file);
+    fs::write(&path, page(body))?;
+    Ok(path)
+}
+
+fn page(body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="{w}mm" height="{h}mm" viewBox="0 0 {w} {h}">
+<rect width="{w}" height="{h}" fill="white"/>
+{body}</svg>
+"#,
+        w = PAGE_WIDTH,
+        h = PAGE_HEIGHT,
+    )
+}
+
+fn title(text: &str) -> String {
+    format!(
+        "<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"7\">{}</text>\n",
+        MARGIN,
+        MARGIN + 5.0,
+        FONT,
+        escape(text)
+    )
+}
+
+fn kana_row(body: &mut String, kana: &str, roman: &str, y: f32) {
+    let _ = writeln!(
+        body,
+        "<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"3\" fill=\"#888888\">{}</text>",
+        MARGIN - 1.0,
+        y - 0.8,
+        FONT,
+        escape(roman)
+    );
+
+    let strokes = text_strokes(kana);
+    let width = strokes.as_ref().map(|(_, cells)| *cells).unwrap_or(1);
+    let units = CELLS_PER_ROW / width;
+    let traced = units / 3;
+
+    for unit in 0..units {
+        let x = MARGIN + (unit * width) as f32 * CELL;
+        for cell in 0..width {
+            grid_cell(body, x + cell as f32 * CELL, y);
+        }
+
+        let color = match unit {
+            0 => "#000000",
+            u if u <= traced => "#bbbbbb",
+            _ => continue,
+        };
+        match &strokes {
+            Some((strokes, _)) => {
+                draw_strokes(body, strokes, x, y, color);
+                if unit == 0 {
+                    stroke_numbers(body, strokes, x, y);
+                }
+            }
+            None => {
+                let _ = writeln!(
+                    body,
+                    "<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"{}\" fill=\"{}\" text-anchor=\"middle\">{}</text>",
+                    x + CELL * width as f32 / 2.0,
+                    y + CELL * 0.8,
+                    FONT,
+                    CELL * 0.8,
+                    color,
+                    escape(kana)
+                );
+            }
+        }
+    }
+}
+
+fn grid_cell(body: &mut String, x: f32, y: f32) {
+    let half = CELL / 2.0;
+    let _ = writeln!(
+        body,
+        "<rect x=\"{x}\" y=\"{y}\" width=\"{CELL}\" height=\"{CELL}\" fill=\"none\" stroke=\"#d08080\" stroke-width=\"0.3\"/>\n\
+         <path d=\"M{} {y} V{} M{x} {} H{}\" stroke=\"#e8b0b0\" stroke-width=\"0.2\" stroke-dasharray=\"1 1\"/>",
+        x + half,
+        y + CELL,
+        y + half,
+        x + CELL,
+    );
+}
+
+fn draw_strokes(body: &mut String, strokes: &[Stroke], x: f32, y: f32, color: &str) {
+    let scale = CELL / GLYPH_SIZE;
+    let mut data = String::new();
+    for stroke in strokes {
+        for (i, p) in stroke.iter().enumerate() {
+            let command = if i == 0 { 'M' } else { 'L' };
+            let _ = write!(data, "{}{:.2} {:.2} ", command, x + p.x * scale, y + p.y * scale);
+        }
+    }
+    let _ = writeln!(
+        body,
+        "<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"0.6\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+        data.trim_end(),
+        color
+    );
+}
+
+fn stroke_numbers(body: &mut String, strokes: &[Stroke], x: f32, y: f32) {
+    let scale = CELL / GLYPH_SIZE;
+    for (i, stroke) in strokes.iter().enumerate() {
+        let start = stroke[0];
+        let _ = writeln!(
+            body,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"{}\" font-size=\"1.8\" fill=\"#d02020\">{}</text>",
+            x + start.x * scale - 1.5,
+            y + start.y * scale - 0.3,
+            FONT,
+            i + 1
+        );
+    }
+}
+
+fn word_table(body: &mut String, words: &[WordData], first_number: usize, with_answers: bool) {
+    let headers = ["№", "Слово", "Чтение", "Перевод"];
+    let mut x = MARGIN;
+    for (header, width) in headers.iter().zip(WORD_COLUMNS) {
+        let _ = writeln!(
+            body,
+            "<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"4\" fill=\"#555555\">{}</text>",
+            x + 1.0,
+            TOP - 2.0,
+            FONT,
+            header
+        );
+        x += width;
+    }
+
+    for (row, word) in words.iter().enumerate() {
+        let y = TOP + row as f32 * WORD_ROW;
+        let reading = kana_reading(word.additional.get("reading").map_or("", |r| r.as_str()));
+        let answer = if with_answers { word.value.clone() } else { String::new() };
+        let cells = [
+            (first_number + row + 1).to_string(),
+            word.key.clone(),
+            reading,
+            answer,
+        ];
+
+        let mut x = MARGIN;
+        for (content, width) in cells.iter().zip(WORD_COLUMNS) {
+            let _ = writeln!(
+                body,
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{WORD_ROW}\" fill=\"none\" stroke=\"#999999\" stroke-width=\"0.3\"/>\n\
+                 <text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"4.5\"{}>{}</text>",
+                x + 1.5,
+                y + WORD_ROW * 0.68,
+                FONT,
+                fit_to_cell(content, width - 3.0),
+                escape(content)
+            );
+            x += width;
+        }
+    }
+}
+
+/// Squeezes a text that would run out of its cell, judging by a rough width estimate.
+fn fit_to_cell(content: &str, width: f32) -> String {
+    let estimate = content
+        .chars()
+        .map(|c| match c as u32 {
+            0..0x80 => 2.5,
+            0x3000.. => 4.5,
+            _ => 2.7,
+        })
+        .sum::<f32>();
+    if estimate <= width {
+        return String::new();
+    }
+    format!(" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\"", width)
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
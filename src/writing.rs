@@ -1,19 +1,37 @@
+use crate::handwriting::{recognize, DrawingPad, HandwritingResult};
 use crate::lang::KanaSet;
+use crate::quiz::record_kana_answer;
+use crate::stroke_guide::StrokeGuide;
+use crate::strokes::{kanjivg_dir, text_strokes, Stroke};
 use crate::Page::PreviousPage;
-use crate::{NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
+use crate::{AppState, NavigatedPage, Page, RootMessage, DEFAULT_SPACING};
 use iced::widget::*;
 use iced::{alignment, Element, Fill, Task};
 use rand::seq::SliceRandom;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-#[derive(Clone, Debug)]
+const CELL_SIZE: f32 = 200.0;
+
+#[derive(Clone)]
 pub struct WritingState {
     kana: String,
+    current: String,
     set: Vec<(String, String)>,
     original_set: Vec<(String, String)>,
     kana_total: String,
     roman_total: String,
     next_text: String,
     show_all: bool,
+    show_guide: bool,
+    guide: Option<StrokeGuide>,
+    animating: bool,
+    handwriting: bool,
+    drawn: Vec<Stroke>,
+    reference: Option<(Vec<Stroke>, usize)>,
+    result: Option<HandwritingResult>,
+    shown_at: Instant,
+    state: Arc<Mutex<AppState>>,
 }
 
 impl NavigatedPage<WritingMessage> for WritingState {
@@ -27,17 +45,27 @@ impl NavigatedPage<WritingMessage> for WritingState {
 }
 
 impl WritingState {
-    pub(crate) fn new(set: &KanaSet) -> WritingState {
+    pub(crate) fn new(set: &KanaSet, state: Arc<Mutex<AppState>>) -> WritingState {
         let mut list = set.list();
         list.shuffle(&mut rand::rng());
         WritingState {
             kana: "".to_string(),
+            current: String::new(),
             set: list.clone(),
             original_set: list,
             kana_total: "".to_string(),
             roman_total: "".to_string(),
             next_text: "Дальше".to_string(),
             show_all: false,
+            show_guide: false,
+            guide: None,
+            animating: false,
+            handwriting: false,
+            drawn: vec![],
+            reference: None,
+            result: None,
+            shown_at: Instant::now(),
+            state,
         }
     }
 }
@@ -48,11 +76,92 @@ impl WritingState {
             WritingMessage::Back => todo!(),
             WritingMessage::Next => self.next(),
             WritingMessage::SwitchShowMode(b) => self.show_all = b,
+            WritingMessage::SwitchGuide(b) => {
+                self.show_guide = b;
+                self.replay();
+            }
+            WritingMessage::Replay => self.replay(),
+            WritingMessage::StepBack => {
+                self.animating = false;
+                if let Some(guide) = &mut self.guide {
+                    guide.step_back();
+                }
+            }
+            WritingMessage::StepForward => {
+                self.animating = false;
+                if let Some(guide) = &mut self.guide {
+                    guide.step_forward();
+                }
+            }
+            WritingMessage::SwitchHandwriting(b) => self.handwriting = b,
+            WritingMessage::Drawn(stroke) => {
+                if self.result.is_none() {
+                    self.drawn.push(stroke);
+                }
+            }
+            WritingMessage::UndoStroke => {
+                self.drawn.pop();
+                // the kana can be corrected and checked again; the answer
+                // was already recorded
+                self.result = None;
+            }
+            WritingMessage::ClearPad => {
+                self.drawn.clear();
+                self.result = None;
+            }
+            WritingMessage::CheckWriting => self.check_writing(),
+            WritingMessage::Tick => {
+                if let Some(guide) = &mut self.guide {
+                    guide.advance();
+                    self.animating = !guide.is_finished();
+                }
+            }
         }
         Task::none()
     }
 
+    /// Whether the stroke guide is being animated and needs the clock ticking.
+    pub fn is_animating(&self) -> bool {
+        self.animating && self.show_guide
+    }
+
+    /// Grades the drawing; only the first attempt at a kana goes to the statistics.
+    fn check_writing(&mut self) {
+        let Some((reference, _)) = &self.reference else {
+            return;
+        };
+        if self.drawn.is_empty() || self.result.is_some() {
+            return;
+        }
+
+        let result = recognize(&self.drawn, reference);
+        if !self.current.is_empty() {
+            record_kana_answer(
+                &mut self.state.lock().unwrap(),
+                &self.current,
+                result.is_passed(),
+                self.show_guide,
+                self.shown_at.elapsed(),
+            );
+            self.current.clear();
+        }
+        self.result = Some(result);
+    }
+
+    fn replay(&mut self) {
+        if let Some(guide) = &mut self.guide {
+            guide.replay();
+            self.animating = true;
+        }
+    }
+
     fn next(&mut self) {
+        self.guide = None;
+        self.animating = false;
+        self.current.clear();
+        self.drawn.clear();
+        self.reference = None;
+        self.result = None;
         if self.set.is_empty() {
             self.kana = "".to_string();
             self.roman_total = "".to_string();
@@ -80,17 +189,33 @@ impl WritingState {
             self.kana_total += &*format!("{} ", &current.0).to_string();
             self.roman_total += &*format!("{} ", &current.1.clone()).to_string();
             self.kana = current.1;
+            self.guide = StrokeGuide::new(&current.0);
+            self.reference = text_strokes(&current.0);
+            self.current = current.0;
+            self.shown_at = Instant::now();
+            self.replay();
         }
     }
 
     pub fn view(&self) -> Element<'_, WritingMessage> {
         container(
             iced::widget::column![
-                checkbox(self.show_all)
-                    .label("Показывать все сразу")
-                    .on_toggle(WritingMessage::SwitchShowMode),
+                row![
+                    checkbox(self.show_all)
+                        .label("Показывать все сразу")
+                        .on_toggle(WritingMessage::SwitchShowMode),
+                    checkbox(self.show_guide)
+                        .label("Порядок черт")
+                        .on_toggle(WritingMessage::SwitchGuide),
+                    checkbox(self.handwriting)
+                        .label("Писать на экране")
+                        .on_toggle(WritingMessage::SwitchHandwriting),
+                ]
+                .spacing(DEFAULT_SPACING),
                 text!("{}", self.roman_total).size(34),
                 text!("{}", self.kana).size(48),
+                self.guide(),
+                self.pad(),
                 self.answers(),
                 row![
                     button(text!("{}", self.next_text)).on_press(WritingMessage::Next),
@@ -107,6 +232,79 @@ impl WritingState {
         .into()
     }
 
+    fn guide(&self) -> Element<'_, WritingMessage> {
+        if !self.show_guide {
+            return space().into();
+        }
+        let Some(guide) = &self.guide else {
+            if self.kana.is_empty() {
+                return space().into();
+            }
+            return text!(
+                "Нет порядка черт: положите файлы KanjiVG в {}",
+                kanjivg_dir().display()
+            )
+            .into();
+        };
+
+        iced::widget::column![
+            guide.view(CELL_SIZE),
+            row![
+                button("◀").on_press(WritingMessage::StepBack),
+                text!("{} / {}", guide.current(), guide.count()),
+                button("▶").on_press(WritingMessage::StepForward),
+                button("Повторить").on_press(WritingMessage::Replay),
+            ]
+            .align_y(alignment::Vertical::Center)
+            .spacing(DEFAULT_SPACING),
+        ]
+        .spacing(DEFAULT_SPACING)
+        .align_x(alignment::Horizontal::Center)
+        .into()
+    }
+
+    fn pad(&self) -> Element<'_, WritingMessage> {
+        let Some((reference, cells)) = self.reference.as_ref().filter(|_| self.handwriting) else {
+            return space().into();
+        };
+
+        let pad = canvas(DrawingPad {
+            strokes: &self.drawn,
+            reference: self.result.as_ref().map(|_| reference.as_slice()),
+            verdicts: self.result.as_ref().map(|r| r.verdicts.as_slice()),
+            cells: *cells,
+            on_stroke: WritingMessage::Drawn,
+        })
+        .width(CELL_SIZE * *cells as f32)
+        .height(CELL_SIZE);
+
+        let mut verdicts = Column::new();
+        if let Some(result) = &self.result {
+            verdicts = verdicts.push(text!("Оценка: {}%", result.grade.round()).size(24));
+            for (i, verdict) in result.verdicts.iter().enumerate() {
+                let color = if verdict.is_ok() {
+                    iced::Color::from_rgb8(60, 170, 60)
+                } else {
+                    iced::Color::from_rgb8(255, 79, 0)
+                };
+                verdicts = verdicts.push(text!("{}: {}", i + 1, verdict).color(color));
+            }
+        }
+
+        iced::widget::column![
+            row![pad, verdicts.spacing(5)].spacing(DEFAULT_SPACING),
+            row![
+                button("Проверить").on_press(WritingMessage::CheckWriting),
+                button("Отменить черту").on_press(WritingMessage::UndoStroke),
+                button("Стереть").on_press(WritingMessage::ClearPad),
+            ]
+            .spacing(DEFAULT_SPACING),
+        ]
+        .spacing(DEFAULT_SPACING)
+        .align_x(alignment::Horizontal::Center)
+        .into()
+    }
+
     fn answers(&self) -> Element<'_, WritingMessage> {
         if self.set.is_empty()  {
             text!("{}", self.kana_total).size(36).into()
@@ -120,4 +318,14 @@ pub enum WritingMessage {
     Next,
     Back,
     SwitchShowMode(bool),
+    SwitchGuide(bool),
+    Replay,
+    StepBack,
+    StepForward,
+    Tick,
+    SwitchHandwriting(bool),
+    Drawn(Stroke),
+    UndoStroke,
+    ClearPad,
+    CheckWriting,
 }